@@ -0,0 +1,44 @@
+const { init } = require("../near");
+const { networkOption, doubleCheck } = require("./common");
+
+exports.command = 'apply-upgrade <address>';
+exports.desc = 'Apply the staged contract upgrade';
+exports.builder = yargs => {
+  yargs
+    .positional('address', {
+      describe: 'Contract address to upgrade',
+      type: 'string'
+    })
+    .option('network', networkOption)
+    .option('signer', {
+      describe: 'signer account ID to call apply_upgrade'
+    })
+    .demandOption(['signer'])
+}
+
+exports.handler = async function (argv) {
+  const address = argv.address;
+
+  const near = await init(argv.network);
+  const account = await near.account(argv.signer);
+
+  const staged = await account.viewFunction(address, 'get_staged_upgrade', {});
+  if (!staged) {
+    console.error('No staged upgrade found');
+    return;
+  }
+  console.log(`Applying upgrade for contract ${address}`);
+  console.log(`- Code hash: ${staged.code_hash}`);
+  console.log(`- Code size: ${staged.code_size} bytes`);
+  console.log(`- Activation epoch: ${staged.activation_epoch}`);
+  await doubleCheck();
+
+  await account.functionCall({
+    contractId: address,
+    methodName: 'apply_upgrade',
+    args: {},
+    gas: 300000000000000,
+  });
+
+  console.log('upgraded!');
+}
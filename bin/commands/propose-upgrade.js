@@ -117,11 +117,11 @@ exports.handler = async function (argv) {
 
   const proposalArgs = {
     proposal: {
-      description: `Upgrade linear contract to ${v}`,
+      description: `Stage upgrade of linear contract to ${v}`,
       kind: {
         UpgradeRemote: {
           receiver_id: address,
-          method_name: 'upgrade',
+          method_name: 'stage_upgrade',
           hash: codeHash,
         }
       }
@@ -137,5 +137,5 @@ exports.handler = async function (argv) {
     attachedDeposit: NEAR.parse('0.1')
   })
 
-  console.log('proposed!');
+  console.log('proposed! Once staged, the upgrade could be applied via `apply_upgrade` after the activation epoch.');
 }
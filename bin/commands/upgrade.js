@@ -4,7 +4,7 @@ const { init } = require("../near");
 const { networkOption } = require("./common");
 
 exports.command = 'upgrade <address>';
-exports.desc = 'Upgrade contract';
+exports.desc = 'Stage contract upgrade, which could be applied after the activation epoch';
 exports.builder = yargs => {
   yargs
     .positional('address', {
@@ -25,7 +25,7 @@ exports.builder = yargs => {
 exports.handler = async function (argv) {
   const address = argv.address;
   const code = readFileSync(argv.wasm);
-  console.log(`Upgrading contract ${address}`);
+  console.log(`Staging upgrade for contract ${address}`);
 
   const near = await init(argv.network);
   const account = await near.account(argv.signer);
@@ -35,14 +35,15 @@ exports.handler = async function (argv) {
       receiverId: address,
       actions: [
         nearAPI.transactions.functionCall(
-          'upgrade',
+          'stage_upgrade',
           code,
-          100000000000000,
+          300000000000000,
           "0"
         )
       ]
     }
   );
 
-  console.log('upgraded!');
+  const staged = await account.viewFunction(address, 'get_staged_upgrade', {});
+  console.log('staged!', staged);
 }
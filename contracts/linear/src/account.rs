@@ -4,12 +4,11 @@ use near_sdk::{
     serde::{Deserialize, Serialize},
     AccountId, Balance, EpochHeight,
 };
-use std::collections::HashMap;
 
 use crate::types::*;
 
 /// Inner account data of a delegate.
-#[derive(BorshDeserialize, BorshSerialize, Debug, PartialEq, Eq, Default)]
+#[derive(BorshDeserialize, BorshSerialize, Debug, PartialEq, Eq, Default, Clone)]
 pub struct Account {
     /// The unstaked balance. It represents the amount the account has on this contract that
     /// can either be staked or withdrawn.
@@ -22,12 +21,40 @@ pub struct Account {
     /// The minimum epoch height when the withdrawn is allowed.
     /// This changes after unstaking action, because the amount is still locked for 3 epochs.
     pub unstaked_available_epoch_height: EpochHeight,
-    /// [DEPRECATED] Farmed tokens that can be withdrawn from the farm.
-    #[deprecated(since = "1.6.0", note = "removed staking farm")]
-    pub amounts: HashMap<AccountId, Balance>,
-    /// [DEPRECATED] Last claimed reward for each active farm.
-    #[deprecated(since = "1.6.0", note = "removed staking farm")]
-    pub last_farm_reward_per_share: HashMap<u64, U256>,
+}
+
+/// How to add a new variant for VersionedAccount:
+/// 1. Put the current definition of Account into legacy.rs as `AccountVx_x_x`
+/// 2. Update the current Account struct
+/// 3. Implement `From<AccountVx_x_x> for Account` so that we can migrate
+///    from the previous version of Account to the latest.
+/// 4. Insert a new variant of VersionedAccount just BEFORE `Current(Account)`.
+///    It stands for the previous version of Account, which should be version
+///    that production is using at the time of writing the code.
+///    Due to the fact that Borsh use variant index instead of name to serialize,
+///    the new variant will be deserialzed to the previous version correctly after
+///    new code is deployed.
+/// 5. Update `impl From<VersionedAccount> for Account`, to match the new variant.
+///
+/// NOTE: Accounts saved by v1.6.0 (`AccountV1_6_0`) are not versioned. They are stored
+/// in `legacy_accounts` and moved to `accounts` when saved or migrated.
+#[derive(BorshSerialize, BorshDeserialize)]
+pub enum VersionedAccount {
+    Current(Account),
+}
+
+impl From<Account> for VersionedAccount {
+    fn from(a: Account) -> Self {
+        VersionedAccount::Current(a)
+    }
+}
+
+impl From<VersionedAccount> for Account {
+    fn from(value: VersionedAccount) -> Self {
+        match value {
+            VersionedAccount::Current(a) => a,
+        }
+    }
 }
 
 /// Represents an account structure readable by humans.
@@ -0,0 +1,354 @@
+use crate::events::Event;
+use crate::*;
+use near_sdk::{assert_one_yocto, near_bindgen};
+
+/// The LiNEAR balances of an account at the end of the recent epochs it changed,
+/// which DAOs read as voting power snapshots. At most `MAX_BALANCE_CHECKPOINTS`
+/// are kept, the oldest one is dropped when it's full.
+///
+/// Incoming transfers are recorded as well, so anyone could send 1 yocto LiNEAR
+/// to the account in each epoch to fill up the checkpoints and drop older snapshots.
+/// The holder could pin the epoch of a snapshot, e.g. of a DAO proposal, whose balance
+/// is kept until another epoch is pinned.
+#[derive(BorshSerialize, BorshDeserialize, Default)]
+pub struct BalanceCheckpoints {
+    /// (epoch height, LiNEAR balance at the end of the epoch), ordered by epoch height
+    checkpoints: Vec<(EpochHeight, ShareBalance)>,
+    /// The pinned (epoch height, LiNEAR balance at the end of the epoch), which is never dropped
+    pinned: Option<(EpochHeight, ShareBalance)>,
+}
+
+impl BalanceCheckpoints {
+    /// Record the balance of the current epoch, which overrides the earlier one
+    /// recorded in the same epoch
+    pub fn record(&mut self, epoch_height: EpochHeight, balance: ShareBalance) {
+        match self.checkpoints.last_mut() {
+            Some((last_epoch_height, last_balance)) if *last_epoch_height == epoch_height => {
+                *last_balance = balance;
+            }
+            Some((_, last_balance)) if *last_balance == balance => {}
+            _ => {
+                if self.checkpoints.len() == MAX_BALANCE_CHECKPOINTS {
+                    self.checkpoints.remove(0);
+                }
+                self.checkpoints.push((epoch_height, balance));
+            }
+        }
+    }
+
+    /// The balance at the end of the epoch, `None` if it's before the oldest checkpoint
+    /// and not pinned
+    pub fn balance_at(&self, epoch_height: EpochHeight) -> Option<ShareBalance> {
+        if let Some((pinned_epoch_height, balance)) = self.pinned {
+            if pinned_epoch_height == epoch_height {
+                return Some(balance);
+            }
+        }
+        let index = self
+            .checkpoints
+            .partition_point(|(checkpoint_epoch_height, _)| {
+                *checkpoint_epoch_height <= epoch_height
+            });
+        (index > 0).then(|| self.checkpoints[index - 1].1)
+    }
+
+    /// Keep the balance at the end of the epoch, which replaces the pinned one.
+    /// Returns false if the balance of the epoch is not available.
+    pub fn pin(&mut self, epoch_height: EpochHeight) -> bool {
+        match self.balance_at(epoch_height) {
+            Some(balance) => {
+                self.pinned = Some((epoch_height, balance));
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[near_bindgen]
+impl LiquidStakingContract {
+    /// Opt in to record the LiNEAR balance of the predecessor whenever it changes,
+    /// starting from the current epoch, so `ft_balance_of_at()` works for the account.
+    /// The attached deposit pays for the full `MAX_BALANCE_CHECKPOINTS` checkpoints up front,
+    /// see `internal_charge_feature_storage()`.
+    #[payable]
+    pub fn enable_balance_checkpoints(&mut self) {
+        self.assert_running();
+        let account_id = env::predecessor_account_id();
+        let account = self
+            .internal_find_account(&account_id)
+            .expect(ERR_ACCOUNT_NOT_REGISTERED);
+        require!(
+            !self.internal_has_balance_checkpoints(&account_id),
+            ERR_BALANCE_CHECKPOINTS_ALREADY_ENABLED
+        );
+
+        self.internal_charge_feature_storage(
+            &account_id,
+            self.internal_balance_checkpoints_storage_cost(),
+            ERR_NO_ENOUGH_BALANCE_CHECKPOINTS_STORAGE_DEPOSIT,
+        );
+
+        let mut checkpoints = BalanceCheckpoints::default();
+        checkpoints.record(get_epoch_height(), account.stake_shares);
+        self.balance_checkpoints.insert(&account_id, &checkpoints);
+        self.balance_checkpoints_num += 1;
+
+        Event::EnableBalanceCheckpoints {
+            account_id: &account_id,
+        }
+        .emit();
+    }
+
+    /// Stop recording the LiNEAR balance of the predecessor, the checkpoints are removed
+    /// and the storage is refunded. Requires exactly 1 yocto NEAR.
+    #[payable]
+    pub fn disable_balance_checkpoints(&mut self) {
+        assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+        require!(
+            self.internal_remove_balance_checkpoints(&account_id),
+            ERR_BALANCE_CHECKPOINTS_NOT_ENABLED
+        );
+
+        self.internal_refund_feature_storage(
+            &account_id,
+            self.internal_balance_checkpoints_storage_cost(),
+        );
+
+        Event::DisableBalanceCheckpoints {
+            account_id: &account_id,
+        }
+        .emit();
+    }
+
+    /// Keep the LiNEAR balance of the predecessor at the end of the ended epoch, so the
+    /// snapshot isn't dropped by later checkpoints. Only one epoch is pinned, which is
+    /// replaced by the next pin. Requires exactly 1 yocto NEAR.
+    #[payable]
+    pub fn pin_balance_checkpoint(&mut self, epoch_height: EpochHeight) {
+        assert_one_yocto();
+        require!(
+            epoch_height < get_epoch_height(),
+            ERR_CHECKPOINT_EPOCH_NOT_ENDED
+        );
+        let account_id = env::predecessor_account_id();
+        let mut checkpoints = self
+            .balance_checkpoints
+            .get(&account_id)
+            .expect(ERR_BALANCE_CHECKPOINTS_NOT_ENABLED);
+        require!(
+            checkpoints.pin(epoch_height),
+            ERR_BALANCE_CHECKPOINT_NOT_AVAILABLE
+        );
+        self.balance_checkpoints.insert(&account_id, &checkpoints);
+
+        Event::PinBalanceCheckpoint {
+            account_id: &account_id,
+            epoch_height,
+        }
+        .emit();
+    }
+
+    /// Returns whether the LiNEAR balance of the account is recorded for snapshots
+    pub fn is_balance_checkpoints_enabled(&self, account_id: AccountId) -> bool {
+        self.internal_has_balance_checkpoints(&account_id)
+    }
+
+    /// Returns the LiNEAR balance of the account at the end of the epoch, which should
+    /// have ended since the balance could still change within the current epoch.
+    /// Panics if the account didn't enable checkpoints by then, or the checkpoint
+    /// of the epoch was dropped and not pinned, see `BalanceCheckpoints`.
+    pub fn ft_balance_of_at(&self, account_id: AccountId, epoch_height: EpochHeight) -> U128 {
+        require!(
+            epoch_height < get_epoch_height(),
+            ERR_CHECKPOINT_EPOCH_NOT_ENDED
+        );
+        self.balance_checkpoints
+            .get(&account_id)
+            .expect(ERR_BALANCE_CHECKPOINTS_NOT_ENABLED)
+            .balance_at(epoch_height)
+            .expect(ERR_BALANCE_CHECKPOINT_NOT_AVAILABLE)
+            .into()
+    }
+
+    /// Returns the total LiNEAR supply at the end of the epoch, which should have ended.
+    /// Panics if the epoch is before the oldest kept checkpoint, see
+    /// `MAX_TOTAL_SUPPLY_CHECKPOINTS`.
+    pub fn ft_total_supply_at(&self, epoch_height: EpochHeight) -> U128 {
+        require!(
+            epoch_height < get_epoch_height(),
+            ERR_CHECKPOINT_EPOCH_NOT_ENDED
+        );
+        let checkpoint_epoch_height = self
+            .total_supply_checkpoints
+            .floor_key(&epoch_height)
+            .expect(ERR_BALANCE_CHECKPOINT_NOT_AVAILABLE);
+        self.total_supply_checkpoints
+            .get(&checkpoint_epoch_height)
+            .unwrap()
+            .into()
+    }
+}
+
+impl LiquidStakingContract {
+    /// The storage fee of the full balance checkpoints of one account
+    pub(crate) fn internal_balance_checkpoints_storage_cost(&self) -> Balance {
+        Balance::from(self.balance_checkpoints_storage_usage) * env::storage_byte_cost()
+    }
+
+    pub(crate) fn internal_has_balance_checkpoints(&self, account_id: &AccountId) -> bool {
+        self.balance_checkpoints.contains_key(account_id)
+    }
+
+    /// Record the LiNEAR balance of the account if it enabled checkpoints
+    pub(crate) fn internal_checkpoint_balance(
+        &mut self,
+        account_id: &AccountId,
+        balance: ShareBalance,
+    ) {
+        if let Some(mut checkpoints) = self.balance_checkpoints.get(account_id) {
+            checkpoints.record(get_epoch_height(), balance);
+            self.balance_checkpoints.insert(account_id, &checkpoints);
+        }
+    }
+
+    /// Record the total LiNEAR supply of the current epoch, which should be called
+    /// once wherever `total_share_amount` changes. One checkpoint is kept for each epoch
+    /// in which the supply changed, whose storage is paid by the contract, so only
+    /// the recent `MAX_TOTAL_SUPPLY_CHECKPOINTS` are kept.
+    pub(crate) fn internal_checkpoint_total_supply(&mut self) {
+        self.total_supply_checkpoints
+            .insert(&get_epoch_height(), &self.total_share_amount);
+        if self.total_supply_checkpoints.len() > MAX_TOTAL_SUPPLY_CHECKPOINTS {
+            let oldest_epoch_height = self.total_supply_checkpoints.min().unwrap();
+            self.total_supply_checkpoints.remove(&oldest_epoch_height);
+        }
+    }
+
+    /// Removes the balance checkpoints of the account.
+    /// Returns false if checkpoints are not enabled.
+    pub(crate) fn internal_remove_balance_checkpoints(&mut self, account_id: &AccountId) -> bool {
+        if self.balance_checkpoints.remove(account_id).is_none() {
+            return false;
+        }
+        self.balance_checkpoints_num -= 1;
+        true
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_contract_standards::fungible_token::core::FungibleTokenCore;
+    use near_sdk::test_utils::accounts;
+
+    use super::*;
+    use crate::test_utils::*;
+
+    #[test]
+    fn record_checkpoints() {
+        let mut checkpoints = BalanceCheckpoints::default();
+        checkpoints.record(10, 100);
+        checkpoints.record(10, 200);
+        checkpoints.record(12, 200);
+        checkpoints.record(13, 300);
+        assert_eq!(checkpoints.checkpoints, vec![(10, 200), (13, 300)]);
+        assert_eq!(checkpoints.balance_at(9), None);
+        assert_eq!(checkpoints.balance_at(12), Some(200));
+        assert_eq!(checkpoints.balance_at(20), Some(300));
+
+        // the oldest checkpoint is dropped when it's full
+        for epoch_height in 14..(14 + MAX_BALANCE_CHECKPOINTS as EpochHeight) {
+            checkpoints.record(epoch_height, epoch_height.into());
+        }
+        assert_eq!(checkpoints.checkpoints.len(), MAX_BALANCE_CHECKPOINTS);
+        assert_eq!(checkpoints.balance_at(13), None);
+    }
+
+    #[test]
+    fn pinned_checkpoint_is_kept() {
+        let mut checkpoints = BalanceCheckpoints::default();
+        checkpoints.record(10, 100);
+        checkpoints.record(12, 200);
+        assert!(!checkpoints.pin(9));
+        assert!(checkpoints.pin(11));
+
+        // others fill up the checkpoints with dust transfers
+        for epoch_height in 13..(13 + MAX_BALANCE_CHECKPOINTS as EpochHeight) {
+            checkpoints.record(epoch_height, 200 + Balance::from(epoch_height));
+        }
+        assert_eq!(checkpoints.balance_at(10), None);
+        assert_eq!(checkpoints.balance_at(11), Some(100));
+        assert_eq!(checkpoints.balance_at(12), None);
+    }
+
+    #[test]
+    fn drop_oldest_total_supply_checkpoint() {
+        let mut contract = new_contract();
+        for epoch_height in 11..(11 + MAX_TOTAL_SUPPLY_CHECKPOINTS) {
+            // keep the gas of each checkpoint within the limit of one call
+            set_context(&accounts(1), 0);
+            contract.set_epoch_height(epoch_height);
+            contract.internal_checkpoint_total_supply();
+        }
+        assert_eq!(
+            contract.total_supply_checkpoints.len(),
+            MAX_TOTAL_SUPPLY_CHECKPOINTS
+        );
+        assert_eq!(contract.total_supply_checkpoints.min(), Some(11));
+        assert_eq!(contract.ft_total_supply_at(11).0, 10 * ONE_NEAR);
+    }
+
+    #[test]
+    fn balance_and_supply_at_epoch() {
+        let mut contract = new_contract();
+        let (alice, bob) = (accounts(2), accounts(3));
+        contract.internal_register_account(&bob);
+        set_context(&alice, 5 * ONE_NEAR);
+        contract.deposit_and_stake();
+        set_context(&alice, contract.internal_balance_checkpoints_storage_cost());
+        contract.enable_balance_checkpoints();
+        assert!(contract.is_balance_checkpoints_enabled(alice.clone()));
+
+        contract.set_epoch_height(12);
+        set_context(&alice, 1);
+        contract.ft_transfer(bob.clone(), U128(2 * ONE_NEAR), None);
+        // transfers don't change the supply
+        assert!(contract.total_supply_checkpoints.get(&12).is_none());
+        contract.set_epoch_height(14);
+        set_context(&alice, 0);
+        contract.unstake(U128(ONE_NEAR));
+        contract.set_epoch_height(15);
+
+        assert_eq!(contract.ft_balance_of_at(alice.clone(), 11).0, 5 * ONE_NEAR);
+        assert_eq!(contract.ft_balance_of_at(alice.clone(), 13).0, 3 * ONE_NEAR);
+        assert_eq!(contract.ft_balance_of_at(alice, 14).0, 2 * ONE_NEAR);
+        assert_eq!(contract.ft_total_supply_at(10).0, 15 * ONE_NEAR);
+        assert_eq!(contract.ft_total_supply_at(13).0, 15 * ONE_NEAR);
+        assert_eq!(contract.ft_total_supply_at(14).0, 14 * ONE_NEAR);
+        assert_eq!(contract.ft_total_supply().0, 14 * ONE_NEAR);
+    }
+
+    #[test]
+    #[should_panic(expected = "No balance checkpoint is kept for the epoch")]
+    fn balance_before_enabled() {
+        let mut contract = new_contract();
+        let alice = accounts(2);
+        contract.internal_register_account(&alice);
+        contract.set_epoch_height(12);
+        set_context(&alice, contract.internal_balance_checkpoints_storage_cost());
+        contract.enable_balance_checkpoints();
+        contract.ft_balance_of_at(alice, 11);
+    }
+
+    #[test]
+    #[should_panic(expected = "The epoch height should be before the current epoch")]
+    fn balance_at_current_epoch() {
+        let mut contract = new_contract();
+        let alice = accounts(2);
+        contract.internal_register_account(&alice);
+        set_context(&alice, contract.internal_balance_checkpoints_storage_cost());
+        contract.enable_balance_checkpoints();
+        contract.ft_balance_of_at(alice, get_epoch_height());
+    }
+}
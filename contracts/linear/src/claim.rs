@@ -0,0 +1,319 @@
+use crate::events::Event;
+use crate::*;
+use near_sdk::{assert_one_yocto, is_promise_success, near_bindgen};
+
+#[ext_contract(ext_self_claim_cb)]
+trait ClaimCallbacks {
+    fn claim_callback(&mut self, account_id: AccountId, amount: U128);
+}
+
+#[near_bindgen]
+impl LiquidStakingContract {
+    /// Opt in to receive the unstaked NEAR automatically once it's withdrawable.
+    /// The account is queued on each unstake and paid out by `process_claims()`.
+    /// The attached deposit pays for the queue entry, see `internal_charge_feature_storage()`.
+    #[payable]
+    pub fn enable_auto_claim(&mut self) {
+        self.assert_running();
+        let account_id = env::predecessor_account_id();
+        let account = self
+            .internal_find_account(&account_id)
+            .expect(ERR_ACCOUNT_NOT_REGISTERED);
+        self.assert_not_omnibus(&account_id);
+        require!(
+            !self.auto_claim_accounts.contains(&account_id),
+            ERR_AUTO_CLAIM_ALREADY_ENABLED
+        );
+
+        self.internal_charge_feature_storage(
+            &account_id,
+            self.internal_auto_claim_storage_cost(),
+            ERR_NO_ENOUGH_AUTO_CLAIM_STORAGE_DEPOSIT,
+        );
+
+        self.auto_claim_accounts.insert(&account_id);
+        self.auto_claim_accounts_num += 1;
+        // the NEAR unstaked before is claimed as well
+        if account.unstaked > 0 {
+            self.claim_queue.insert(
+                &(account.unstaked_available_epoch_height, account_id.clone()),
+                &(),
+            );
+        }
+
+        Event::EnableAutoClaim {
+            account_id: &account_id,
+        }
+        .emit();
+    }
+
+    /// Opt out of receiving the unstaked NEAR automatically and refund the storage.
+    /// Requires exactly 1 yocto NEAR.
+    #[payable]
+    pub fn disable_auto_claim(&mut self) {
+        assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+        require!(
+            self.internal_remove_auto_claim(&account_id),
+            ERR_AUTO_CLAIM_NOT_ENABLED
+        );
+
+        self.internal_refund_feature_storage(&account_id, self.internal_auto_claim_storage_cost());
+
+        Event::DisableAutoClaim {
+            account_id: &account_id,
+        }
+        .emit();
+    }
+
+    /// Transfer the withdrawable unstaked NEAR of the queued accounts
+    /// in the order of `unstaked_available_epoch_height`. It could be called by anyone,
+    /// e.g. a keeper after `epoch_withdraw()`. The whole unstaked balance of an account
+    /// is transferred. Accounts the contract doesn't have enough balance for yet are
+    /// skipped and left in the queue, so they don't block the smaller claims behind them.
+    /// Returns the number of processed queue entries, which is at most `limit`.
+    /// Skipped entries don't count toward `limit`.
+    pub fn process_claims(&mut self, limit: u32) -> u32 {
+        self.assert_running();
+        require!(limit > 0, ERR_NON_POSITIVE_CLAIM_LIMIT);
+
+        let mut processed = 0;
+        let mut next_key = self.claim_queue.min();
+        while processed < limit {
+            let key = match next_key {
+                Some(key) if key.0 <= get_epoch_height() => key,
+                _ => break,
+            };
+            next_key = self.claim_queue.higher(&key);
+            let (_, account_id) = &key;
+            // accounts that withdrew, unregistered, became omnibus or don't allow
+            // withdrawals to themselves are dropped from the queue
+            let amount = match self.internal_find_account(account_id) {
+                Some(account)
+                    if account.unstaked_available_epoch_height <= get_epoch_height()
+                        && !self.internal_is_omnibus(account_id)
+                        && self.internal_is_allowed_receiver(account_id, account_id) =>
+                {
+                    account.unstaked
+                }
+                _ => 0,
+            };
+            if amount > self.internal_contract_withdrawable_balance() {
+                continue;
+            }
+            self.claim_queue.remove(&key);
+            processed += 1;
+            if amount == 0 {
+                continue;
+            }
+
+            self.internal_withdraw(account_id, None, account_id, amount)
+                .then(ext_self_claim_cb::claim_callback(
+                    account_id.clone(),
+                    amount.into(),
+                    env::current_account_id(),
+                    NO_DEPOSIT,
+                    GAS_CB_CLAIM,
+                ));
+            Event::Claim {
+                account_id,
+                amount: &U128(amount),
+            }
+            .emit();
+        }
+        processed
+    }
+
+    /// Restores the unstaked balance of the account if the claim transfer failed,
+    /// and queues the account again to be claimed by a later `process_claims()`.
+    #[private]
+    pub fn claim_callback(&mut self, account_id: AccountId, amount: U128) {
+        if is_promise_success() {
+            return;
+        }
+
+        // the refund of the failed transfer goes back to this contract
+        let new_unstaked_balance = self.internal_restore_withdraw(&account_id, None, amount.0);
+
+        Event::ClaimFailed {
+            account_id: &account_id,
+            amount: &amount,
+            new_unstaked_balance: &U128(new_unstaked_balance),
+        }
+        .emit();
+    }
+
+    /// Returns whether the account receives the unstaked NEAR automatically
+    pub fn is_auto_claim_enabled(&self, account_id: AccountId) -> bool {
+        self.auto_claim_accounts.contains(&account_id)
+    }
+
+    /// Returns the earliest epoch height at which queued unstaked NEAR becomes claimable,
+    /// which tells keepers when `process_claims()` is worth calling
+    pub fn get_next_claim_epoch_height(&self) -> Option<EpochHeight> {
+        self.claim_queue.min().map(|(epoch_height, _)| epoch_height)
+    }
+}
+
+impl LiquidStakingContract {
+    /// The storage fee of the auto-claim flag and queue entry of one account
+    pub(crate) fn internal_auto_claim_storage_cost(&self) -> Balance {
+        Balance::from(self.auto_claim_storage_usage) * env::storage_byte_cost()
+    }
+
+    pub(crate) fn internal_has_auto_claim(&self, account_id: &AccountId) -> bool {
+        self.auto_claim_accounts.contains(account_id)
+    }
+
+    /// Move the queue entry of the account to the new epoch height after unstaking
+    pub(crate) fn internal_queue_claim(
+        &mut self,
+        account_id: &AccountId,
+        prev_epoch_height: EpochHeight,
+        epoch_height: EpochHeight,
+    ) {
+        if !self.internal_has_auto_claim(account_id) {
+            return;
+        }
+        self.claim_queue
+            .remove(&(prev_epoch_height, account_id.clone()));
+        self.claim_queue
+            .insert(&(epoch_height, account_id.clone()), &());
+    }
+
+    /// Removes the auto-claim flag and the queue entry of the account.
+    /// Returns false if auto-claim is not enabled.
+    pub(crate) fn internal_remove_auto_claim(&mut self, account_id: &AccountId) -> bool {
+        if !self.auto_claim_accounts.remove(account_id) {
+            return false;
+        }
+        self.auto_claim_accounts_num -= 1;
+        let account = self.internal_get_account(account_id);
+        self.claim_queue
+            .remove(&(account.unstaked_available_epoch_height, account_id.clone()));
+        true
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::{accounts, get_logs};
+    use near_sdk::{testing_env, PromiseResult, RuntimeFeesConfig, VMConfig};
+
+    use super::*;
+    use crate::test_utils::*;
+
+    #[test]
+    fn claim_matured_unstaked_balance() {
+        let mut contract = new_contract();
+        let (alice, bob) = (accounts(2), accounts(3));
+        let storage_cost = contract.internal_auto_claim_storage_cost();
+        for account_id in [&alice, &bob] {
+            set_context(account_id, 5 * ONE_NEAR);
+            contract.deposit_and_stake();
+        }
+        set_context(&alice, storage_cost);
+        contract.enable_auto_claim();
+        assert_eq!(contract.internal_get_storage_deposit(&alice), storage_cost);
+
+        // only the accounts that opted in are queued
+        for account_id in [&alice, &bob] {
+            set_context(account_id, 0);
+            contract.unstake(U128(2 * ONE_NEAR));
+        }
+        let epoch_height = contract
+            .internal_get_account(&alice)
+            .unstaked_available_epoch_height;
+        assert_eq!(contract.get_next_claim_epoch_height(), Some(epoch_height));
+        assert_eq!(contract.process_claims(10), 0);
+
+        contract.set_epoch_height(epoch_height);
+        assert_eq!(contract.process_claims(10), 1);
+        assert_eq!(contract.internal_get_account(&alice).unstaked, 0);
+        assert_eq!(contract.internal_get_account(&bob).unstaked, 2 * ONE_NEAR);
+        assert_eq!(contract.get_next_claim_epoch_height(), None);
+        assert!(get_logs()
+            .iter()
+            .any(|log| log.contains(r#""event":"claim""#)));
+    }
+
+    #[test]
+    fn claim_exceeding_balance_does_not_block_queue() {
+        let mut contract = new_contract();
+        let (alice, bob) = (accounts(2), accounts(3));
+        for (account_id, amount) in [(&alice, 40 * ONE_NEAR), (&bob, 5 * ONE_NEAR)] {
+            set_context(account_id, amount);
+            contract.deposit_and_stake();
+            set_context(account_id, contract.internal_auto_claim_storage_cost());
+            contract.enable_auto_claim();
+            set_context(account_id, 0);
+            contract.unstake(U128(amount - ONE_NEAR));
+        }
+
+        // alice is queued first but the contract can't pay her yet,
+        // which doesn't use up the limit
+        let epoch_height = contract
+            .internal_get_account(&alice)
+            .unstaked_available_epoch_height;
+        contract.set_epoch_height(epoch_height);
+        assert_eq!(contract.process_claims(1), 1);
+        assert_eq!(
+            contract.internal_get_account(&alice).unstaked,
+            39 * ONE_NEAR
+        );
+        assert_eq!(contract.internal_get_account(&bob).unstaked, 0);
+        assert_eq!(contract.get_next_claim_epoch_height(), Some(epoch_height));
+    }
+
+    #[test]
+    fn failed_claim_is_queued_again() {
+        let mut contract = new_contract();
+        let alice = accounts(2);
+        set_context(&alice, 5 * ONE_NEAR);
+        contract.deposit_and_stake();
+        set_context(&alice, contract.internal_auto_claim_storage_cost());
+        contract.enable_auto_claim();
+        set_context(&alice, 0);
+        contract.unstake(U128(2 * ONE_NEAR));
+        let epoch_height = contract
+            .internal_get_account(&alice)
+            .unstaked_available_epoch_height;
+        contract.set_epoch_height(epoch_height);
+        assert_eq!(contract.process_claims(10), 1);
+        assert_eq!(contract.get_next_claim_epoch_height(), None);
+
+        testing_env!(
+            get_context(&accounts(0), 0).build(),
+            VMConfig::test(),
+            RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Failed]
+        );
+        contract.claim_callback(alice.clone(), U128(2 * ONE_NEAR));
+        assert_eq!(contract.internal_get_account(&alice).unstaked, 2 * ONE_NEAR);
+        assert_eq!(contract.get_next_claim_epoch_height(), Some(epoch_height));
+    }
+
+    #[test]
+    fn disable_auto_claim_dequeues_account() {
+        let mut contract = new_contract();
+        let alice = accounts(2);
+        set_context(&alice, 5 * ONE_NEAR);
+        contract.deposit_and_stake();
+        set_context(&alice, 0);
+        contract.unstake(U128(ONE_NEAR));
+
+        // the pending unstaked balance is queued when enabling
+        set_context(&alice, contract.internal_auto_claim_storage_cost());
+        contract.enable_auto_claim();
+        assert!(contract.is_auto_claim_enabled(alice.clone()));
+        assert!(contract.get_next_claim_epoch_height().is_some());
+
+        set_context(&alice, 1);
+        contract.disable_auto_claim();
+        assert!(!contract.is_auto_claim_enabled(alice.clone()));
+        assert_eq!(contract.get_next_claim_epoch_height(), None);
+        assert_eq!(contract.internal_get_storage_deposit(&alice), 0);
+        assert_eq!(contract.auto_claim_accounts_num, 0);
+    }
+}
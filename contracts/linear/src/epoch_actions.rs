@@ -219,6 +219,9 @@ impl LiquidStakingContract {
     /// Cleaning up stake requirements and unstake requirements,
     /// since some stake requirements could be eliminated if
     /// there are more unstake requirements, and vice versa.
+    /// The liquidity reserve is netted against the stake requirements as well,
+    /// the reserve above the target is staked and the reserve below the target
+    /// is filled up from the amount to stake.
     fn epoch_cleanup(&mut self) {
         if self.last_settlement_epoch == get_epoch_height() {
             return;
@@ -230,6 +233,13 @@ impl LiquidStakingContract {
         self.unstake_amount_to_settle += self.epoch_requested_unstake_amount;
         self.epoch_requested_stake_amount = 0;
         self.epoch_requested_unstake_amount = 0;
+        self.epoch_reserve_unstake_amount = 0;
+
+        let reserve_target = self.internal_liquidity_reserve_target();
+        if self.liquidity_reserve_amount > reserve_target {
+            self.stake_amount_to_settle += self.liquidity_reserve_amount - reserve_target;
+            self.liquidity_reserve_amount = reserve_target;
+        }
 
         if self.stake_amount_to_settle > self.unstake_amount_to_settle {
             self.stake_amount_to_settle -= self.unstake_amount_to_settle;
@@ -239,9 +249,17 @@ impl LiquidStakingContract {
             self.stake_amount_to_settle = 0;
         }
 
+        let reserve_shortfall = std::cmp::min(
+            reserve_target - self.liquidity_reserve_amount,
+            self.stake_amount_to_settle,
+        );
+        self.liquidity_reserve_amount += reserve_shortfall;
+        self.stake_amount_to_settle -= reserve_shortfall;
+
         Event::EpochCleanup {
             stake_amount_to_settle: &U128(self.stake_amount_to_settle),
             unstake_amount_to_settle: &U128(self.unstake_amount_to_settle),
+            liquidity_reserve_amount: &U128(self.liquidity_reserve_amount),
         }
         .emit();
     }
@@ -520,3 +538,111 @@ impl LiquidStakingContract {
         }
     }
 }
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::accounts;
+
+    use super::*;
+    use crate::test_utils::*;
+
+    #[test]
+    fn liquidity_reserve_serves_unstake() {
+        let mut contract = new_contract();
+        contract.set_liquidity_reserve_bps(2000);
+        let alice = accounts(2);
+        set_context(&alice, 10 * ONE_NEAR);
+        contract.deposit_and_stake();
+
+        // 20% of the total staked amount is kept from the amount to stake
+        contract.set_epoch_height(11);
+        contract.epoch_cleanup();
+        assert_eq!(contract.liquidity_reserve_amount, 4 * ONE_NEAR);
+        assert_eq!(contract.stake_amount_to_settle, 16 * ONE_NEAR);
+        assert!(contract.internal_check_invariants().valid);
+
+        // the unstake covered by the reserve could be withdrawn immediately
+        set_context(&alice, 0);
+        assert!(
+            contract
+                .preview_unstake(U128(3 * ONE_NEAR), None)
+                .from_liquidity_reserve
+        );
+        contract.unstake(U128(3 * ONE_NEAR));
+        let account = contract.internal_get_account(&alice);
+        assert_eq!(account.unstaked_available_epoch_height, 11);
+        assert_eq!(contract.liquidity_reserve_amount, ONE_NEAR);
+        assert_eq!(contract.epoch_requested_unstake_amount, 0);
+        assert_eq!(contract.epoch_reserve_unstake_amount, 3 * ONE_NEAR);
+        assert!(contract.internal_check_invariants().valid);
+
+        // the unstake exceeding the reserve waits for validators
+        contract.unstake(U128(2 * ONE_NEAR));
+        let account = contract.internal_get_account(&alice);
+        assert!(account.unstaked_available_epoch_height > 11);
+        assert_eq!(contract.liquidity_reserve_amount, ONE_NEAR);
+        assert_eq!(contract.epoch_requested_unstake_amount, 2 * ONE_NEAR);
+
+        // the pending unlock of the account is not released by a reserve-served unstake
+        let preview = contract.preview_unstake(U128(ONE_NEAR), Some(alice.clone()));
+        assert!(preview.from_liquidity_reserve);
+        assert_eq!(
+            preview.unstaked_available_epoch_height,
+            account.unstaked_available_epoch_height
+        );
+        assert_eq!(
+            contract
+                .preview_unstake(U128(ONE_NEAR), None)
+                .unstaked_available_epoch_height,
+            11
+        );
+
+        // the reserve above the target is netted against the amount to unstake
+        set_context(&accounts(1), 0);
+        contract.set_liquidity_reserve_bps(0);
+        contract.set_epoch_height(12);
+        contract.epoch_cleanup();
+        assert_eq!(contract.liquidity_reserve_amount, 0);
+        assert_eq!(contract.stake_amount_to_settle, 15 * ONE_NEAR);
+        assert_eq!(contract.unstake_amount_to_settle, 0);
+        assert!(contract.internal_check_invariants().valid);
+        assert_eq!(contract.get_summary().liquidity_reserve_amount.0, 0);
+        assert_eq!(contract.epoch_reserve_unstake_amount, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "The unstaked amount in this epoch would exceed the maximum")]
+    fn liquidity_reserve_counts_toward_unstake_limit() {
+        let mut contract = new_contract();
+        contract.set_liquidity_reserve_bps(2000);
+        contract.set_stake_limits(None, None, Some(U128(3 * ONE_NEAR)));
+        let alice = accounts(2);
+        set_context(&alice, 10 * ONE_NEAR);
+        contract.deposit_and_stake();
+        contract.set_epoch_height(11);
+        contract.epoch_cleanup();
+
+        set_context(&alice, 0);
+        contract.unstake(U128(2 * ONE_NEAR));
+        contract.unstake(U128(2 * ONE_NEAR));
+    }
+
+    #[test]
+    fn liquidity_reserve_lowers_rewards() {
+        let mut contract = new_contract();
+        contract.set_liquidity_reserve_bps(2000);
+        contract.set_epoch_height(11);
+        contract.epoch_cleanup();
+        assert_eq!(contract.liquidity_reserve_amount, 2 * ONE_NEAR);
+
+        // only the NEAR out of the reserve is staked and earns 10% rewards
+        let validator_id = accounts(2);
+        let mut validator = contract.validator_pool.add_validator(&validator_id, 10);
+        validator.on_stake_success(&mut contract.validator_pool, 8 * ONE_NEAR);
+        set_context(&accounts(0), 0);
+        contract.validator_get_balance_callback(validator_id, Ok(U128(8 * ONE_NEAR * 11 / 10)));
+
+        // the LiNEAR price only increases 8% since the reserve is counted in it
+        assert_eq!(contract.ft_price().0, ONE_NEAR * 108 / 100);
+    }
+}
@@ -11,12 +11,81 @@ pub const ERR_PAUSED: &str = "The contract is paused now. Please try later";
 pub const ERR_ALREADY_PAUSED: &str = "The contract is already paused";
 pub const ERR_NOT_PAUSED: &str = "The contract is not paused yet";
 
+// invariants
+pub const ERR_INVARIANTS_VIOLATED: &str = "Contract invariants are violated";
+
+// upgrade
+pub const ERR_NO_STAGED_UPGRADE: &str = "There is no staged upgrade";
+pub const ERR_UPGRADE_ALREADY_STAGED: &str =
+    "There is already a staged upgrade. Cancel it before staging a new one";
+pub const ERR_EMPTY_UPGRADE_CODE: &str = "The upgrade code cannot be empty";
+pub const ERR_UPGRADE_NOT_ACTIVATED: &str = "The staged upgrade is not activated yet";
+pub const ERR_UPGRADE_CODE_HASH_MISMATCH: &str =
+    "The staged code doesn't match the staged code hash";
+pub const ERR_NO_ENOUGH_UPGRADE_STORAGE_DEPOSIT: &str =
+    "The attached deposit is less than the storage cost of the staged code";
+
+// migration
+pub const ERR_MIGRATION_IN_PROGRESS: &str = "State migration is in progress. Please try later";
+pub const ERR_NO_MIGRATION_IN_PROGRESS: &str = "There is no migration in progress";
+pub const ERR_NON_POSITIVE_MIGRATION_LIMIT: &str = "Migration limit should be positive";
+
 // manager
 pub const ERR_NOT_MANAGER: &str = "Only manager can perform this action";
 
 // account
-#[allow(dead_code)]
 pub const ERR_UNREGISTER_POSITIVE_UNSTAKED: &str = "Cannot delete the account because the unstaked amount is not empty. Withdraw your balance first.";
+pub const ERR_UNREGISTER_POSITIVE_BALANCE: &str =
+    "Can't unregister the account with the positive balance without force";
+pub const ERR_ACCOUNT_NOT_REGISTERED: &str = "The account is not registered";
+
+// omnibus accounts
+pub const ERR_OMNIBUS_ACCOUNT: &str =
+    "The account is an omnibus account, which can only be operated via sub-accounts";
+pub const ERR_NOT_OMNIBUS_ACCOUNT: &str = "The account is not an omnibus account";
+pub const ERR_SUB_ACCOUNTS_ALREADY_ENABLED: &str =
+    "Sub-accounts are already enabled for the account";
+pub const ERR_OMNIBUS_POSITIVE_BALANCE: &str =
+    "Sub-accounts can only be enabled for accounts without balance";
+pub const ERR_OMNIBUS_HAS_SUB_ACCOUNTS: &str = "The omnibus account still has sub-accounts";
+pub const ERR_OMNIBUS_BENEFICIARY: &str = "Beneficiaries cannot be omnibus accounts";
+pub const ERR_INVALID_SUB_ACCOUNT_ID: &str = "The sub-account ID should have 1 to 64 bytes";
+pub const ERR_SUB_ACCOUNT_NOT_EXIST: &str = "The sub-account doesn't exist";
+pub const ERR_SELF_SUB_ACCOUNT_TRANSFER: &str =
+    "Sender and receiver sub-accounts should be different";
+pub const ERR_NON_POSITIVE_TRANSFER_AMOUNT: &str = "The amount should be a positive number";
+pub const ERR_NO_ENOUGH_SUB_ACCOUNT_BALANCE: &str =
+    "The sub-account doesn't have enough LiNEAR balance";
+pub const ERR_NO_ENOUGH_OMNIBUS_STORAGE_DEPOSIT: &str =
+    "The attached deposit is less than the storage cost of the omnibus account";
+pub const ERR_NO_ENOUGH_SUB_ACCOUNT_STORAGE_DEPOSIT: &str =
+    "The attached deposit is less than the storage cost of the sub-account";
+pub const ERR_NO_ENOUGH_SUB_ACCOUNT_STORAGE_BALANCE: &str =
+    "The available storage balance of the omnibus account is less than the storage cost of the sub-account";
+pub const ERR_NO_RECEIVER_SUB_ACCOUNT: &str =
+    "Transfers to an omnibus account should name the receiver sub-account in the memo";
+
+// account stats
+pub const ERR_ACCOUNT_STATS_ALREADY_ENABLED: &str = "The account stats are already enabled";
+pub const ERR_ACCOUNT_STATS_NOT_ENABLED: &str = "The account stats are not enabled";
+pub const ERR_NO_ENOUGH_ACCOUNT_STATS_STORAGE_DEPOSIT: &str =
+    "The attached deposit is less than the storage cost of the account stats";
+
+// transfer call
+pub const ERR_NOT_SELF_TRANSFER_CALL: &str =
+    "Only LiNEAR or wNEAR transferred to this contract can be received";
+pub const ERR_INVALID_TRANSFER_CALL_MSG: &str = "Invalid transfer call message";
+pub const ERR_UNSTAKE_DELAYS_RECEIVER: &str =
+    "The unstake would delay the withdrawal of the unstaked NEAR of the receiver";
+
+// allowance
+pub const ERR_SELF_ALLOWANCE: &str = "Owner and spender should be different";
+pub const ERR_NO_ALLOWANCE: &str = "The allowance doesn't exist";
+pub const ERR_NO_ENOUGH_ALLOWANCE: &str = "The spender doesn't have enough allowance";
+pub const ERR_ALLOWANCE_OVERFLOW: &str = "Allowance overflow";
+pub const ERR_DECREASE_ALLOWANCE_BELOW_ZERO: &str = "Cannot decrease the allowance below zero";
+pub const ERR_NO_ENOUGH_ALLOWANCE_STORAGE_DEPOSIT: &str =
+    "The attached deposit is less than the storage cost of the allowance";
 
 // fraction
 pub const ERR_FRACTION_BAD_DENOMINATOR: &str = "Denominator cannot be zero";
@@ -42,6 +111,64 @@ pub const ERR_NON_POSITIVE_TOTAL_STAKED_BALANCE: &str = "The total staked balanc
 pub const ERR_NON_POSITIVE_TOTAL_STAKE_SHARES: &str = "The total number of stake shares can't be 0";
 pub const ERR_CONTRACT_NO_STAKED_BALANCE: &str = "Invariant violation. The calculated number of \"stake\" shares for unstaking should be positive";
 
+// stake limits
+pub const ERR_EXCEED_MAX_TOTAL_STAKED_AMOUNT: &str =
+    "The total staked amount would exceed the maximum. Please try a smaller amount";
+pub const ERR_EXCEED_MAX_EPOCH_STAKE_AMOUNT: &str =
+    "The staked amount in this epoch would exceed the maximum. Please try later";
+pub const ERR_EXCEED_MAX_EPOCH_UNSTAKE_AMOUNT: &str =
+    "The unstaked amount in this epoch would exceed the maximum. Please try later";
+pub const ERR_DEPOSIT_AMOUNT_TOO_SMALL: &str = "The deposit amount is less than the minimum";
+pub const ERR_STAKE_AMOUNT_TOO_SMALL: &str = "The stake amount is less than the minimum";
+pub const ERR_UNSTAKE_AMOUNT_TOO_SMALL: &str =
+    "The unstake amount is less than the minimum. Unstake all instead";
+
+// auto-claim
+pub const ERR_AUTO_CLAIM_ALREADY_ENABLED: &str = "Auto-claim is already enabled for the account";
+pub const ERR_AUTO_CLAIM_NOT_ENABLED: &str = "Auto-claim is not enabled for the account";
+pub const ERR_NO_ENOUGH_AUTO_CLAIM_STORAGE_DEPOSIT: &str =
+    "The attached deposit is less than the storage cost of auto-claim";
+pub const ERR_NON_POSITIVE_CLAIM_LIMIT: &str = "Claim limit should be positive";
+
+// lock schedule
+pub const ERR_LOCK_SCHEDULE_ALREADY_EXISTS: &str = "The account already has a lock schedule";
+pub const ERR_LOCK_SCHEDULE_NOT_EXIST: &str = "The account doesn't have a lock schedule";
+pub const ERR_INVALID_LOCK_SCHEDULE: &str =
+    "The lock schedule should satisfy start <= cliff <= end and start < end";
+pub const ERR_SHARES_LOCKED: &str = "The LiNEAR balance is locked by the lock schedule";
+
+// withdrawal allowlist
+pub const ERR_WITHDRAWAL_ALLOWLIST_ALREADY_ENABLED: &str =
+    "The withdrawal allowlist is already enabled for the account";
+pub const ERR_WITHDRAWAL_ALLOWLIST_NOT_ENABLED: &str =
+    "The withdrawal allowlist is not enabled for the account";
+pub const ERR_NO_ENOUGH_WITHDRAWAL_ALLOWLIST_STORAGE_DEPOSIT: &str =
+    "The attached deposit is less than the storage cost of the withdrawal allowlist";
+pub const ERR_INVALID_WITHDRAWAL_ALLOWLIST: &str =
+    "The allowlist should have 1 to 10 distinct accounts";
+pub const ERR_NO_WITHDRAWAL_ALLOWLIST_CHANGE: &str =
+    "There is no pending change of the withdrawal allowlist";
+pub const ERR_WITHDRAWAL_ALLOWLIST_CHANGE_NOT_EFFECTIVE: &str =
+    "The change of the withdrawal allowlist is not effective yet";
+pub const ERR_RECEIVER_NOT_ALLOWLISTED: &str =
+    "The receiver is not in the withdrawal allowlist of the account";
+
+// balance checkpoints
+pub const ERR_BALANCE_CHECKPOINTS_ALREADY_ENABLED: &str =
+    "Balance checkpoints are already enabled for the account";
+pub const ERR_BALANCE_CHECKPOINTS_NOT_ENABLED: &str =
+    "Balance checkpoints are not enabled for the account";
+pub const ERR_NO_ENOUGH_BALANCE_CHECKPOINTS_STORAGE_DEPOSIT: &str =
+    "The attached deposit is less than the storage cost of balance checkpoints";
+pub const ERR_CHECKPOINT_EPOCH_NOT_ENDED: &str =
+    "The epoch height should be before the current epoch";
+pub const ERR_BALANCE_CHECKPOINT_NOT_AVAILABLE: &str =
+    "No balance checkpoint is kept for the epoch";
+
+// liquidity reserve
+pub const ERR_INVALID_LIQUIDITY_RESERVE_BPS: &str =
+    "The liquidity reserve bps should not exceed 10000";
+
 // drain operations
 pub const ERR_NON_ZERO_WEIGHT: &str = "Validator weight must be zero for drain operation";
 pub const ERR_NON_ZERO_BASE_STAKE_AMOUNT: &str =
@@ -56,6 +183,14 @@ pub const ERR_NOT_IN_DRAINING: &str =
 // deposit
 pub const ERR_NON_POSITIVE_DEPOSIT_AMOUNT: &str = "Deposit amount should be positive";
 
+// donation
+pub const ERR_DONATION_AMOUNT_TOO_SMALL: &str = "The donation amount is less than the minimum";
+
+// burn to pool
+pub const ERR_NON_POSITIVE_BURN_AMOUNT: &str = "The burn amount should be positive";
+pub const ERR_BURN_TOO_MANY_SHARES: &str =
+    "Cannot burn more than half of the total LiNEAR supply at once";
+
 // withdraw
 pub const ERR_NON_POSITIVE_WITHDRAWAL_AMOUNT: &str = "Withdrawal amount should be positive";
 pub const ERR_NO_ENOUGH_UNSTAKED_BALANCE_TO_WITHDRAW: &str =
@@ -1,4 +1,12 @@
-use near_sdk::{json_types::U128, log, serde::Serialize, serde_json::json, AccountId};
+use near_sdk::{
+    json_types::{Base58CryptoHash, U128, U64},
+    log,
+    serde::Serialize,
+    serde_json::json,
+    AccountId,
+};
+
+use crate::types::SubAccountId;
 
 const EVENT_STANDARD: &str = "linear";
 const EVENT_STANDARD_VERSION: &str = "1.0.1";
@@ -54,6 +62,7 @@ pub enum Event<'a> {
     EpochCleanup {
         stake_amount_to_settle: &'a U128,
         unstake_amount_to_settle: &'a U128,
+        liquidity_reserve_amount: &'a U128,
     },
     // Drain Operations
     DrainUnstakeAttempt {
@@ -108,16 +117,45 @@ pub enum Event<'a> {
     // Staking Pool Interface
     Deposit {
         account_id: &'a AccountId,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        sub_account_id: Option<&'a SubAccountId>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        payer_id: Option<&'a AccountId>,
         amount: &'a U128,
         new_unstaked_balance: &'a U128,
     },
     Withdraw {
         account_id: &'a AccountId,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        sub_account_id: Option<&'a SubAccountId>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        receiver_id: Option<&'a AccountId>,
         amount: &'a U128,
         new_unstaked_balance: &'a U128,
     },
+    WithdrawToFailed {
+        account_id: &'a AccountId,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        sub_account_id: Option<&'a SubAccountId>,
+        receiver_id: &'a AccountId,
+        amount: &'a U128,
+        new_unstaked_balance: &'a U128,
+    },
+    Donation {
+        account_id: &'a AccountId,
+        amount: &'a U128,
+    },
+    BurnToPool {
+        account_id: &'a AccountId,
+        burnt_stake_shares: &'a U128,
+        new_total_share_amount: &'a U128,
+    },
     Stake {
         account_id: &'a AccountId,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        sub_account_id: Option<&'a SubAccountId>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        payer_id: Option<&'a AccountId>,
         staked_amount: &'a U128,
         minted_stake_shares: &'a U128,
         new_unstaked_balance: &'a U128,
@@ -125,6 +163,8 @@ pub enum Event<'a> {
     },
     Unstake {
         account_id: &'a AccountId,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        sub_account_id: Option<&'a SubAccountId>,
         unstaked_amount: &'a U128,
         burnt_stake_shares: &'a U128,
         new_unstaked_balance: &'a U128,
@@ -173,8 +213,151 @@ pub enum Event<'a> {
     SetWhitelist {
         account_id: &'a AccountId,
     },
+    SetWnear {
+        account_id: &'a AccountId,
+    },
+    SetStakeLimits {
+        max_total_staked_near_amount: Option<&'a U128>,
+        max_epoch_stake_amount: Option<&'a U128>,
+        max_epoch_unstake_amount: Option<&'a U128>,
+    },
+    // Auto-claim
+    EnableAutoClaim {
+        account_id: &'a AccountId,
+    },
+    DisableAutoClaim {
+        account_id: &'a AccountId,
+    },
+    Claim {
+        account_id: &'a AccountId,
+        amount: &'a U128,
+    },
+    ClaimFailed {
+        account_id: &'a AccountId,
+        amount: &'a U128,
+        new_unstaked_balance: &'a U128,
+    },
+    // Lock schedule
+    CreateLockSchedule {
+        account_id: &'a AccountId,
+        amount: &'a U128,
+        start_timestamp: &'a U64,
+        cliff_timestamp: &'a U64,
+        end_timestamp: &'a U64,
+    },
+    TerminateLockSchedule {
+        account_id: &'a AccountId,
+        returned_shares: &'a U128,
+    },
+    // Withdrawal allowlist
+    EnableWithdrawalAllowlist {
+        account_id: &'a AccountId,
+        allowlist: &'a Vec<AccountId>,
+    },
+    RequestWithdrawalAllowlistChange {
+        account_id: &'a AccountId,
+        allowlist: Option<&'a Vec<AccountId>>,
+        effective_epoch_height: &'a u64,
+    },
+    CancelWithdrawalAllowlistChange {
+        account_id: &'a AccountId,
+    },
+    ApplyWithdrawalAllowlistChange {
+        account_id: &'a AccountId,
+        allowlist: Option<&'a Vec<AccountId>>,
+    },
+    // Balance checkpoints
+    EnableBalanceCheckpoints {
+        account_id: &'a AccountId,
+    },
+    DisableBalanceCheckpoints {
+        account_id: &'a AccountId,
+    },
+    PinBalanceCheckpoint {
+        account_id: &'a AccountId,
+        epoch_height: u64,
+    },
+    SetLiquidityReserve {
+        bps: &'a u32,
+    },
+    SetMinAmounts {
+        min_deposit_amount: &'a U128,
+        min_stake_amount: &'a U128,
+        min_unstake_amount: &'a U128,
+    },
     PauseContract {},
     ResumeContract {},
+    // Upgrade
+    StageUpgrade {
+        code_hash: &'a Base58CryptoHash,
+        code_size: u64,
+        activation_epoch: u64,
+    },
+    ApplyUpgrade {
+        code_hash: &'a Base58CryptoHash,
+    },
+    CancelUpgrade {
+        code_hash: &'a Base58CryptoHash,
+    },
+    MigrationCompleted {
+        accounts_num: u64,
+        validators_num: u64,
+    },
+    LegacyStorageCleanup {
+        storage: &'a str,
+        removed_entries: u64,
+        freed_bytes: u64,
+        freed_near: &'a U128,
+    },
+    // wNEAR
+    DepositWnear {
+        account_id: &'a AccountId,
+        amount: &'a U128,
+        minted_stake_shares: &'a U128,
+    },
+    DepositWnearFailed {
+        account_id: &'a AccountId,
+        amount: &'a U128,
+    },
+    // Allowance
+    FtApprove {
+        owner_id: &'a AccountId,
+        spender_id: &'a AccountId,
+        amount: &'a U128,
+    },
+    FtRevoke {
+        owner_id: &'a AccountId,
+        spender_id: &'a AccountId,
+    },
+    // Account stats
+    EnableAccountStats {
+        account_id: &'a AccountId,
+    },
+    DisableAccountStats {
+        account_id: &'a AccountId,
+    },
+    // Omnibus accounts
+    EnableSubAccounts {
+        account_id: &'a AccountId,
+    },
+    DisableSubAccounts {
+        account_id: &'a AccountId,
+    },
+    SubAccountTransfer {
+        account_id: &'a AccountId,
+        from_sub_account_id: &'a SubAccountId,
+        to_sub_account_id: &'a SubAccountId,
+        stake_shares: &'a U128,
+    },
+    SubAccountFtTransfer {
+        sender_id: &'a AccountId,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        sender_sub_account_id: Option<&'a SubAccountId>,
+        receiver_id: &'a AccountId,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        receiver_sub_account_id: Option<&'a SubAccountId>,
+        amount: &'a U128,
+    },
 }
 
 impl Event<'_> {
@@ -304,14 +487,16 @@ mod tests {
     fn epoch_cleanup() {
         let stake_amount_to_settle = &U128(100);
         let unstake_amount_to_settle = &U128(0);
+        let liquidity_reserve_amount = &U128(10);
         Event::EpochCleanup {
             stake_amount_to_settle,
             unstake_amount_to_settle,
+            liquidity_reserve_amount,
         }
         .emit();
         assert_eq!(
             test_utils::get_logs()[0],
-            r#"EVENT_JSON:{"standard":"linear","version":"1.0.1","event":"epoch_cleanup","data":[{"stake_amount_to_settle":"100","unstake_amount_to_settle":"0"}]}"#
+            r#"EVENT_JSON:{"standard":"linear","version":"1.0.1","event":"epoch_cleanup","data":[{"stake_amount_to_settle":"100","unstake_amount_to_settle":"0","liquidity_reserve_amount":"10"}]}"#
         );
     }
 
@@ -500,6 +685,8 @@ mod tests {
         let new_unstaked_balance = &U128(200);
         Event::Deposit {
             account_id,
+            sub_account_id: None,
+            payer_id: None,
             amount,
             new_unstaked_balance,
         }
@@ -510,6 +697,26 @@ mod tests {
         );
     }
 
+    #[test]
+    fn deposit_for() {
+        let account_id = &alice();
+        let payer_id = &AccountId::new_unchecked("bob".to_string());
+        let amount = &U128(100);
+        let new_unstaked_balance = &U128(200);
+        Event::Deposit {
+            account_id,
+            sub_account_id: None,
+            payer_id: Some(payer_id),
+            amount,
+            new_unstaked_balance,
+        }
+        .emit();
+        assert_eq!(
+            test_utils::get_logs()[0],
+            r#"EVENT_JSON:{"standard":"linear","version":"1.0.1","event":"deposit","data":[{"account_id":"alice","payer_id":"bob","amount":"100","new_unstaked_balance":"200"}]}"#
+        );
+    }
+
     #[test]
     fn withdraw() {
         let account_id = &alice();
@@ -517,6 +724,8 @@ mod tests {
         let new_unstaked_balance = &U128(50);
         Event::Withdraw {
             account_id,
+            sub_account_id: None,
+            receiver_id: None,
             amount,
             new_unstaked_balance,
         }
@@ -527,6 +736,74 @@ mod tests {
         );
     }
 
+    #[test]
+    fn withdraw_to() {
+        let account_id = &alice();
+        let receiver_id = &AccountId::new_unchecked("bob".to_string());
+        let amount = &U128(100);
+        let new_unstaked_balance = &U128(50);
+        Event::Withdraw {
+            account_id,
+            sub_account_id: None,
+            receiver_id: Some(receiver_id),
+            amount,
+            new_unstaked_balance,
+        }
+        .emit();
+        assert_eq!(
+            test_utils::get_logs()[0],
+            r#"EVENT_JSON:{"standard":"linear","version":"1.0.1","event":"withdraw","data":[{"account_id":"alice","receiver_id":"bob","amount":"100","new_unstaked_balance":"50"}]}"#
+        );
+    }
+
+    #[test]
+    fn withdraw_to_failed() {
+        let account_id = &alice();
+        let receiver_id = &AccountId::new_unchecked("bob".to_string());
+        let amount = &U128(100);
+        let new_unstaked_balance = &U128(150);
+        Event::WithdrawToFailed {
+            account_id,
+            sub_account_id: None,
+            receiver_id,
+            amount,
+            new_unstaked_balance,
+        }
+        .emit();
+        assert_eq!(
+            test_utils::get_logs()[0],
+            r#"EVENT_JSON:{"standard":"linear","version":"1.0.1","event":"withdraw_to_failed","data":[{"account_id":"alice","receiver_id":"bob","amount":"100","new_unstaked_balance":"150"}]}"#
+        );
+    }
+
+    #[test]
+    fn donation() {
+        let account_id = &alice();
+        let amount = &U128(100);
+        Event::Donation { account_id, amount }.emit();
+        assert_eq!(
+            test_utils::get_logs()[0],
+            r#"EVENT_JSON:{"standard":"linear","version":"1.0.1","event":"donation","data":[{"account_id":"alice","amount":"100"}]}"#
+        );
+    }
+
+    #[test]
+    fn burn_to_pool() {
+        let account_id = &alice();
+        let burnt_stake_shares = &U128(100);
+        let new_total_share_amount = &U128(900);
+        Event::BurnToPool {
+            account_id,
+            burnt_stake_shares,
+            new_total_share_amount,
+        }
+        .emit();
+        assert_eq!(
+            test_utils::get_logs()[0],
+            r#"EVENT_JSON:{"standard":"linear","version":"1.0.1","event":"burn_to_pool","data":[{"account_id":"alice","burnt_stake_shares":"100","new_total_share_amount":"900"}]}"#
+        );
+    }
+
     #[test]
     fn stake() {
         let account_id = &alice();
@@ -536,6 +813,8 @@ mod tests {
         let new_stake_shares = &U128(199);
         Event::Stake {
             account_id,
+            sub_account_id: None,
+            payer_id: None,
             staked_amount,
             minted_stake_shares,
             new_unstaked_balance,
@@ -548,6 +827,30 @@ mod tests {
         );
     }
 
+    #[test]
+    fn stake_for() {
+        let account_id = &alice();
+        let payer_id = &AccountId::new_unchecked("bob".to_string());
+        let staked_amount = &U128(100);
+        let minted_stake_shares = &U128(99);
+        let new_unstaked_balance = &U128(10);
+        let new_stake_shares = &U128(199);
+        Event::Stake {
+            account_id,
+            sub_account_id: None,
+            payer_id: Some(payer_id),
+            staked_amount,
+            minted_stake_shares,
+            new_unstaked_balance,
+            new_stake_shares,
+        }
+        .emit();
+        assert_eq!(
+            test_utils::get_logs()[0],
+            r#"EVENT_JSON:{"standard":"linear","version":"1.0.1","event":"stake","data":[{"account_id":"alice","payer_id":"bob","staked_amount":"100","minted_stake_shares":"99","new_unstaked_balance":"10","new_stake_shares":"199"}]}"#
+        );
+    }
+
     #[test]
     fn unstake() {
         let account_id = &alice();
@@ -558,6 +861,7 @@ mod tests {
         let unstaked_available_epoch_height = 932;
         Event::Unstake {
             account_id,
+            sub_account_id: None,
             unstaked_amount,
             burnt_stake_shares,
             new_unstaked_balance,
@@ -625,4 +929,436 @@ mod tests {
             r#"EVENT_JSON:{"standard":"linear","version":"1.0.1","event":"validator_removed","data":[{"account_id":"alice"}]}"#
         );
     }
+
+    #[test]
+    fn stage_upgrade() {
+        let code_hash = &Base58CryptoHash::from([1u8; 32]);
+        Event::StageUpgrade {
+            code_hash,
+            code_size: 100,
+            activation_epoch: 14,
+        }
+        .emit();
+        assert_eq!(
+            test_utils::get_logs()[0],
+            r#"EVENT_JSON:{"standard":"linear","version":"1.0.1","event":"stage_upgrade","data":[{"code_hash":"4vJ9JU1bJJE96FWSJKvHsmmFADCg4gpZQff4P3bkLKi","code_size":100,"activation_epoch":14}]}"#
+        );
+    }
+
+    #[test]
+    fn apply_upgrade() {
+        let code_hash = &Base58CryptoHash::from([1u8; 32]);
+        Event::ApplyUpgrade { code_hash }.emit();
+        assert_eq!(
+            test_utils::get_logs()[0],
+            r#"EVENT_JSON:{"standard":"linear","version":"1.0.1","event":"apply_upgrade","data":[{"code_hash":"4vJ9JU1bJJE96FWSJKvHsmmFADCg4gpZQff4P3bkLKi"}]}"#
+        );
+    }
+
+    #[test]
+    fn cancel_upgrade() {
+        let code_hash = &Base58CryptoHash::from([1u8; 32]);
+        Event::CancelUpgrade { code_hash }.emit();
+        assert_eq!(
+            test_utils::get_logs()[0],
+            r#"EVENT_JSON:{"standard":"linear","version":"1.0.1","event":"cancel_upgrade","data":[{"code_hash":"4vJ9JU1bJJE96FWSJKvHsmmFADCg4gpZQff4P3bkLKi"}]}"#
+        );
+    }
+
+    #[test]
+    fn migration_completed() {
+        Event::MigrationCompleted {
+            accounts_num: 100,
+            validators_num: 10,
+        }
+        .emit();
+        assert_eq!(
+            test_utils::get_logs()[0],
+            r#"EVENT_JSON:{"standard":"linear","version":"1.0.1","event":"migration_completed","data":[{"accounts_num":100,"validators_num":10}]}"#
+        );
+    }
+
+    #[test]
+    fn legacy_storage_cleanup() {
+        let freed_near = &U128(100);
+        Event::LegacyStorageCleanup {
+            storage: "farms",
+            removed_entries: 2,
+            freed_bytes: 10,
+            freed_near,
+        }
+        .emit();
+        assert_eq!(
+            test_utils::get_logs()[0],
+            r#"EVENT_JSON:{"standard":"linear","version":"1.0.1","event":"legacy_storage_cleanup","data":[{"storage":"farms","removed_entries":2,"freed_bytes":10,"freed_near":"100"}]}"#
+        );
+    }
+
+    #[test]
+    fn deposit_wnear() {
+        let account_id = &alice();
+        let amount = &U128(100);
+        let minted_stake_shares = &U128(99);
+        Event::DepositWnear {
+            account_id,
+            amount,
+            minted_stake_shares,
+        }
+        .emit();
+        assert_eq!(
+            test_utils::get_logs()[0],
+            r#"EVENT_JSON:{"standard":"linear","version":"1.0.1","event":"deposit_wnear","data":[{"account_id":"alice","amount":"100","minted_stake_shares":"99"}]}"#
+        );
+    }
+
+    #[test]
+    fn deposit_wnear_failed() {
+        let account_id = &alice();
+        let amount = &U128(100);
+        Event::DepositWnearFailed { account_id, amount }.emit();
+        assert_eq!(
+            test_utils::get_logs()[0],
+            r#"EVENT_JSON:{"standard":"linear","version":"1.0.1","event":"deposit_wnear_failed","data":[{"account_id":"alice","amount":"100"}]}"#
+        );
+    }
+
+    #[test]
+    fn ft_approve() {
+        let owner_id = &alice();
+        let spender_id = &AccountId::new_unchecked("bob".to_string());
+        let amount = &U128(100);
+        Event::FtApprove {
+            owner_id,
+            spender_id,
+            amount,
+        }
+        .emit();
+        assert_eq!(
+            test_utils::get_logs()[0],
+            r#"EVENT_JSON:{"standard":"linear","version":"1.0.1","event":"ft_approve","data":[{"owner_id":"alice","spender_id":"bob","amount":"100"}]}"#
+        );
+    }
+
+    #[test]
+    fn ft_revoke() {
+        let owner_id = &alice();
+        let spender_id = &AccountId::new_unchecked("bob".to_string());
+        Event::FtRevoke {
+            owner_id,
+            spender_id,
+        }
+        .emit();
+        assert_eq!(
+            test_utils::get_logs()[0],
+            r#"EVENT_JSON:{"standard":"linear","version":"1.0.1","event":"ft_revoke","data":[{"owner_id":"alice","spender_id":"bob"}]}"#
+        );
+    }
+
+    #[test]
+    fn enable_account_stats() {
+        let account_id = &alice();
+        Event::EnableAccountStats { account_id }.emit();
+        assert_eq!(
+            test_utils::get_logs()[0],
+            r#"EVENT_JSON:{"standard":"linear","version":"1.0.1","event":"enable_account_stats","data":[{"account_id":"alice"}]}"#
+        );
+    }
+
+    #[test]
+    fn disable_account_stats() {
+        let account_id = &alice();
+        Event::DisableAccountStats { account_id }.emit();
+        assert_eq!(
+            test_utils::get_logs()[0],
+            r#"EVENT_JSON:{"standard":"linear","version":"1.0.1","event":"disable_account_stats","data":[{"account_id":"alice"}]}"#
+        );
+    }
+
+    #[test]
+    fn sub_account_stake() {
+        let account_id = &alice();
+        let sub_account_id = &"customer-1".to_string();
+        let staked_amount = &U128(100);
+        let minted_stake_shares = &U128(99);
+        let new_unstaked_balance = &U128(10);
+        let new_stake_shares = &U128(199);
+        Event::Stake {
+            account_id,
+            sub_account_id: Some(sub_account_id),
+            payer_id: None,
+            staked_amount,
+            minted_stake_shares,
+            new_unstaked_balance,
+            new_stake_shares,
+        }
+        .emit();
+        assert_eq!(
+            test_utils::get_logs()[0],
+            r#"EVENT_JSON:{"standard":"linear","version":"1.0.1","event":"stake","data":[{"account_id":"alice","sub_account_id":"customer-1","staked_amount":"100","minted_stake_shares":"99","new_unstaked_balance":"10","new_stake_shares":"199"}]}"#
+        );
+    }
+
+    #[test]
+    fn enable_sub_accounts() {
+        let account_id = &alice();
+        Event::EnableSubAccounts { account_id }.emit();
+        assert_eq!(
+            test_utils::get_logs()[0],
+            r#"EVENT_JSON:{"standard":"linear","version":"1.0.1","event":"enable_sub_accounts","data":[{"account_id":"alice"}]}"#
+        );
+    }
+
+    #[test]
+    fn disable_sub_accounts() {
+        let account_id = &alice();
+        Event::DisableSubAccounts { account_id }.emit();
+        assert_eq!(
+            test_utils::get_logs()[0],
+            r#"EVENT_JSON:{"standard":"linear","version":"1.0.1","event":"disable_sub_accounts","data":[{"account_id":"alice"}]}"#
+        );
+    }
+
+    #[test]
+    fn sub_account_transfer() {
+        let account_id = &alice();
+        let from_sub_account_id = &"customer-1".to_string();
+        let to_sub_account_id = &"customer-2".to_string();
+        let stake_shares = &U128(100);
+        Event::SubAccountTransfer {
+            account_id,
+            from_sub_account_id,
+            to_sub_account_id,
+            stake_shares,
+        }
+        .emit();
+        assert_eq!(
+            test_utils::get_logs()[0],
+            r#"EVENT_JSON:{"standard":"linear","version":"1.0.1","event":"sub_account_transfer","data":[{"account_id":"alice","from_sub_account_id":"customer-1","to_sub_account_id":"customer-2","stake_shares":"100"}]}"#
+        );
+    }
+
+    #[test]
+    fn sub_account_ft_transfer() {
+        let sender_id = &alice();
+        let receiver_id = &AccountId::new_unchecked("bob".to_string());
+        let sender_sub_account_id = &"customer-1".to_string();
+        let amount = &U128(100);
+        Event::SubAccountFtTransfer {
+            sender_id,
+            sender_sub_account_id: Some(sender_sub_account_id),
+            receiver_id,
+            receiver_sub_account_id: None,
+            amount,
+        }
+        .emit();
+        assert_eq!(
+            test_utils::get_logs()[0],
+            r#"EVENT_JSON:{"standard":"linear","version":"1.0.1","event":"sub_account_ft_transfer","data":[{"sender_id":"alice","sender_sub_account_id":"customer-1","receiver_id":"bob","amount":"100"}]}"#
+        );
+    }
+
+    #[test]
+    fn set_stake_limits() {
+        Event::SetStakeLimits {
+            max_total_staked_near_amount: Some(&U128(1000)),
+            max_epoch_stake_amount: None,
+            max_epoch_unstake_amount: Some(&U128(100)),
+        }
+        .emit();
+        assert_eq!(
+            test_utils::get_logs()[0],
+            r#"EVENT_JSON:{"standard":"linear","version":"1.0.1","event":"set_stake_limits","data":[{"max_total_staked_near_amount":"1000","max_epoch_stake_amount":null,"max_epoch_unstake_amount":"100"}]}"#
+        );
+    }
+
+    #[test]
+    fn set_min_amounts() {
+        Event::SetMinAmounts {
+            min_deposit_amount: &U128(100),
+            min_stake_amount: &U128(50),
+            min_unstake_amount: &U128(10),
+        }
+        .emit();
+        assert_eq!(
+            test_utils::get_logs()[0],
+            r#"EVENT_JSON:{"standard":"linear","version":"1.0.1","event":"set_min_amounts","data":[{"min_deposit_amount":"100","min_stake_amount":"50","min_unstake_amount":"10"}]}"#
+        );
+    }
+
+    #[test]
+    fn set_liquidity_reserve() {
+        Event::SetLiquidityReserve { bps: &500 }.emit();
+        assert_eq!(
+            test_utils::get_logs()[0],
+            r#"EVENT_JSON:{"standard":"linear","version":"1.0.1","event":"set_liquidity_reserve","data":[{"bps":500}]}"#
+        );
+    }
+
+    #[test]
+    fn enable_auto_claim() {
+        let account_id = &alice();
+        Event::EnableAutoClaim { account_id }.emit();
+        assert_eq!(
+            test_utils::get_logs()[0],
+            r#"EVENT_JSON:{"standard":"linear","version":"1.0.1","event":"enable_auto_claim","data":[{"account_id":"alice"}]}"#
+        );
+    }
+
+    #[test]
+    fn disable_auto_claim() {
+        let account_id = &alice();
+        Event::DisableAutoClaim { account_id }.emit();
+        assert_eq!(
+            test_utils::get_logs()[0],
+            r#"EVENT_JSON:{"standard":"linear","version":"1.0.1","event":"disable_auto_claim","data":[{"account_id":"alice"}]}"#
+        );
+    }
+
+    #[test]
+    fn claim() {
+        let account_id = &alice();
+        let amount = &U128(100);
+        Event::Claim { account_id, amount }.emit();
+        assert_eq!(
+            test_utils::get_logs()[0],
+            r#"EVENT_JSON:{"standard":"linear","version":"1.0.1","event":"claim","data":[{"account_id":"alice","amount":"100"}]}"#
+        );
+    }
+
+    #[test]
+    fn claim_failed() {
+        let account_id = &alice();
+        let amount = &U128(100);
+        let new_unstaked_balance = &U128(100);
+        Event::ClaimFailed {
+            account_id,
+            amount,
+            new_unstaked_balance,
+        }
+        .emit();
+        assert_eq!(
+            test_utils::get_logs()[0],
+            r#"EVENT_JSON:{"standard":"linear","version":"1.0.1","event":"claim_failed","data":[{"account_id":"alice","amount":"100","new_unstaked_balance":"100"}]}"#
+        );
+    }
+
+    #[test]
+    fn create_lock_schedule() {
+        let account_id = &alice();
+        let amount = &U128(100);
+        Event::CreateLockSchedule {
+            account_id,
+            amount,
+            start_timestamp: &U64(10),
+            cliff_timestamp: &U64(20),
+            end_timestamp: &U64(30),
+        }
+        .emit();
+        assert_eq!(
+            test_utils::get_logs()[0],
+            r#"EVENT_JSON:{"standard":"linear","version":"1.0.1","event":"create_lock_schedule","data":[{"account_id":"alice","amount":"100","start_timestamp":"10","cliff_timestamp":"20","end_timestamp":"30"}]}"#
+        );
+    }
+
+    #[test]
+    fn terminate_lock_schedule() {
+        let account_id = &alice();
+        let returned_shares = &U128(100);
+        Event::TerminateLockSchedule {
+            account_id,
+            returned_shares,
+        }
+        .emit();
+        assert_eq!(
+            test_utils::get_logs()[0],
+            r#"EVENT_JSON:{"standard":"linear","version":"1.0.1","event":"terminate_lock_schedule","data":[{"account_id":"alice","returned_shares":"100"}]}"#
+        );
+    }
+
+    #[test]
+    fn enable_withdrawal_allowlist() {
+        let account_id = &alice();
+        Event::EnableWithdrawalAllowlist {
+            account_id,
+            allowlist: &vec![alice()],
+        }
+        .emit();
+        assert_eq!(
+            test_utils::get_logs()[0],
+            r#"EVENT_JSON:{"standard":"linear","version":"1.0.1","event":"enable_withdrawal_allowlist","data":[{"account_id":"alice","allowlist":["alice"]}]}"#
+        );
+    }
+
+    #[test]
+    fn request_withdrawal_allowlist_change() {
+        let account_id = &alice();
+        Event::RequestWithdrawalAllowlistChange {
+            account_id,
+            allowlist: None,
+            effective_epoch_height: &14,
+        }
+        .emit();
+        assert_eq!(
+            test_utils::get_logs()[0],
+            r#"EVENT_JSON:{"standard":"linear","version":"1.0.1","event":"request_withdrawal_allowlist_change","data":[{"account_id":"alice","allowlist":null,"effective_epoch_height":14}]}"#
+        );
+    }
+
+    #[test]
+    fn cancel_withdrawal_allowlist_change() {
+        let account_id = &alice();
+        Event::CancelWithdrawalAllowlistChange { account_id }.emit();
+        assert_eq!(
+            test_utils::get_logs()[0],
+            r#"EVENT_JSON:{"standard":"linear","version":"1.0.1","event":"cancel_withdrawal_allowlist_change","data":[{"account_id":"alice"}]}"#
+        );
+    }
+
+    #[test]
+    fn apply_withdrawal_allowlist_change() {
+        let account_id = &alice();
+        Event::ApplyWithdrawalAllowlistChange {
+            account_id,
+            allowlist: Some(&vec![alice()]),
+        }
+        .emit();
+        assert_eq!(
+            test_utils::get_logs()[0],
+            r#"EVENT_JSON:{"standard":"linear","version":"1.0.1","event":"apply_withdrawal_allowlist_change","data":[{"account_id":"alice","allowlist":["alice"]}]}"#
+        );
+    }
+
+    #[test]
+    fn enable_balance_checkpoints() {
+        let account_id = &alice();
+        Event::EnableBalanceCheckpoints { account_id }.emit();
+        assert_eq!(
+            test_utils::get_logs()[0],
+            r#"EVENT_JSON:{"standard":"linear","version":"1.0.1","event":"enable_balance_checkpoints","data":[{"account_id":"alice"}]}"#
+        );
+    }
+
+    #[test]
+    fn disable_balance_checkpoints() {
+        let account_id = &alice();
+        Event::DisableBalanceCheckpoints { account_id }.emit();
+        assert_eq!(
+            test_utils::get_logs()[0],
+            r#"EVENT_JSON:{"standard":"linear","version":"1.0.1","event":"disable_balance_checkpoints","data":[{"account_id":"alice"}]}"#
+        );
+    }
+
+    #[test]
+    fn pin_balance_checkpoint() {
+        let account_id = &alice();
+        Event::PinBalanceCheckpoint {
+            account_id,
+            epoch_height: 10,
+        }
+        .emit();
+        assert_eq!(
+            test_utils::get_logs()[0],
+            r#"EVENT_JSON:{"standard":"linear","version":"1.0.1","event":"pin_balance_checkpoint","data":[{"account_id":"alice","epoch_height":10}]}"#
+        );
+    }
 }
@@ -0,0 +1,357 @@
+use crate::events::Event;
+use crate::*;
+use near_sdk::json_types::U128;
+use near_sdk::{assert_one_yocto, env, near_bindgen, AccountId, Balance, PromiseOrValue};
+
+/// The LiNEAR amount that the spender is allowed to transfer on behalf of the owner.
+/// The storage of the allowance is added to the storage deposit of the owner,
+/// which is refunded on revoke or unregister.
+#[derive(BorshSerialize, BorshDeserialize, Default)]
+pub struct Allowance {
+    pub amount: Balance,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AllowanceView {
+    pub owner_id: AccountId,
+    pub spender_id: AccountId,
+    pub amount: U128,
+}
+
+#[near_bindgen]
+impl LiquidStakingContract {
+    /// Set the allowance of `spender_id` to `amount`, replacing the existing one.
+    /// The owner should be registered, and creating a new allowance charges its storage.
+    /// Updating an existing allowance requires exactly 1 yocto NEAR.
+    #[payable]
+    pub fn ft_approve(&mut self, spender_id: AccountId, amount: U128) {
+        let owner_id = env::predecessor_account_id();
+        self.internal_set_allowance(&owner_id, &spender_id, amount.0);
+    }
+
+    /// Increase the allowance of `spender_id` by `amount`. The deposit requirement
+    /// is the same as `ft_approve()`.
+    #[payable]
+    pub fn ft_increase_allowance(&mut self, spender_id: AccountId, amount: U128) {
+        let owner_id = env::predecessor_account_id();
+        let allowance = self.internal_get_allowance(&owner_id, &spender_id);
+        let new_amount = allowance
+            .amount
+            .checked_add(amount.0)
+            .expect(ERR_ALLOWANCE_OVERFLOW);
+        self.internal_set_allowance(&owner_id, &spender_id, new_amount);
+    }
+
+    /// Decrease the allowance of `spender_id` by `amount`. Requires exactly 1 yocto NEAR.
+    #[payable]
+    pub fn ft_decrease_allowance(&mut self, spender_id: AccountId, amount: U128) {
+        let owner_id = env::predecessor_account_id();
+        let allowance = self
+            .allowances
+            .get(&(owner_id.clone(), spender_id.clone()))
+            .expect(ERR_NO_ALLOWANCE);
+        let new_amount = allowance
+            .amount
+            .checked_sub(amount.0)
+            .expect(ERR_DECREASE_ALLOWANCE_BELOW_ZERO);
+        self.internal_set_allowance(&owner_id, &spender_id, new_amount);
+    }
+
+    /// Remove the allowance of `spender_id` and refund its storage deposit to the owner.
+    /// Requires exactly 1 yocto NEAR.
+    #[payable]
+    pub fn ft_revoke(&mut self, spender_id: AccountId) {
+        assert_one_yocto();
+        let owner_id = env::predecessor_account_id();
+        self.allowances
+            .remove(&(owner_id.clone(), spender_id.clone()))
+            .expect(ERR_NO_ALLOWANCE);
+        let mut spenders = self.allowance_spenders.get(&owner_id).unwrap_or_default();
+        spenders.retain(|id| id != &spender_id);
+        if spenders.is_empty() {
+            self.allowance_spenders.remove(&owner_id);
+        } else {
+            self.allowance_spenders.insert(&owner_id, &spenders);
+        }
+        self.allowances_num -= 1;
+        self.internal_refund_feature_storage(&owner_id, self.internal_allowance_storage_cost());
+
+        Event::FtRevoke {
+            owner_id: &owner_id,
+            spender_id: &spender_id,
+        }
+        .emit();
+    }
+
+    /// Transfer LiNEAR from `owner_id` to `receiver_id` by the spender,
+    /// the allowance is reduced by `amount`.
+    #[payable]
+    pub fn ft_transfer_from(
+        &mut self,
+        owner_id: AccountId,
+        receiver_id: AccountId,
+        amount: U128,
+        memo: Option<String>,
+    ) {
+        assert_one_yocto();
+        let spender_id = env::predecessor_account_id();
+        self.internal_spend_allowance(&owner_id, &spender_id, amount.0);
+        self.assert_allowed_receiver(&owner_id, &receiver_id);
+        let receiver_sub_account_id = self.internal_receiver_sub_account(&receiver_id, &memo);
+        self.internal_ft_transfer(
+            &owner_id,
+            None,
+            &receiver_id,
+            receiver_sub_account_id.as_ref(),
+            amount.0,
+            memo,
+        );
+    }
+
+    /// Transfer LiNEAR from `owner_id` to `receiver_id` by the spender and call
+    /// `ft_on_transfer` on the receiver with `owner_id` as the sender.
+    /// The unused amount is refunded to the owner, but the allowance is not restored.
+    #[payable]
+    pub fn ft_transfer_call_from(
+        &mut self,
+        owner_id: AccountId,
+        receiver_id: AccountId,
+        amount: U128,
+        memo: Option<String>,
+        msg: String,
+    ) -> PromiseOrValue<U128> {
+        assert_one_yocto();
+        let spender_id = env::predecessor_account_id();
+        self.internal_spend_allowance(&owner_id, &spender_id, amount.0);
+        self.internal_ft_transfer_call(owner_id, None, receiver_id, amount.0, memo, msg)
+    }
+
+    // --- View Functions ---
+
+    /// Returns the LiNEAR amount that `spender_id` is allowed to transfer from `owner_id`
+    pub fn ft_allowance(&self, owner_id: AccountId, spender_id: AccountId) -> U128 {
+        self.internal_get_allowance(&owner_id, &spender_id)
+            .amount
+            .into()
+    }
+
+    /// Returns the allowance details
+    pub fn get_allowance(
+        &self,
+        owner_id: AccountId,
+        spender_id: AccountId,
+    ) -> Option<AllowanceView> {
+        self.allowances
+            .get(&(owner_id.clone(), spender_id.clone()))
+            .map(|allowance| AllowanceView {
+                owner_id,
+                spender_id,
+                amount: allowance.amount.into(),
+            })
+    }
+}
+
+impl LiquidStakingContract {
+    pub(crate) fn internal_allowance_storage_cost(&self) -> Balance {
+        Balance::from(self.allowance_storage_usage) * env::storage_byte_cost()
+    }
+
+    /// The storage balance of the allowances of the owner
+    pub(crate) fn internal_allowances_storage_balance(&self, owner_id: &AccountId) -> Balance {
+        let allowances_num = self
+            .allowance_spenders
+            .get(owner_id)
+            .map_or(0, |spenders| spenders.len());
+        self.internal_allowance_storage_cost() * allowances_num as Balance
+    }
+
+    /// Remove all allowances of the owner, whose storage is refunded
+    /// with the storage deposit of the owner
+    pub(crate) fn internal_remove_allowances(&mut self, owner_id: &AccountId) {
+        if let Some(spenders) = self.allowance_spenders.remove(owner_id) {
+            for spender_id in spenders.iter() {
+                self.allowances
+                    .remove(&(owner_id.clone(), spender_id.clone()));
+            }
+            self.allowances_num -= spenders.len() as u64;
+        }
+    }
+
+    pub(crate) fn internal_get_allowance(
+        &self,
+        owner_id: &AccountId,
+        spender_id: &AccountId,
+    ) -> Allowance {
+        self.allowances
+            .get(&(owner_id.clone(), spender_id.clone()))
+            .unwrap_or_default()
+    }
+
+    fn internal_set_allowance(
+        &mut self,
+        owner_id: &AccountId,
+        spender_id: &AccountId,
+        amount: Balance,
+    ) {
+        self.assert_running();
+        require!(owner_id != spender_id, ERR_SELF_ALLOWANCE);
+
+        let key = (owner_id.clone(), spender_id.clone());
+        match self.allowances.get(&key) {
+            Some(mut allowance) => {
+                assert_one_yocto();
+                allowance.amount = amount;
+                self.allowances.insert(&key, &allowance);
+            }
+            None => {
+                require!(
+                    self.internal_find_account(owner_id).is_some(),
+                    ERR_ACCOUNT_NOT_REGISTERED
+                );
+                self.internal_charge_feature_storage(
+                    owner_id,
+                    self.internal_allowance_storage_cost(),
+                    ERR_NO_ENOUGH_ALLOWANCE_STORAGE_DEPOSIT,
+                );
+                self.allowances.insert(&key, &Allowance { amount });
+                let mut spenders = self.allowance_spenders.get(owner_id).unwrap_or_default();
+                spenders.push(spender_id.clone());
+                self.allowance_spenders.insert(owner_id, &spenders);
+                self.allowances_num += 1;
+            }
+        }
+
+        Event::FtApprove {
+            owner_id,
+            spender_id,
+            amount: &U128(amount),
+        }
+        .emit();
+    }
+
+    fn internal_spend_allowance(
+        &mut self,
+        owner_id: &AccountId,
+        spender_id: &AccountId,
+        amount: Balance,
+    ) {
+        let key = (owner_id.clone(), spender_id.clone());
+        let mut allowance = self.allowances.get(&key).expect(ERR_NO_ALLOWANCE);
+        allowance.amount = allowance
+            .amount
+            .checked_sub(amount)
+            .expect(ERR_NO_ENOUGH_ALLOWANCE);
+        self.allowances.insert(&key, &allowance);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use near_contract_standards::fungible_token::core::FungibleTokenCore;
+    use near_contract_standards::storage_management::StorageManagement;
+    use near_sdk::test_utils::accounts;
+
+    use super::*;
+    use crate::test_utils::*;
+
+    fn register_with_shares(
+        contract: &mut LiquidStakingContract,
+        account_id: &AccountId,
+        shares: Balance,
+    ) {
+        contract.internal_register_account(account_id);
+        contract.internal_ft_deposit(account_id, None, shares);
+    }
+
+    #[test]
+    fn approve_and_transfer_from() {
+        let mut contract = new_contract();
+        let (alice, bob, charlie) = (accounts(2), accounts(3), accounts(4));
+        register_with_shares(&mut contract, &alice, 10 * ONE_NEAR);
+        contract.internal_register_account(&charlie);
+
+        let storage_cost = contract.internal_allowance_storage_cost();
+        assert!(storage_cost > 0);
+
+        set_context(&alice, ONE_NEAR);
+        contract.ft_approve(bob.clone(), U128(3 * ONE_NEAR));
+        let allowance = contract.get_allowance(alice.clone(), bob.clone()).unwrap();
+        assert_eq!(allowance.amount.0, 3 * ONE_NEAR);
+        assert_eq!(contract.internal_get_storage_deposit(&alice), storage_cost);
+        assert_eq!(
+            contract.internal_account_min_storage_balance(&alice),
+            contract.internal_min_storage_balance() + storage_cost
+        );
+
+        set_context(&bob, 1);
+        contract.ft_transfer_from(alice.clone(), charlie.clone(), U128(2 * ONE_NEAR), None);
+        assert_eq!(
+            contract.ft_allowance(alice.clone(), bob.clone()).0,
+            ONE_NEAR
+        );
+        assert_eq!(contract.ft_balance_of(alice.clone()).0, 8 * ONE_NEAR);
+        assert_eq!(contract.ft_balance_of(charlie).0, 2 * ONE_NEAR);
+
+        set_context(&alice, 1);
+        contract.ft_increase_allowance(bob.clone(), U128(ONE_NEAR));
+        assert_eq!(
+            contract.ft_allowance(alice.clone(), bob.clone()).0,
+            2 * ONE_NEAR
+        );
+        contract.ft_decrease_allowance(bob.clone(), U128(2 * ONE_NEAR));
+        assert_eq!(contract.ft_allowance(alice.clone(), bob.clone()).0, 0);
+
+        contract.ft_revoke(bob.clone());
+        assert!(contract.get_allowance(alice.clone(), bob).is_none());
+        assert_eq!(contract.internal_get_storage_deposit(&alice), 0);
+        assert_eq!(contract.allowances_num, 0);
+    }
+
+    #[test]
+    fn unregister_removes_allowances() {
+        let mut contract = new_contract();
+        let (alice, bob, charlie) = (accounts(2), accounts(3), accounts(4));
+        contract.internal_register_account(&alice);
+
+        set_context(&alice, ONE_NEAR);
+        contract.ft_approve(bob.clone(), U128(ONE_NEAR));
+        contract.ft_approve(charlie.clone(), U128(ONE_NEAR));
+        assert_eq!(contract.allowances_num, 2);
+
+        set_context(&alice, 1);
+        assert!(contract.storage_unregister(None));
+        assert_eq!(contract.allowances_num, 0);
+        assert!(contract.allowance_spenders.get(&alice).is_none());
+
+        // the allowances are not restored after registering again
+        contract.internal_register_account(&alice);
+        assert!(contract.get_allowance(alice.clone(), bob).is_none());
+        assert!(contract.get_allowance(alice, charlie).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "The spender doesn't have enough allowance")]
+    fn transfer_from_exceeds_allowance() {
+        let mut contract = new_contract();
+        let (alice, bob) = (accounts(2), accounts(3));
+        register_with_shares(&mut contract, &alice, 10 * ONE_NEAR);
+
+        set_context(&alice, ONE_NEAR);
+        contract.ft_approve(bob.clone(), U128(ONE_NEAR));
+
+        set_context(&bob, 1);
+        contract.ft_transfer_from(alice, bob, U128(2 * ONE_NEAR), None);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "The attached deposit is less than the storage cost of the allowance"
+    )]
+    fn approve_without_storage_deposit() {
+        let mut contract = new_contract();
+        contract.internal_register_account(&accounts(2));
+        set_context(&accounts(2), 1);
+        contract.ft_approve(accounts(3), U128(ONE_NEAR));
+    }
+}
@@ -1,3 +1,4 @@
+use crate::events::Event;
 use crate::*;
 use near_contract_standards::fungible_token::core::FungibleTokenCore;
 use near_contract_standards::fungible_token::events::FtTransfer;
@@ -39,6 +40,15 @@ trait FungibleTokenResolver {
         receiver_id: AccountId,
         amount: U128,
     ) -> U128;
+
+    fn ft_resolve_sub_account_transfer(
+        &mut self,
+        sender_id: AccountId,
+        sender_sub_account_id: Option<SubAccountId>,
+        receiver_id: AccountId,
+        receiver_sub_account_id: Option<SubAccountId>,
+        amount: U128,
+    ) -> U128;
 }
 
 #[near_bindgen]
@@ -48,7 +58,16 @@ impl FungibleTokenCore for LiquidStakingContract {
         assert_one_yocto();
         let sender_id = env::predecessor_account_id();
         let amount = amount.into();
-        self.internal_ft_transfer(&sender_id, &receiver_id, amount, memo);
+        self.assert_allowed_receiver(&sender_id, &receiver_id);
+        let receiver_sub_account_id = self.internal_receiver_sub_account(&receiver_id, &memo);
+        self.internal_ft_transfer(
+            &sender_id,
+            None,
+            &receiver_id,
+            receiver_sub_account_id.as_ref(),
+            amount,
+            memo,
+        );
     }
 
     #[payable]
@@ -60,35 +79,8 @@ impl FungibleTokenCore for LiquidStakingContract {
         msg: String,
     ) -> PromiseOrValue<U128> {
         assert_one_yocto();
-        // Ensure minimum required gas is attached
-        require!(
-            env::prepaid_gas() > MIN_GAS_FOR_FT_TRANSFER_CALL,
-            format!(
-                "{}. require at least {:?}",
-                ERR_NO_ENOUGH_GAS, MIN_GAS_FOR_FT_TRANSFER_CALL
-            )
-        );
         let sender_id = env::predecessor_account_id();
-        let amount = amount.into();
-        self.internal_ft_transfer(&sender_id, &receiver_id, amount, memo);
-        // Initiating receiver's call and the callback
-        ext_fungible_token_receiver::ft_on_transfer(
-            sender_id.clone(),
-            amount.into(),
-            msg,
-            receiver_id.clone(),
-            NO_DEPOSIT,
-            env::prepaid_gas() - GAS_RESERVED_FOR_FT_TRANSFER_CALL, // > MIN_GAS_FOR_FT_ON_TRANSFER
-        )
-        .then(ext_ft_self::ft_resolve_transfer(
-            sender_id,
-            receiver_id,
-            amount.into(),
-            env::current_account_id(),
-            NO_DEPOSIT,
-            GAS_FOR_RESOLVE_TRANSFER,
-        ))
-        .into()
+        self.internal_ft_transfer_call(sender_id, None, receiver_id, amount.into(), memo, msg)
     }
 
     fn ft_total_supply(&self) -> U128 {
@@ -110,51 +102,38 @@ impl FungibleTokenResolver for LiquidStakingContract {
         receiver_id: AccountId,
         amount: U128,
     ) -> U128 {
-        let amount: Balance = amount.into();
-
-        // Get the unused amount from the `ft_on_transfer` call result.
-        let unused_amount = match env::promise_result(0) {
-            PromiseResult::NotReady => unreachable!(),
-            PromiseResult::Successful(value) => {
-                if let Ok(unused_amount) = near_sdk::serde_json::from_slice::<U128>(&value) {
-                    std::cmp::min(amount, unused_amount.0)
-                } else {
-                    amount
-                }
-            }
-            PromiseResult::Failed => amount,
-        };
-
-        if unused_amount > 0 {
-            let mut receiver = self.internal_get_account(&receiver_id);
-            let receiver_balance = receiver.stake_shares;
-            if receiver_balance > 0 {
-                let refund_amount = std::cmp::min(receiver_balance, unused_amount);
-                receiver.stake_shares -= refund_amount;
-                self.internal_save_account(&receiver_id, &receiver);
-
-                let mut sender = self.internal_get_account(&sender_id);
-                sender.stake_shares += refund_amount;
-                self.internal_save_account(&sender_id, &sender);
-
-                FtTransfer {
-                    old_owner_id: &receiver_id,
-                    new_owner_id: &sender_id,
-                    amount: &U128(refund_amount),
-                    memo: Some("refund"),
-                }
-                .emit();
+        self.internal_ft_resolve_transfer(&sender_id, None, &receiver_id, None, amount.into())
+            .into()
+    }
+}
 
-                return (amount - refund_amount).into();
-            }
-        }
-        amount.into()
+#[near_bindgen]
+impl LiquidStakingContract {
+    /// Resolves `ft_transfer_call()` from or to a sub-account, the unused amount is
+    /// refunded from the receiver sub-account to the sender sub-account.
+    #[private]
+    pub fn ft_resolve_sub_account_transfer(
+        &mut self,
+        sender_id: AccountId,
+        sender_sub_account_id: Option<SubAccountId>,
+        receiver_id: AccountId,
+        receiver_sub_account_id: Option<SubAccountId>,
+        amount: U128,
+    ) -> U128 {
+        self.internal_ft_resolve_transfer(
+            &sender_id,
+            sender_sub_account_id.as_ref(),
+            &receiver_id,
+            receiver_sub_account_id.as_ref(),
+            amount.into(),
+        )
+        .into()
     }
 }
 
 impl LiquidStakingContract {
     pub(crate) fn internal_ft_get_account(&self, account_id: &AccountId) -> Account {
-        match self.accounts.get(account_id) {
+        match self.internal_find_account(account_id) {
             Some(account) => account,
             None => {
                 env::panic_str(format!("The account {} is not registered", &account_id).as_str())
@@ -162,7 +141,13 @@ impl LiquidStakingContract {
         }
     }
 
-    pub(crate) fn internal_ft_deposit(&mut self, account_id: &AccountId, amount: ShareBalance) {
+    /// Deposit LiNEAR into the account, and into its sub-account if given, which must exist.
+    pub(crate) fn internal_ft_deposit(
+        &mut self,
+        account_id: &AccountId,
+        sub_account_id: Option<&SubAccountId>,
+        amount: ShareBalance,
+    ) {
         self.assert_running();
 
         let mut account = self.internal_ft_get_account(account_id);
@@ -170,6 +155,9 @@ impl LiquidStakingContract {
         if let Some(new_balance) = balance.checked_add(amount) {
             account.stake_shares = new_balance;
             self.internal_save_account(account_id, &account);
+            self.internal_update_sub_account(account_id, sub_account_id, |sub_account| {
+                sub_account.stake_shares += amount;
+            });
             self.total_share_amount = self
                 .total_share_amount
                 .checked_add(amount)
@@ -179,14 +167,28 @@ impl LiquidStakingContract {
         }
     }
 
-    pub(crate) fn internal_ft_withdraw(&mut self, account_id: &AccountId, amount: Balance) {
+    /// Withdraw LiNEAR from the account, and from its sub-account if given.
+    pub(crate) fn internal_ft_withdraw(
+        &mut self,
+        account_id: &AccountId,
+        sub_account_id: Option<&SubAccountId>,
+        amount: Balance,
+    ) {
         self.assert_running();
 
         let mut account = self.internal_ft_get_account(account_id);
         let balance = account.stake_shares;
         if let Some(new_balance) = balance.checked_sub(amount) {
+            self.assert_not_locked(account_id, new_balance);
             account.stake_shares = new_balance;
             self.internal_save_account(account_id, &account);
+            self.internal_update_sub_account(account_id, sub_account_id, |sub_account| {
+                require!(
+                    sub_account.stake_shares >= amount,
+                    ERR_NO_ENOUGH_SUB_ACCOUNT_BALANCE
+                );
+                sub_account.stake_shares -= amount;
+            });
             self.total_share_amount = self
                 .total_share_amount
                 .checked_sub(amount)
@@ -196,11 +198,15 @@ impl LiquidStakingContract {
         }
     }
 
-    /// Inner method to transfer LINEAR from sender to receiver
+    /// Inner method to transfer LINEAR from sender to receiver. The sub-accounts of
+    /// omnibus accounts should be given, and a new receiver sub-account is paid from
+    /// the available storage balance of the receiver.
     pub(crate) fn internal_ft_transfer(
         &mut self,
         sender_id: &AccountId,
+        sender_sub_account_id: Option<&SubAccountId>,
         receiver_id: &AccountId,
+        receiver_sub_account_id: Option<&SubAccountId>,
         amount: Balance,
         memo: Option<String>,
     ) {
@@ -208,10 +214,22 @@ impl LiquidStakingContract {
             sender_id != receiver_id,
             "Sender and receiver should be different"
         );
-        require!(amount > 0, "The amount should be a positive number");
+        require!(amount > 0, ERR_NON_POSITIVE_TRANSFER_AMOUNT);
+
+        let sender_shares = self.internal_get_account(sender_id).stake_shares;
+        self.internal_ft_withdraw(sender_id, sender_sub_account_id, amount);
+        if let Some(receiver_sub_account_id) = receiver_sub_account_id {
+            self.internal_ensure_sub_account_from_storage_balance(
+                receiver_id,
+                receiver_sub_account_id,
+            );
+        }
+        self.internal_ft_deposit(receiver_id, receiver_sub_account_id, amount);
 
-        self.internal_ft_withdraw(sender_id, amount);
-        self.internal_ft_deposit(receiver_id, amount);
+        // the transferred LiNEAR is valued at the current price for account stats
+        let value = self.staked_amount_from_num_shares_rounded_down(amount);
+        self.internal_record_shares_out(sender_id, amount, value, sender_shares);
+        self.internal_record_shares_in(receiver_id, amount, value);
 
         FtTransfer {
             old_owner_id: sender_id,
@@ -220,5 +238,178 @@ impl LiquidStakingContract {
             memo: memo.as_deref(),
         }
         .emit();
+        if sender_sub_account_id.is_some() || receiver_sub_account_id.is_some() {
+            Event::SubAccountFtTransfer {
+                sender_id,
+                sender_sub_account_id,
+                receiver_id,
+                receiver_sub_account_id,
+                amount: &U128(amount),
+            }
+            .emit();
+        }
+    }
+
+    /// Refunds the unused amount of `ft_transfer_call()` from the receiver to the sender,
+    /// except the locked LiNEAR of the receiver. Returns the used amount.
+    pub(crate) fn internal_ft_resolve_transfer(
+        &mut self,
+        sender_id: &AccountId,
+        sender_sub_account_id: Option<&SubAccountId>,
+        receiver_id: &AccountId,
+        receiver_sub_account_id: Option<&SubAccountId>,
+        amount: Balance,
+    ) -> Balance {
+        // Get the unused amount from the `ft_on_transfer` call result.
+        let unused_amount = match env::promise_result(0) {
+            PromiseResult::NotReady => unreachable!(),
+            PromiseResult::Successful(value) => {
+                if let Ok(unused_amount) = near_sdk::serde_json::from_slice::<U128>(&value) {
+                    std::cmp::min(amount, unused_amount.0)
+                } else {
+                    amount
+                }
+            }
+            PromiseResult::Failed => amount,
+        };
+
+        if unused_amount > 0 {
+            let mut receiver = self.internal_get_account(receiver_id);
+            let receiver_balance = receiver.stake_shares;
+            // the locked LiNEAR of the receiver is not refunded
+            let unlocked_balance =
+                receiver_balance.saturating_sub(self.internal_locked_shares(receiver_id));
+            // only the receiver sub-account is refunded from an omnibus account
+            let refundable_balance = match receiver_sub_account_id {
+                Some(receiver_sub_account_id) => std::cmp::min(
+                    unlocked_balance,
+                    self.internal_get_sub_account(receiver_id, receiver_sub_account_id)
+                        .stake_shares,
+                ),
+                None if self.internal_is_omnibus(receiver_id) => 0,
+                None => unlocked_balance,
+            };
+            if refundable_balance > 0 {
+                let refund_amount = std::cmp::min(refundable_balance, unused_amount);
+                receiver.stake_shares -= refund_amount;
+                self.internal_save_account(receiver_id, &receiver);
+                self.internal_update_sub_account(
+                    receiver_id,
+                    receiver_sub_account_id,
+                    |sub_account| {
+                        sub_account.stake_shares -= refund_amount;
+                    },
+                );
+
+                // the sender sub-account could be emptied in the meantime, or the sender
+                // is no longer an omnibus account if emptied
+                let sender_sub_account_id =
+                    sender_sub_account_id.filter(|_| self.internal_is_omnibus(sender_id));
+                let mut sender = self.internal_get_account(sender_id);
+                sender.stake_shares += refund_amount;
+                self.internal_save_account(sender_id, &sender);
+                if let Some(sender_sub_account_id) = sender_sub_account_id {
+                    self.internal_restore_sub_account(sender_id, sender_sub_account_id);
+                }
+                self.internal_update_sub_account(sender_id, sender_sub_account_id, |sub_account| {
+                    sub_account.stake_shares += refund_amount;
+                });
+
+                let value = self.staked_amount_from_num_shares_rounded_down(refund_amount);
+                self.internal_record_shares_out(
+                    receiver_id,
+                    refund_amount,
+                    value,
+                    receiver_balance,
+                );
+                self.internal_record_shares_in(sender_id, refund_amount, value);
+
+                FtTransfer {
+                    old_owner_id: receiver_id,
+                    new_owner_id: sender_id,
+                    amount: &U128(refund_amount),
+                    memo: Some("refund"),
+                }
+                .emit();
+
+                return amount - refund_amount;
+            }
+        }
+        amount
+    }
+
+    /// Inner method to transfer LINEAR from sender to receiver and call `ft_on_transfer`
+    /// on the receiver, the unused amount is refunded to sender by `ft_resolve_transfer`.
+    /// The receiver sub-account of an omnibus account is named by the memo.
+    pub(crate) fn internal_ft_transfer_call(
+        &mut self,
+        sender_id: AccountId,
+        sender_sub_account_id: Option<SubAccountId>,
+        receiver_id: AccountId,
+        amount: Balance,
+        memo: Option<String>,
+        msg: String,
+    ) -> PromiseOrValue<U128> {
+        // Ensure minimum required gas is attached
+        require!(
+            env::prepaid_gas() > MIN_GAS_FOR_FT_TRANSFER_CALL,
+            format!(
+                "{}. require at least {:?}",
+                ERR_NO_ENOUGH_GAS, MIN_GAS_FOR_FT_TRANSFER_CALL
+            )
+        );
+        // LiNEAR transferred to this contract is held by its own account until handled
+        // by `ft_on_transfer()`, which checks the allowlist of the sender for the receiver
+        // of the unstaked NEAR
+        if receiver_id == env::current_account_id() {
+            if self.internal_find_account(&receiver_id).is_none() {
+                self.internal_register_account(&receiver_id);
+            }
+        } else {
+            self.assert_allowed_receiver(&sender_id, &receiver_id);
+        }
+        let receiver_sub_account_id = self.internal_receiver_sub_account(&receiver_id, &memo);
+        self.internal_ft_transfer(
+            &sender_id,
+            sender_sub_account_id.as_ref(),
+            &receiver_id,
+            receiver_sub_account_id.as_ref(),
+            amount,
+            memo,
+        );
+        // Initiating receiver's call and the callback
+        let on_transfer = ext_fungible_token_receiver::ft_on_transfer(
+            sender_id.clone(),
+            amount.into(),
+            msg,
+            receiver_id.clone(),
+            NO_DEPOSIT,
+            env::prepaid_gas() - GAS_RESERVED_FOR_FT_TRANSFER_CALL, // > MIN_GAS_FOR_FT_ON_TRANSFER
+        );
+        if sender_sub_account_id.is_none() && receiver_sub_account_id.is_none() {
+            on_transfer
+                .then(ext_ft_self::ft_resolve_transfer(
+                    sender_id,
+                    receiver_id,
+                    amount.into(),
+                    env::current_account_id(),
+                    NO_DEPOSIT,
+                    GAS_FOR_RESOLVE_TRANSFER,
+                ))
+                .into()
+        } else {
+            on_transfer
+                .then(ext_ft_self::ft_resolve_sub_account_transfer(
+                    sender_id,
+                    sender_sub_account_id,
+                    receiver_id,
+                    receiver_sub_account_id,
+                    amount.into(),
+                    env::current_account_id(),
+                    NO_DEPOSIT,
+                    GAS_FOR_RESOLVE_TRANSFER,
+                ))
+                .into()
+        }
     }
 }
@@ -1,5 +1,8 @@
+use crate::events::Event;
 use crate::*;
+use near_contract_standards::fungible_token::events::FtBurn;
 use near_sdk::json_types::U128;
+use near_sdk::{assert_one_yocto, env, near_bindgen, AccountId, Balance};
 
 pub trait FungibleTokenPrice {
     fn ft_price(&self) -> U128;
@@ -12,3 +15,85 @@ impl FungibleTokenPrice for LiquidStakingContract {
         amount.into()
     }
 }
+
+#[near_bindgen]
+impl LiquidStakingContract {
+    /// Burn the LiNEAR of the predecessor without unstaking the underlying NEAR,
+    /// so the LiNEAR price rises for all holders. This cannot be reverted.
+    /// Requires exactly 1 yocto NEAR.
+    #[payable]
+    pub fn burn_to_pool(&mut self, amount: U128) {
+        assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+        self.internal_burn_to_pool(&account_id, &account_id, amount.into());
+    }
+}
+
+impl LiquidStakingContract {
+    /// Burn `amount` of LiNEAR held by `owner_id` on behalf of `account_id`
+    /// without unstaking. At least the same amount of LiNEAR should be left.
+    pub(crate) fn internal_burn_to_pool(
+        &mut self,
+        owner_id: &AccountId,
+        account_id: &AccountId,
+        amount: Balance,
+    ) {
+        require!(amount > 0, ERR_NON_POSITIVE_BURN_AMOUNT);
+        // burning (almost) all the shares would make the LiNEAR price unbounded
+        require!(
+            self.total_share_amount.saturating_sub(amount) >= amount,
+            ERR_BURN_TOO_MANY_SHARES
+        );
+
+        let owner_shares = self.internal_get_account(owner_id).stake_shares;
+        self.internal_ft_withdraw(owner_id, None, amount);
+        self.internal_checkpoint_total_supply();
+        // the burnt LiNEAR is given away, so nothing is received for it
+        self.internal_record_shares_out(owner_id, amount, 0, owner_shares);
+
+        FtBurn {
+            owner_id,
+            amount: &U128(amount),
+            memo: Some("burn to pool"),
+        }
+        .emit();
+        Event::BurnToPool {
+            account_id,
+            burnt_stake_shares: &U128(amount),
+            new_total_share_amount: &U128(self.total_share_amount),
+        }
+        .emit();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use near_sdk::test_utils::accounts;
+
+    use super::*;
+    use crate::test_utils::*;
+
+    fn new_contract_with_shares(shares: Balance) -> LiquidStakingContract {
+        let mut contract = new_contract();
+        contract.internal_register_account(&accounts(2));
+        contract.internal_ft_deposit(&accounts(2), None, shares);
+        contract.total_staked_near_amount += shares;
+        set_context(&accounts(2), 1);
+        contract
+    }
+
+    #[test]
+    fn burn_to_pool_raises_price() {
+        let mut contract = new_contract_with_shares(10 * ONE_NEAR);
+        contract.burn_to_pool(U128(5 * ONE_NEAR));
+        assert_eq!(contract.total_share_amount, 15 * ONE_NEAR);
+        assert!(contract.ft_price().0 > ONE_NEAR);
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot burn more than half of the total LiNEAR supply at once")]
+    fn burn_too_many_shares_to_pool() {
+        let mut contract = new_contract_with_shares(100 * ONE_NEAR);
+        contract.burn_to_pool(U128(60 * ONE_NEAR));
+    }
+}
@@ -1,9 +1,13 @@
+mod allowance;
 mod core;
 mod custom;
 mod metadata;
+mod receiver;
 mod storage;
 
+pub use self::allowance::*;
 pub use self::core::*;
 pub use self::custom::*;
 pub use self::metadata::*;
+pub use self::receiver::*;
 pub use self::storage::*;
@@ -0,0 +1,186 @@
+use crate::*;
+use near_contract_standards::fungible_token::receiver::FungibleTokenReceiver;
+use near_sdk::json_types::U128;
+use near_sdk::{env, near_bindgen, AccountId, PromiseOrValue};
+
+/// The message of LiNEAR `ft_transfer_call()` to this contract itself, e.g.
+/// `{"action":"unstake"}`
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum TransferCallMessage {
+    /// Burn the received LiNEAR and unstake NEAR for `receiver_id`, or the sender if not
+    /// given, which is registered if not yet. The unstaked NEAR is available for withdrawal
+    /// after the unstaking delay. The receiver must be allowed by the withdrawal allowlist
+    /// of the sender, and the unstake must not delay the withdrawal of the unstaked NEAR
+    /// that another receiver already has.
+    Unstake { receiver_id: Option<AccountId> },
+    /// Burn the received LiNEAR without unstaking, which raises the LiNEAR price
+    /// for all holders. See `burn_to_pool()`.
+    BurnToPool,
+}
+
+#[near_bindgen]
+impl FungibleTokenReceiver for LiquidStakingContract {
+    /// Handles LiNEAR transferred to this contract via `ft_transfer_call()`.
+    /// Panics if the message is invalid or the action fails, then the LiNEAR is
+    /// refunded to the sender by `ft_resolve_transfer()`.
+    ///
+    /// wNEAR transferred from the configured wNEAR contract is unwrapped and staked
+    /// for the sender, the message is ignored. The wNEAR is refunded if unwrapping fails.
+    fn ft_on_transfer(
+        &mut self,
+        sender_id: AccountId,
+        amount: U128,
+        msg: String,
+    ) -> PromiseOrValue<U128> {
+        let predecessor_id = env::predecessor_account_id();
+        if self.wnear_account_id.as_ref() == Some(&predecessor_id) {
+            return self.internal_deposit_wnear(sender_id, amount.0);
+        }
+
+        let contract_id = env::current_account_id();
+        require!(predecessor_id == contract_id, ERR_NOT_SELF_TRANSFER_CALL);
+        let message: TransferCallMessage =
+            near_sdk::serde_json::from_str(&msg).expect(ERR_INVALID_TRANSFER_CALL_MSG);
+
+        match message {
+            TransferCallMessage::Unstake { receiver_id } => {
+                let receiver_id = receiver_id.unwrap_or_else(|| sender_id.clone());
+                self.assert_allowed_receiver(&sender_id, &receiver_id);
+                let prev_receiver = self.internal_find_account(&receiver_id);
+                if prev_receiver.is_none() {
+                    self.internal_register_account(&receiver_id);
+                }
+                self.internal_ft_transfer(&contract_id, None, &receiver_id, None, amount.0, None);
+                // rounding dust of the shares is left in the receiver account, the same as `unstake_all()`
+                let unstake_amount = self.staked_amount_from_num_shares_rounded_down(amount.0);
+                self.internal_unstake(&receiver_id, None, unstake_amount);
+
+                // others could otherwise keep the receiver from withdrawing by unstaking dust
+                if receiver_id != sender_id {
+                    if let Some(prev_receiver) =
+                        prev_receiver.filter(|account| account.unstaked > 0)
+                    {
+                        require!(
+                            self.internal_get_account(&receiver_id)
+                                .unstaked_available_epoch_height
+                                <= prev_receiver.unstaked_available_epoch_height,
+                            ERR_UNSTAKE_DELAYS_RECEIVER
+                        );
+                    }
+                }
+            }
+            TransferCallMessage::BurnToPool => {
+                self.internal_burn_to_pool(&contract_id, &sender_id, amount.0);
+            }
+        }
+
+        // all the LiNEAR is used
+        PromiseOrValue::Value(U128(0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use near_contract_standards::fungible_token::core::FungibleTokenCore;
+    use near_sdk::test_utils::accounts;
+
+    use super::*;
+    use crate::test_utils::*;
+
+    fn new_contract_with_received_shares(shares: Balance) -> LiquidStakingContract {
+        let mut contract = new_contract();
+        let alice = accounts(2);
+        contract.internal_register_account(&alice);
+        contract.internal_ft_deposit(&alice, None, shares);
+        // keep the LiNEAR price at 1 NEAR
+        contract.total_staked_near_amount += shares;
+        // the transfer call to this contract
+        contract.internal_register_account(&accounts(0));
+        contract.internal_ft_transfer(&alice, None, &accounts(0), None, shares, None);
+        set_context(&accounts(0), 0);
+        contract
+    }
+
+    #[test]
+    fn unstake_on_transfer() {
+        let mut contract = new_contract_with_received_shares(ONE_NEAR);
+        let total_share_amount = contract.total_share_amount;
+        let msg = format!(r#"{{"action":"unstake","receiver_id":"{}"}}"#, accounts(2));
+        match contract.ft_on_transfer(accounts(2), U128(ONE_NEAR), msg) {
+            PromiseOrValue::Value(unused) => assert_eq!(unused.0, 0),
+            _ => panic!("unexpected promise"),
+        }
+
+        assert_eq!(contract.ft_balance_of(accounts(0)).0, 0);
+        assert_eq!(contract.ft_balance_of(accounts(2)).0, 0);
+        assert_eq!(contract.total_share_amount, total_share_amount - ONE_NEAR);
+        assert_eq!(
+            contract.internal_get_account(&accounts(2)).unstaked,
+            ONE_NEAR
+        );
+    }
+
+    #[test]
+    fn unstake_on_transfer_for_other_account() {
+        let mut contract = new_contract_with_received_shares(ONE_NEAR);
+        let msg = format!(r#"{{"action":"unstake","receiver_id":"{}"}}"#, accounts(3));
+        contract.ft_on_transfer(accounts(2), U128(ONE_NEAR), msg);
+
+        // the receiver is registered and receives the unstaked NEAR
+        let receiver = contract.internal_get_account(&accounts(3));
+        assert_eq!(receiver.unstaked, ONE_NEAR);
+        assert_eq!(receiver.stake_shares, 0);
+        assert_eq!(contract.internal_get_account(&accounts(2)).unstaked, 0);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "The unstake would delay the withdrawal of the unstaked NEAR of the receiver"
+    )]
+    fn unstake_on_transfer_cannot_delay_other_account() {
+        let mut contract = new_contract_with_received_shares(ONE_NEAR);
+        let receiver = Account {
+            unstaked: ONE_NEAR,
+            unstaked_available_epoch_height: get_epoch_height(),
+            ..Default::default()
+        };
+        contract.internal_save_account(&accounts(3), &receiver);
+
+        let msg = format!(r#"{{"action":"unstake","receiver_id":"{}"}}"#, accounts(3));
+        contract.ft_on_transfer(accounts(2), U128(ONE_NEAR), msg);
+    }
+
+    #[test]
+    fn burn_to_pool_on_transfer() {
+        let mut contract = new_contract_with_received_shares(ONE_NEAR);
+        let total_share_amount = contract.total_share_amount;
+        let total_staked_near_amount = contract.total_staked_near_amount;
+        let msg = r#"{"action":"burn_to_pool"}"#.to_string();
+        contract.ft_on_transfer(accounts(2), U128(ONE_NEAR), msg);
+
+        assert_eq!(contract.ft_balance_of(accounts(0)).0, 0);
+        assert_eq!(contract.total_share_amount, total_share_amount - ONE_NEAR);
+        assert_eq!(contract.total_staked_near_amount, total_staked_near_amount);
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid transfer call message")]
+    fn invalid_transfer_call_msg() {
+        let mut contract = new_contract_with_received_shares(ONE_NEAR);
+        contract.ft_on_transfer(accounts(2), U128(ONE_NEAR), "unstake".to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "Only LiNEAR or wNEAR transferred to this contract can be received")]
+    fn receive_from_other_token() {
+        let mut contract = new_contract_with_received_shares(ONE_NEAR);
+        set_context(&accounts(3), 0);
+        contract.ft_on_transfer(
+            accounts(2),
+            U128(ONE_NEAR),
+            r#"{"action":"unstake"}"#.to_string(),
+        );
+    }
+}
@@ -3,43 +3,67 @@ use near_contract_standards::fungible_token::events::FtBurn;
 use near_contract_standards::storage_management::{
     StorageBalance, StorageBalanceBounds, StorageManagement,
 };
-use near_sdk::{assert_one_yocto, env, log, AccountId, Balance, Promise};
+use near_sdk::{assert_one_yocto, env, log, AccountId, Balance, Promise, StorageUsage};
 
-/// Temporarily set fixed storage amount to be compatible with standard FT implementation
-const STORAGE_AMOUNT_BYTES: usize = 125;
+/// The fixed storage size that accounts were charged for before the storage bounds were
+/// derived from the measured account size. Accounts registered before v1.7.0 paid the
+/// resulting 0.00125Ⓝ, so they are credited with it when moved to the latest format,
+/// and the rest is tracked as storage debt.
+const LEGACY_STORAGE_AMOUNT_BYTES: StorageUsage = 125;
 
 impl LiquidStakingContract {
-    /// Internal method that returns the Account ID and the balance in case the account was
-    /// unregistered.
-    #[allow(dead_code)]
+    /// Internal method that returns the Account ID and the burnt LiNEAR balance in case
+    /// the account was unregistered. Only the storage fee that was actually deposited
+    /// by the account will be refunded.
     pub(crate) fn internal_storage_unregister(
         &mut self,
         force: Option<bool>,
     ) -> Option<(AccountId, Balance)> {
         assert_one_yocto();
+        self.assert_running();
+        // removing an account moves the last one to its index, which could make
+        // `migrate_batch()` skip it
+        self.assert_not_migrating();
         let account_id = env::predecessor_account_id();
         let force = force.unwrap_or(false);
-        if let Some(account) = self.accounts.get(&account_id) {
+        if let Some(account) = self.internal_find_account(&account_id) {
+            // pending and withdrawable unstaked NEAR would be lost
             require!(account.unstaked == 0, ERR_UNREGISTER_POSITIVE_UNSTAKED);
             let balance = account.stake_shares;
-            if balance == 0 || force {
-                self.accounts.remove(&account_id);
+            require!(balance == 0 || force, ERR_UNREGISTER_POSITIVE_BALANCE);
+
+            if self.internal_is_omnibus(&account_id) {
+                self.internal_remove_omnibus_account(&account_id);
+            }
+            if balance > 0 {
+                // the burnt LiNEAR is given up, so nothing is received for it
+                self.internal_record_shares_out(&account_id, balance, 0, balance);
+            }
+            self.internal_remove_account(&account_id);
+            self.internal_remove_allowances(&account_id);
+            self.internal_remove_account_stats(&account_id);
+            self.internal_remove_auto_claim(&account_id);
+            self.lock_schedules.remove(&account_id);
+            self.internal_remove_withdrawal_allowlist(&account_id);
+            self.internal_remove_balance_checkpoints(&account_id);
+            if balance > 0 {
                 self.total_share_amount -= balance;
-                if balance > 0 {
-                    FtBurn {
-                        owner_id: &account_id,
-                        amount: &U128(balance),
-                        memo: Some("force storage unregister"),
-                    }
-                    .emit();
+                self.internal_checkpoint_total_supply();
+                FtBurn {
+                    owner_id: &account_id,
+                    amount: &U128(balance),
+                    memo: Some("force storage unregister"),
                 }
-                Promise::new(account_id.clone()).transfer(self.storage_balance_bounds().min.0 + 1);
-                Some((account_id, balance))
-            } else {
-                env::panic_str(
-                    "Can't unregister the account with the positive balance without force",
-                )
+                .emit();
+            }
+
+            // accounts registered on stake or beneficiary rewards never paid for storage
+            let storage_deposit = self.internal_get_storage_deposit(&account_id);
+            self.internal_set_storage_deposit(&account_id, 0);
+            if storage_deposit > 0 {
+                Promise::new(account_id.clone()).transfer(storage_deposit);
             }
+            Some((account_id, balance))
         } else {
             log!("The account {} is not registered", &account_id);
             None
@@ -50,32 +74,137 @@ impl LiquidStakingContract {
         &self,
         account_id: &AccountId,
     ) -> Option<StorageBalance> {
-        if self.accounts.get(account_id).is_some() {
+        if self.internal_find_account(account_id).is_some() {
+            let total = self.internal_get_storage_deposit(account_id);
             Some(StorageBalance {
-                total: self.storage_balance_bounds().min,
-                available: 0.into(),
+                total: total.into(),
+                available: total
+                    .saturating_sub(self.internal_account_min_storage_balance(account_id))
+                    .into(),
             })
         } else {
             None
         }
     }
 
+    /// The storage balance of one account without opt-in features, which is
+    /// the storage balance bounds for registration
+    pub(crate) fn internal_min_storage_balance(&self) -> Balance {
+        Balance::from(self.account_storage_usage) * env::storage_byte_cost()
+    }
+
+    /// The storage balance charged before the bounds were derived from the measured size
+    pub(crate) fn internal_legacy_storage_balance(&self) -> Balance {
+        Balance::from(LEGACY_STORAGE_AMOUNT_BYTES) * env::storage_byte_cost()
+    }
+
+    /// The storage balance that the given account should deposit, which includes
+    /// the storage of its allowances, lifetime counters, auto-claim, allowlist,
+    /// balance checkpoints and sub-accounts if enabled.
+    pub(crate) fn internal_account_min_storage_balance(&self, account_id: &AccountId) -> Balance {
+        let stats_storage_balance = if self.internal_has_account_stats(account_id) {
+            self.internal_account_stats_storage_cost()
+        } else {
+            0
+        };
+        let auto_claim_storage_balance = if self.internal_has_auto_claim(account_id) {
+            self.internal_auto_claim_storage_cost()
+        } else {
+            0
+        };
+        let allowlist_storage_balance = if self.internal_has_withdrawal_allowlist(account_id) {
+            self.internal_withdrawal_allowlist_storage_cost()
+        } else {
+            0
+        };
+        let checkpoints_storage_balance = if self.internal_has_balance_checkpoints(account_id) {
+            self.internal_balance_checkpoints_storage_cost()
+        } else {
+            0
+        };
+        self.internal_min_storage_balance()
+            + stats_storage_balance
+            + checkpoints_storage_balance
+            + auto_claim_storage_balance
+            + allowlist_storage_balance
+            + self.internal_omnibus_storage_balance(account_id)
+            + self.internal_allowances_storage_balance(account_id)
+    }
+
+    /// The storage balance that was actually deposited by the account.
+    pub(crate) fn internal_get_storage_deposit(&self, account_id: &AccountId) -> Balance {
+        self.storage_deposits.get(account_id).unwrap_or(0)
+    }
+
+    /// The storage balance that the account still needs to deposit, which is positive
+    /// for accounts registered without deposit or charged before the bounds increased.
+    pub(crate) fn internal_get_storage_debt(&self, account_id: &AccountId) -> Balance {
+        self.internal_account_min_storage_balance(account_id)
+            .saturating_sub(self.internal_get_storage_deposit(account_id))
+    }
+
+    pub(crate) fn internal_set_storage_deposit(&mut self, account_id: &AccountId, amount: Balance) {
+        let prev_amount = self.internal_get_storage_deposit(account_id);
+        self.total_storage_deposit = self.total_storage_deposit - prev_amount + amount;
+        if amount > 0 {
+            self.storage_deposits.insert(account_id, &amount);
+        } else {
+            self.storage_deposits.remove(account_id);
+        }
+    }
+
+    /// Add the storage cost of an opt-in feature to the storage deposit of the account,
+    /// which is paid by the attached deposit of the call enabling the feature. The exceeding
+    /// deposit is refunded, and the cost is refunded by `internal_refund_feature_storage()`
+    /// when the feature is disabled. The cost of each feature is derived from its storage
+    /// usage measured by `measure_account_storage_usage()`, so it follows the storage price.
+    pub(crate) fn internal_charge_feature_storage(
+        &mut self,
+        account_id: &AccountId,
+        storage_cost: Balance,
+        err_no_enough_deposit: &str,
+    ) {
+        let attached_deposit = env::attached_deposit();
+        require!(attached_deposit >= storage_cost, err_no_enough_deposit);
+        let storage_deposit = self.internal_get_storage_deposit(account_id);
+        self.internal_set_storage_deposit(account_id, storage_deposit + storage_cost);
+        let refund = attached_deposit - storage_cost;
+        if refund > 0 {
+            Promise::new(account_id.clone()).transfer(refund);
+        }
+    }
+
+    /// Refund the storage cost of a disabled feature from the storage deposit of the account.
+    /// The deposit is short if the account has storage debt, then only the deposit is refunded.
+    pub(crate) fn internal_refund_feature_storage(
+        &mut self,
+        account_id: &AccountId,
+        storage_cost: Balance,
+    ) {
+        let storage_deposit = self.internal_get_storage_deposit(account_id);
+        let refund = std::cmp::min(storage_deposit, storage_cost);
+        self.internal_set_storage_deposit(account_id, storage_deposit - refund);
+        if refund > 0 {
+            Promise::new(account_id.clone()).transfer(refund);
+        }
+    }
+
     pub(crate) fn internal_register_account(&mut self, account_id: &AccountId) {
-        if self
-            .accounts
-            .insert(account_id, &Account::default())
-            .is_some()
-        {
+        if self.internal_find_account(account_id).is_some() {
             env::panic_str("The account is already registered");
         }
+        self.internal_save_account(account_id, &Account::default());
     }
 }
 
 #[allow(unused_variables)]
 #[near_bindgen]
 impl StorageManagement for LiquidStakingContract {
-    // `registration_only` doesn't affect the implementation for vanilla fungible token.
-    #[allow(unused_variables)]
+    /// Register the account, or top up the storage balance of a registered account
+    /// which has storage debt, i.e. registered without deposit or charged before
+    /// the storage bounds increased. Any deposit beyond the storage balance of an account
+    /// is refunded.
+    /// With `registration_only`, registered accounts are always refunded.
     #[payable]
     fn storage_deposit(
         &mut self,
@@ -84,19 +213,32 @@ impl StorageManagement for LiquidStakingContract {
     ) -> StorageBalance {
         let amount: Balance = env::attached_deposit();
         let account_id = account_id.unwrap_or_else(env::predecessor_account_id);
-        if let Some(account) = self.accounts.get(&account_id) {
-            log!("The account is already registered, refunding the deposit");
-            if amount > 0 {
-                Promise::new(env::predecessor_account_id()).transfer(amount);
+        if self.internal_find_account(&account_id).is_some() {
+            let storage_debt = if registration_only.unwrap_or(false) {
+                0
+            } else {
+                self.internal_get_storage_debt(&account_id)
+            };
+            let top_up = std::cmp::min(amount, storage_debt);
+            if top_up > 0 {
+                let storage_deposit = self.internal_get_storage_deposit(&account_id);
+                self.internal_set_storage_deposit(&account_id, storage_deposit + top_up);
+            } else {
+                log!("The account is already registered, refunding the deposit");
             }
-        } else {
-            let min_balance = self.storage_balance_bounds().min.0;
-            if amount < min_balance {
-                env::panic_str("The attached deposit is less than the minimum storage balance");
+            let refund = amount - top_up;
+            if refund > 0 {
+                Promise::new(env::predecessor_account_id()).transfer(refund);
             }
-
+        } else {
+            let storage_deposit = self.internal_min_storage_balance();
+            require!(
+                amount >= storage_deposit,
+                "The attached deposit is less than the minimum storage balance"
+            );
             self.internal_register_account(&account_id);
-            let refund = amount - min_balance;
+            self.internal_set_storage_deposit(&account_id, storage_deposit);
+            let refund = amount - storage_deposit;
             if refund > 0 {
                 Promise::new(env::predecessor_account_id()).transfer(refund);
             }
@@ -104,23 +246,32 @@ impl StorageManagement for LiquidStakingContract {
         self.internal_storage_balance_of(&account_id).unwrap()
     }
 
-    /// While storage_withdraw normally allows the caller to retrieve `available` balance, the basic
-    /// Fungible Token implementation sets storage_balance_bounds.min == storage_balance_bounds.max,
-    /// which means available balance will always be 0. So this implementation:
-    /// * panics if `amount > 0`
-    /// * never transfers Ⓝ to caller
-    /// * returns a `storage_balance` struct if `amount` is 0
+    /// Storage balance bounds are fixed for each account, so the available balance is
+    /// only positive when the account was charged before the storage bounds decreased.
+    /// This implementation:
+    /// * withdraws all the available balance if `amount` is not given
+    /// * panics if `amount` is greater than the available balance
+    /// * returns the updated `storage_balance` struct
     #[payable]
     fn storage_withdraw(&mut self, amount: Option<U128>) -> StorageBalance {
         assert_one_yocto();
         let predecessor_account_id = env::predecessor_account_id();
         if let Some(storage_balance) = self.internal_storage_balance_of(&predecessor_account_id) {
-            match amount {
-                Some(amount) if amount.0 > 0 => {
-                    env::panic_str("The amount is greater than the available storage balance");
-                }
-                _ => storage_balance,
+            let available = storage_balance.available.0;
+            let amount = amount.map(|amount| amount.0).unwrap_or(available);
+            require!(
+                amount <= available,
+                "The amount is greater than the available storage balance"
+            );
+            if amount > 0 {
+                self.internal_set_storage_deposit(
+                    &predecessor_account_id,
+                    storage_balance.total.0 - amount,
+                );
+                Promise::new(predecessor_account_id.clone()).transfer(amount);
             }
+            self.internal_storage_balance_of(&predecessor_account_id)
+                .unwrap()
         } else {
             env::panic_str(
                 format!("The account {} is not registered", &predecessor_account_id).as_str(),
@@ -128,18 +279,21 @@ impl StorageManagement for LiquidStakingContract {
         }
     }
 
-    /// Storage unregister is disabled because staking users don't need
-    /// to deposit but they are allowed to withdraw storage fee with
-    /// the current implementation.
+    /// Unregister the account and refund the storage deposit it has paid.
+    /// * panics if the account has unstaked balance, which should be withdrawn first
+    /// * panics if the account has LiNEAR balance and `force` is not true.
+    ///   Otherwise the LiNEAR balance will be burnt.
+    /// * returns false if the account is not registered
     #[payable]
     fn storage_unregister(&mut self, force: Option<bool>) -> bool {
-        panic!("Storage unregister is not supported yet.");
+        self.internal_storage_unregister(force).is_some()
     }
 
+    /// The storage balance of an account is the measured storage of one account.
+    /// The storage of opt-in features is not included, since it's paid when each feature
+    /// is enabled, see `internal_charge_feature_storage()`.
     fn storage_balance_bounds(&self) -> StorageBalanceBounds {
-        // let required_storage_balance =
-        //     Balance::from(self.account_storage_usage) * env::storage_byte_cost();
-        let required_storage_balance = STORAGE_AMOUNT_BYTES as Balance * env::storage_byte_cost();
+        let required_storage_balance = self.internal_min_storage_balance();
         StorageBalanceBounds {
             min: required_storage_balance.into(),
             max: Some(required_storage_balance.into()),
@@ -150,3 +304,173 @@ impl StorageManagement for LiquidStakingContract {
         self.internal_storage_balance_of(&account_id)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use near_sdk::test_utils::accounts;
+
+    use super::*;
+    use crate::test_utils::*;
+
+    fn register_with_shares(
+        contract: &mut LiquidStakingContract,
+        account_id: &AccountId,
+        shares: Balance,
+    ) {
+        contract.internal_register_account(account_id);
+        let mut account = contract.internal_get_account(account_id);
+        account.stake_shares = shares;
+        contract.internal_save_account(account_id, &account);
+        contract.total_share_amount += shares;
+    }
+
+    #[test]
+    fn unregister_account_with_storage_deposit() {
+        let mut contract = new_contract();
+        let alice = accounts(2);
+        let min_balance = contract.internal_min_storage_balance();
+
+        set_context(&alice, min_balance);
+        contract.storage_deposit(None, None);
+        assert_eq!(contract.storage_deposits.get(&alice), Some(min_balance));
+
+        set_context(&alice, 1);
+        assert!(contract.storage_unregister(None));
+        assert!(contract.internal_find_account(&alice).is_none());
+        assert!(contract.storage_deposits.get(&alice).is_none());
+
+        // not registered any more
+        assert!(!contract.storage_unregister(None));
+    }
+
+    #[test]
+    #[should_panic(expected = "The attached deposit is less than the minimum storage balance")]
+    fn register_with_legacy_storage_balance() {
+        let mut contract = new_contract();
+        let alice = accounts(2);
+        assert_eq!(
+            contract.storage_balance_bounds().min.0,
+            contract.internal_min_storage_balance()
+        );
+
+        // only accounts registered before v1.7.0 are credited with the legacy balance
+        set_context(&alice, contract.internal_legacy_storage_balance());
+        contract.storage_deposit(None, None);
+    }
+
+    #[test]
+    fn force_unregister_account_without_storage_deposit() {
+        let mut contract = new_contract();
+        let alice = accounts(2);
+        let total_share_amount = contract.total_share_amount;
+        register_with_shares(&mut contract, &alice, ONE_NEAR);
+
+        set_context(&alice, 1);
+        assert_eq!(
+            contract.internal_storage_unregister(Some(true)),
+            Some((alice.clone(), ONE_NEAR))
+        );
+        assert!(contract.internal_find_account(&alice).is_none());
+        assert_eq!(contract.total_share_amount, total_share_amount);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Can't unregister the account with the positive balance without force"
+    )]
+    fn unregister_account_with_positive_balance() {
+        let mut contract = new_contract();
+        let alice = accounts(2);
+        register_with_shares(&mut contract, &alice, ONE_NEAR);
+
+        set_context(&alice, 1);
+        contract.storage_unregister(None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot delete the account because the unstaked amount is not empty")]
+    fn unregister_account_with_positive_unstaked() {
+        let mut contract = new_contract();
+        let alice = accounts(2);
+        contract.internal_register_account(&alice);
+        let mut account = contract.internal_get_account(&alice);
+        account.unstaked = ONE_NEAR;
+        contract.internal_save_account(&alice, &account);
+
+        set_context(&alice, 1);
+        contract.storage_unregister(Some(true));
+    }
+
+    #[test]
+    fn top_up_storage_debt() {
+        let mut contract = new_contract();
+        let alice = accounts(2);
+        let min_balance = contract.internal_min_storage_balance();
+        // registered on stake without storage deposit
+        register_with_shares(&mut contract, &alice, ONE_NEAR);
+        assert_eq!(
+            contract.get_account_storage_debt(alice.clone()).0,
+            min_balance
+        );
+        assert_eq!(contract.get_storage_debt().storage_debt.0, min_balance);
+
+        // registration only doesn't top up
+        set_context(&alice, min_balance);
+        contract.storage_deposit(None, Some(true));
+        assert_eq!(contract.internal_get_storage_deposit(&alice), 0);
+
+        // the deposit beyond the debt is refunded
+        set_context(&alice, 2 * min_balance);
+        let storage_balance = contract.storage_deposit(None, None);
+        assert_eq!(storage_balance.total.0, min_balance);
+        assert_eq!(storage_balance.available.0, 0);
+        assert_eq!(contract.get_account_storage_debt(alice).0, 0);
+
+        let storage_debt = contract.get_storage_debt();
+        assert_eq!(storage_debt.accounts_num, 1);
+        assert_eq!(storage_debt.total_storage_deposit.0, min_balance);
+        assert_eq!(storage_debt.storage_debt.0, 0);
+    }
+
+    #[test]
+    fn withdraw_storage_balance_after_bounds_decreased() {
+        let mut contract = new_contract();
+        let alice = accounts(2);
+        let min_balance = contract.internal_min_storage_balance();
+        set_context(&alice, min_balance);
+        contract.storage_deposit(None, None);
+
+        // nothing to withdraw with the current bounds
+        set_context(&alice, 1);
+        assert_eq!(contract.storage_withdraw(None).total.0, min_balance);
+
+        contract.account_storage_usage -= 100;
+        let excess = 100 * env::storage_byte_cost();
+        assert_eq!(
+            contract
+                .storage_balance_of(alice.clone())
+                .unwrap()
+                .available
+                .0,
+            excess
+        );
+
+        let storage_balance = contract.storage_withdraw(None);
+        assert_eq!(storage_balance.total.0, min_balance - excess);
+        assert_eq!(storage_balance.available.0, 0);
+        assert_eq!(contract.total_storage_deposit, min_balance - excess);
+    }
+
+    #[test]
+    #[should_panic(expected = "The amount is greater than the available storage balance")]
+    fn withdraw_more_than_available_storage_balance() {
+        let mut contract = new_contract();
+        let alice = accounts(2);
+        let min_balance = contract.internal_min_storage_balance();
+        set_context(&alice, min_balance);
+        contract.storage_deposit(None, None);
+
+        set_context(&alice, 1);
+        contract.storage_withdraw(Some(1.into()));
+    }
+}
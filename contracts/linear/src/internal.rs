@@ -1,10 +1,35 @@
 use crate::events::Event;
 use crate::types::*;
+use crate::view::InvariantsReport;
 use crate::*;
 use near_contract_standards::fungible_token::events::{FtBurn, FtMint};
 use near_sdk::{log, Promise};
 use std::collections::HashMap;
 
+/// The calculated result of staking some amount of NEAR
+pub(crate) struct StakeQuote {
+    /// Number of "stake" shares minted to the account, rounded down
+    pub num_shares: ShareBalance,
+    /// Amount of NEAR charged from the account's unstaked balance, rounded down
+    pub charge_amount: Balance,
+    /// Amount of NEAR added to `total_staked_near_amount`, rounded up
+    pub stake_amount: Balance,
+}
+
+/// The calculated result of unstaking some amount of NEAR
+pub(crate) struct UnstakeQuote {
+    /// Number of "stake" shares burnt from the account, rounded up
+    pub num_shares: ShareBalance,
+    /// Amount of NEAR added to the account's unstaked balance, rounded up
+    pub receive_amount: Balance,
+    /// Amount of NEAR removed from `total_staked_near_amount`, rounded down
+    pub unstake_amount: Balance,
+    /// The epoch height when the unstaked NEAR could be withdrawn
+    pub unstaked_available_epoch_height: EpochHeight,
+    /// Whether the unstake is served by the liquidity reserve instead of validators
+    pub from_liquidity_reserve: bool,
+}
+
 impl LiquidStakingContract {
     /********************/
     /* Internal methods */
@@ -13,27 +38,51 @@ impl LiquidStakingContract {
         require!(!self.paused, ERR_PAUSED);
     }
 
-    pub(crate) fn internal_deposit(&mut self, amount: Balance) {
+    /// Deposit the amount into the inner account of `account_id`, which is registered
+    /// if not yet. The predecessor is the payer.
+    /// For omnibus accounts, the sub-account ledger is updated as well.
+    pub(crate) fn internal_deposit(
+        &mut self,
+        account_id: &AccountId,
+        sub_account_id: Option<&SubAccountId>,
+        amount: Balance,
+    ) {
         self.assert_running();
         require!(amount > 0, ERR_NON_POSITIVE_DEPOSIT_AMOUNT);
+        self.assert_min_deposit_amount(amount);
 
-        let account_id = env::predecessor_account_id();
-        let mut account = self.internal_get_account(&account_id);
+        let mut account = self.internal_get_account(account_id);
         account.unstaked += amount;
-        self.internal_save_account(&account_id, &account);
+        self.internal_save_account(account_id, &account);
+        self.internal_update_sub_account(account_id, sub_account_id, |sub_account| {
+            sub_account.unstaked += amount;
+        });
+        self.internal_record_deposit(account_id, amount);
 
+        let payer_id = env::predecessor_account_id();
         Event::Deposit {
-            account_id: &account_id,
+            account_id,
+            sub_account_id,
+            payer_id: (&payer_id != account_id).then_some(&payer_id),
             amount: &U128(amount),
             new_unstaked_balance: &U128(account.unstaked),
         }
         .emit();
     }
 
-    pub(crate) fn assert_can_withdraw(&self, account_id: &AccountId, amount: Balance) {
+    pub(crate) fn assert_can_withdraw(
+        &self,
+        account_id: &AccountId,
+        sub_account_id: Option<&SubAccountId>,
+        amount: Balance,
+    ) {
         require!(amount > 0, ERR_NON_POSITIVE_WITHDRAWAL_AMOUNT);
 
-        let account = self.internal_get_account(account_id);
+        // sub-accounts of the same omnibus account unstake at different epochs
+        let account = match sub_account_id {
+            Some(sub_account_id) => self.internal_get_sub_account(account_id, sub_account_id),
+            None => self.internal_get_account(account_id),
+        };
         require!(
             account.unstaked >= amount,
             ERR_NO_ENOUGH_UNSTAKED_BALANCE_TO_WITHDRAW
@@ -43,51 +92,96 @@ impl LiquidStakingContract {
             ERR_UNSTAKED_BALANCE_NOT_AVAILABLE
         );
         // Make sure the contract has enough NEAR for user to withdraw,
-        // Note that account locked balance should not be included.
-        let available_balance = env::account_balance();
-        // at least 1 NEAR should be left to cover storage/gas.
         require!(
-            available_balance.saturating_sub(CONTRACT_MIN_RESERVE_BALANCE) >= amount,
+            self.internal_contract_withdrawable_balance() >= amount,
             ERR_NO_ENOUGH_CONTRACT_BALANCE
         );
     }
 
-    pub(crate) fn internal_withdraw(&mut self, amount: Balance) {
+    /// The NEAR balance of this contract that could be withdrawn by users.
+    /// Note that account locked balance should not be included.
+    pub(crate) fn internal_contract_withdrawable_balance(&self) -> Balance {
+        // at least 1 NEAR should be left to cover storage/gas.
+        env::account_balance().saturating_sub(CONTRACT_MIN_RESERVE_BALANCE)
+    }
+
+    /// Withdraw the amount from the unstaked balance of `account_id` and
+    /// transfer it to `receiver_id`
+    pub(crate) fn internal_withdraw(
+        &mut self,
+        account_id: &AccountId,
+        sub_account_id: Option<&SubAccountId>,
+        receiver_id: &AccountId,
+        amount: Balance,
+    ) -> Promise {
         self.assert_running();
 
-        let account_id = env::predecessor_account_id();
-        self.assert_can_withdraw(&account_id, amount);
+        self.assert_can_withdraw(account_id, sub_account_id, amount);
+        self.assert_allowed_receiver(account_id, receiver_id);
 
-        let mut account = self.internal_get_account(&account_id);
+        let mut account = self.internal_get_account(account_id);
         account.unstaked -= amount;
-        self.internal_save_account(&account_id, &account);
+        self.internal_save_account(account_id, &account);
+        self.internal_update_sub_account(account_id, sub_account_id, |sub_account| {
+            sub_account.unstaked -= amount;
+        });
+        self.internal_record_withdraw(account_id, amount);
 
         Event::Withdraw {
-            account_id: &account_id,
+            account_id,
+            sub_account_id,
+            receiver_id: (receiver_id != account_id).then_some(receiver_id),
             amount: &U128(amount),
             new_unstaked_balance: &U128(account.unstaked),
         }
         .emit();
-        Promise::new(account_id).transfer(amount);
+        Promise::new(receiver_id.clone()).transfer(amount)
     }
 
-    pub(crate) fn internal_stake(&mut self, amount: Balance) -> ShareBalance {
-        self.assert_running();
-
-        require!(amount > 0, ERR_NON_POSITIVE_STAKING_AMOUNT);
+    /// Restore the unstaked balance of the account and its sub-account after the transfer
+    /// of a withdrawal failed, whose refund goes back to this contract. The sub-account
+    /// is created again if the withdrawal emptied it, and the account is queued again
+    /// for auto-claim. Returns the new unstaked balance of the account.
+    pub(crate) fn internal_restore_withdraw(
+        &mut self,
+        account_id: &AccountId,
+        sub_account_id: Option<&SubAccountId>,
+        amount: Balance,
+    ) -> Balance {
+        let mut account = self.internal_get_account(account_id);
+        account.unstaked += amount;
+        self.internal_save_account(account_id, &account);
+        if let Some(sub_account_id) = sub_account_id {
+            self.internal_restore_sub_account(account_id, sub_account_id);
+        }
+        self.internal_update_sub_account(account_id, sub_account_id, |sub_account| {
+            sub_account.unstaked += amount;
+        });
+        self.internal_record_withdraw_failed(account_id, amount);
+        // the queue entry was removed when the account was paid or withdrew
+        let epoch_height = account.unstaked_available_epoch_height;
+        self.internal_queue_claim(account_id, epoch_height, epoch_height);
+        account.unstaked
+    }
 
-        let account_id = env::predecessor_account_id();
-        let mut account = self.internal_get_account(&account_id);
+    /// Stake the amount from the unstaked balance of `account_id`.
+    /// The predecessor is the payer if it's not the account itself.
+    pub(crate) fn internal_stake(
+        &mut self,
+        account_id: &AccountId,
+        sub_account_id: Option<&SubAccountId>,
+        amount: Balance,
+    ) -> ShareBalance {
+        self.assert_running();
 
-        // Calculate the number of "stake" shares that the account will receive for staking the
-        // given amount.
-        let num_shares = self.num_shares_from_staked_amount_rounded_down(amount);
-        require!(num_shares > 0, ERR_NON_POSITIVE_CALCULATED_STAKING_SHARE);
-        // The amount of tokens the account will be charged from the unstaked balance.
-        // Rounded down to avoid overcharging the account to guarantee that the account can always
-        // unstake at least the same amount as staked.
-        let charge_amount = self.staked_amount_from_num_shares_rounded_down(num_shares);
-        require!(charge_amount > 0, ERR_NON_POSITIVE_CALCULATED_STAKED_AMOUNT);
+        self.assert_min_stake_amount(amount);
+        let mut account = self.internal_get_account(account_id);
+        let StakeQuote {
+            num_shares,
+            charge_amount,
+            stake_amount,
+        } = self.internal_quote_stake(amount);
+        self.assert_stake_limits(stake_amount);
 
         require!(
             account.unstaked >= charge_amount,
@@ -95,31 +189,44 @@ impl LiquidStakingContract {
         );
         account.unstaked -= charge_amount;
         account.stake_shares += num_shares;
-        self.internal_save_account(&account_id, &account);
-
-        // The staked amount that will be added to the total to guarantee the "stake" share price
-        // never decreases. The difference between `stake_amount` and `charge_amount` is paid
-        // from the allocated STAKE_SHARE_PRICE_GUARANTEE_FUND.
-        let stake_amount = self.staked_amount_from_num_shares_rounded_up(num_shares);
+        self.internal_save_account(account_id, &account);
+        self.internal_update_sub_account(account_id, sub_account_id, |sub_account| {
+            require!(
+                sub_account.unstaked >= charge_amount,
+                ERR_NO_ENOUGH_UNSTAKED_BALANCE
+            );
+            sub_account.unstaked -= charge_amount;
+            sub_account.stake_shares += num_shares;
+        });
+        self.internal_record_shares_in(account_id, num_shares, charge_amount);
 
         self.total_staked_near_amount += stake_amount;
         self.total_share_amount += num_shares;
+        self.internal_checkpoint_total_supply();
 
         // Increase requested stake amount within the current epoch
         self.epoch_requested_stake_amount += stake_amount;
 
+        let payer_id = env::predecessor_account_id();
+        let payer_id = (&payer_id != account_id).then_some(&payer_id);
         Event::Stake {
-            account_id: &account_id,
+            account_id,
+            sub_account_id,
+            payer_id,
             staked_amount: &U128(charge_amount),
             minted_stake_shares: &U128(num_shares),
             new_unstaked_balance: &U128(account.unstaked),
             new_stake_shares: &U128(account.stake_shares),
         }
         .emit();
+        let memo = match payer_id {
+            Some(payer_id) => format!("stake by {}", payer_id),
+            None => "stake".to_string(),
+        };
         FtMint {
-            owner_id: &account_id,
+            owner_id: account_id,
             amount: &U128(num_shares),
-            memo: Some("stake"),
+            memo: Some(&memo),
         }
         .emit();
         log!(
@@ -131,61 +238,92 @@ impl LiquidStakingContract {
         num_shares
     }
 
-    pub(crate) fn internal_unstake(&mut self, amount: u128) {
+    /// Unstake the amount from the staked balance of `account_id`
+    pub(crate) fn internal_unstake(
+        &mut self,
+        account_id: &AccountId,
+        sub_account_id: Option<&SubAccountId>,
+        amount: u128,
+    ) {
         self.assert_running();
 
-        require!(amount > 0, ERR_NON_POSITIVE_UNSTAKING_AMOUNT);
-
-        let account_id = env::predecessor_account_id();
-        let mut account = self.internal_get_account(&account_id);
+        let mut account = self.internal_get_account(account_id);
+        let UnstakeQuote {
+            num_shares,
+            receive_amount,
+            unstake_amount,
+            unstaked_available_epoch_height,
+            from_liquidity_reserve,
+        } = self.internal_quote_unstake(amount);
+        self.assert_unstake_limits(unstake_amount);
+        // the full balance of the sub-account is exempted, not the omnibus account
+        let stake_shares = match sub_account_id {
+            Some(sub_account_id) => {
+                self.internal_get_sub_account(account_id, sub_account_id)
+                    .stake_shares
+            }
+            None => account.stake_shares,
+        };
+        self.assert_min_unstake_amount(amount, stake_shares);
 
-        require!(
-            self.total_staked_near_amount > 0,
-            ERR_CONTRACT_NO_STAKED_BALANCE
-        );
-        // Calculate the number of shares required to unstake the given amount.
-        // NOTE: The number of shares the account will pay is rounded up.
-        let num_shares = self.num_shares_from_staked_amount_rounded_up(amount);
-        require!(num_shares > 0, ERR_NON_POSITIVE_CALCULATED_UNSTAKING_SHARE);
         require!(
             account.stake_shares >= num_shares,
             ERR_NO_ENOUGH_STAKED_BALANCE
         );
 
-        // Calculating the amount of tokens the account will receive by unstaking the corresponding
-        // number of "stake" shares, rounding up.
-        let receive_amount = self.staked_amount_from_num_shares_rounded_up(num_shares);
-        require!(
-            receive_amount > 0,
-            ERR_NON_POSITIVE_CALCULATED_STAKED_AMOUNT
+        self.internal_record_shares_out(
+            account_id,
+            num_shares,
+            receive_amount,
+            account.stake_shares,
         );
-
         account.stake_shares -= num_shares;
+        self.assert_not_locked(account_id, account.stake_shares);
         account.unstaked += receive_amount;
-        account.unstaked_available_epoch_height =
-            get_epoch_height() + self.validator_pool.get_num_epoch_to_unstake(amount);
-        if self.last_settlement_epoch == get_epoch_height() {
-            // The unstake request is received after epoch_cleanup
-            // so actual unstake will happen in the next epoch,
-            // which will put withdraw off for one more epoch.
-            account.unstaked_available_epoch_height += 1;
-        }
+        // the unstaked balance that is still locked is not released by a
+        // shorter unstake served from the liquidity reserve
+        let prev_unstaked_available_epoch_height = account.unstaked_available_epoch_height;
+        account.unstaked_available_epoch_height = std::cmp::max(
+            account.unstaked_available_epoch_height,
+            unstaked_available_epoch_height,
+        );
 
-        self.internal_save_account(&account_id, &account);
+        self.internal_save_account(account_id, &account);
+        self.internal_update_sub_account(account_id, sub_account_id, |sub_account| {
+            require!(
+                sub_account.stake_shares >= num_shares,
+                ERR_NO_ENOUGH_STAKED_BALANCE
+            );
+            sub_account.stake_shares -= num_shares;
+            sub_account.unstaked += receive_amount;
+            sub_account.unstaked_available_epoch_height = std::cmp::max(
+                sub_account.unstaked_available_epoch_height,
+                unstaked_available_epoch_height,
+            );
+        });
 
-        // The amount tokens that will be unstaked from the total to guarantee the "stake" share
-        // price never decreases. The difference between `receive_amount` and `unstake_amount` is
-        // paid from the allocated STAKE_SHARE_PRICE_GUARANTEE_FUND.
-        let unstake_amount = self.staked_amount_from_num_shares_rounded_down(num_shares);
+        self.internal_queue_claim(
+            account_id,
+            prev_unstaked_available_epoch_height,
+            account.unstaked_available_epoch_height,
+        );
 
         self.total_staked_near_amount -= unstake_amount;
         self.total_share_amount -= num_shares;
-
-        // Increase requested unstake amount within the current epoch
-        self.epoch_requested_unstake_amount += unstake_amount;
+        self.internal_checkpoint_total_supply();
+
+        if from_liquidity_reserve {
+            // Paid from the reserve, so no unstaking from validators is needed
+            self.liquidity_reserve_amount -= unstake_amount;
+            self.epoch_reserve_unstake_amount += unstake_amount;
+        } else {
+            // Increase requested unstake amount within the current epoch
+            self.epoch_requested_unstake_amount += unstake_amount;
+        }
 
         Event::Unstake {
-            account_id: &account_id,
+            account_id,
+            sub_account_id,
             unstaked_amount: &U128(receive_amount),
             burnt_stake_shares: &U128(num_shares),
             new_unstaked_balance: &U128(account.unstaked),
@@ -194,7 +332,7 @@ impl LiquidStakingContract {
         }
         .emit();
         FtBurn {
-            owner_id: &account_id,
+            owner_id: account_id,
             amount: &U128(num_shares),
             memo: Some("unstake"),
         }
@@ -206,6 +344,91 @@ impl LiquidStakingContract {
         );
     }
 
+    /// Calculates the result of staking `amount` of NEAR, which is shared by
+    /// `internal_stake()` and `preview_stake()`.
+    pub(crate) fn internal_quote_stake(&self, amount: Balance) -> StakeQuote {
+        require!(amount > 0, ERR_NON_POSITIVE_STAKING_AMOUNT);
+
+        // Calculate the number of "stake" shares that the account will receive for staking the
+        // given amount.
+        let num_shares = self.num_shares_from_staked_amount_rounded_down(amount);
+        require!(num_shares > 0, ERR_NON_POSITIVE_CALCULATED_STAKING_SHARE);
+        // The amount of tokens the account will be charged from the unstaked balance.
+        // Rounded down to avoid overcharging the account to guarantee that the account can always
+        // unstake at least the same amount as staked.
+        let charge_amount = self.staked_amount_from_num_shares_rounded_down(num_shares);
+        require!(charge_amount > 0, ERR_NON_POSITIVE_CALCULATED_STAKED_AMOUNT);
+
+        // The staked amount that will be added to the total to guarantee the "stake" share price
+        // never decreases. The difference between `stake_amount` and `charge_amount` is paid
+        // from the allocated STAKE_SHARE_PRICE_GUARANTEE_FUND.
+        let stake_amount = self.staked_amount_from_num_shares_rounded_up(num_shares);
+
+        StakeQuote {
+            num_shares,
+            charge_amount,
+            stake_amount,
+        }
+    }
+
+    /// Calculates the result of unstaking `amount` of NEAR, which is shared by
+    /// `internal_unstake()` and `preview_unstake()`.
+    pub(crate) fn internal_quote_unstake(&self, amount: Balance) -> UnstakeQuote {
+        require!(amount > 0, ERR_NON_POSITIVE_UNSTAKING_AMOUNT);
+        require!(
+            self.total_staked_near_amount > 0,
+            ERR_CONTRACT_NO_STAKED_BALANCE
+        );
+
+        // Calculate the number of shares required to unstake the given amount.
+        // NOTE: The number of shares the account will pay is rounded up.
+        let num_shares = self.num_shares_from_staked_amount_rounded_up(amount);
+        require!(num_shares > 0, ERR_NON_POSITIVE_CALCULATED_UNSTAKING_SHARE);
+
+        // Calculating the amount of tokens the account will receive by unstaking the corresponding
+        // number of "stake" shares, rounding up.
+        let receive_amount = self.staked_amount_from_num_shares_rounded_up(num_shares);
+        require!(
+            receive_amount > 0,
+            ERR_NON_POSITIVE_CALCULATED_STAKED_AMOUNT
+        );
+
+        // The amount tokens that will be unstaked from the total to guarantee the "stake" share
+        // price never decreases. The difference between `receive_amount` and `unstake_amount` is
+        // paid from the allocated STAKE_SHARE_PRICE_GUARANTEE_FUND.
+        let unstake_amount = self.staked_amount_from_num_shares_rounded_down(num_shares);
+
+        // The NEAR of the liquidity reserve is kept in the contract balance,
+        // so unstakes covered by it could be withdrawn immediately.
+        let from_liquidity_reserve = unstake_amount <= self.liquidity_reserve_amount;
+        let mut unstaked_available_epoch_height = get_epoch_height();
+        if !from_liquidity_reserve {
+            unstaked_available_epoch_height += self.validator_pool.get_num_epoch_to_unstake(amount);
+            if self.last_settlement_epoch == get_epoch_height() {
+                // The unstake request is received after epoch_cleanup
+                // so actual unstake will happen in the next epoch,
+                // which will put withdraw off for one more epoch.
+                unstaked_available_epoch_height += 1;
+            }
+        }
+
+        UnstakeQuote {
+            num_shares,
+            receive_amount,
+            unstake_amount,
+            unstaked_available_epoch_height,
+            from_liquidity_reserve,
+        }
+    }
+
+    /// The amount of NEAR the liquidity reserve is filled up to,
+    /// which is a ratio of `total_staked_near_amount`
+    pub(crate) fn internal_liquidity_reserve_target(&self) -> Balance {
+        (U256::from(self.total_staked_near_amount) * U256::from(self.liquidity_reserve_bps)
+            / U256::from(FULL_BASIS_POINTS))
+        .as_u128()
+    }
+
     /// Asserts that the method was called by the owner.
     pub(crate) fn assert_owner(&self) {
         require!(
@@ -242,6 +465,9 @@ impl LiquidStakingContract {
             // mint extra LiNEAR for him
             self.internal_mint_beneficiary_reward_shares(account_id, shares);
         }
+        if !hashmap.is_empty() {
+            self.internal_checkpoint_total_supply();
+        }
     }
 
     /// Mint new LiNEAR tokens to given account.
@@ -254,10 +480,10 @@ impl LiquidStakingContract {
         self.assert_running();
 
         // mint to account
-        if self.accounts.get(account_id).is_none() {
+        if self.internal_find_account(account_id).is_none() {
             self.internal_register_account(account_id);
         }
-        self.internal_ft_deposit(account_id, shares);
+        self.internal_ft_deposit(account_id, None, shares);
         FtMint {
             owner_id: account_id,
             amount: &U128(shares),
@@ -346,14 +572,150 @@ impl LiquidStakingContract {
         .as_u128()
     }
 
+    pub(crate) fn internal_check_invariants(&self) -> InvariantsReport {
+        let mut validators_staked_amount: Balance = 0;
+        let mut validators_unstaked_amount: Balance = 0;
+        let mut validators_total_base_stake_amount: Balance = 0;
+        let mut validators_total_weight: u64 = 0;
+        let mut executing_validators_num: u64 = 0;
+        for validator in self.validator_pool.validators.values() {
+            let validator: Validator = validator.into();
+            validators_staked_amount += validator.staked_amount;
+            // funds on draining validators will be restaked after drain withdraw,
+            // otherwise the unstaked funds will be withdrawn for the unstaked liabilities
+            if validator.draining {
+                validators_staked_amount += validator.unstaked_amount;
+            } else {
+                validators_unstaked_amount += validator.unstaked_amount;
+            }
+            validators_total_base_stake_amount += validator.base_stake_amount;
+            validators_total_weight += validator.weight as u64;
+            if validator.executing {
+                executing_validators_num += 1;
+            }
+        }
+
+        // NEAR that waits to be staked on validators, and that waits to be unstaked
+        // from validators for the unstaked liabilities
+        let pending_stake_amount = self.stake_amount_to_settle + self.epoch_requested_stake_amount;
+        let pending_unstake_amount =
+            self.unstake_amount_to_settle + self.epoch_requested_unstake_amount;
+
+        let accounted_near_amount = validators_staked_amount as i128
+            + self.liquidity_reserve_amount as i128
+            + pending_stake_amount as i128
+            - pending_unstake_amount as i128;
+        let staked_near_amount_consistent = accounted_near_amount >= 0
+            && abs_diff_eq(
+                accounted_near_amount as u128,
+                self.total_staked_near_amount,
+                MAX_ACCOUNTED_NEAR_AMOUNT_DIFF,
+            );
+
+        // the contract balance locked for storage cannot be withdrawn by accounts
+        let contract_available_balance = env::account_balance()
+            .saturating_sub(Balance::from(env::storage_usage()) * env::storage_byte_cost());
+        let unstaked_assets_amount = contract_available_balance as i128
+            + validators_unstaked_amount as i128
+            + pending_unstake_amount as i128
+            - pending_stake_amount as i128
+            - self.liquidity_reserve_amount as i128;
+        let unstaked_liabilities_covered = unstaked_assets_amount
+            + MAX_ACCOUNTED_NEAR_AMOUNT_DIFF as i128
+            >= self.total_unstaked_amount as i128;
+
+        let base_stake_amount_consistent =
+            validators_total_base_stake_amount == self.validator_pool.total_base_stake_amount;
+        let weight_consistent = validators_total_weight == self.validator_pool.total_weight as u64;
+        // price = total_staked_near_amount / total_share_amount >= 1
+        let ft_price_valid = self.total_staked_near_amount >= self.total_share_amount;
+
+        let identities_valid = base_stake_amount_consistent && weight_consistent && ft_price_valid;
+
+        InvariantsReport {
+            // the validator balances are not reliable while there are actions in flight
+            valid: executing_validators_num == 0
+                && staked_near_amount_consistent
+                && unstaked_liabilities_covered
+                && identities_valid,
+            identities_valid,
+            executing_validators_num,
+            total_staked_near_amount: self.total_staked_near_amount.into(),
+            accounted_near_amount: accounted_near_amount.into(),
+            staked_near_amount_consistent,
+            unstaked_liabilities_amount: self.total_unstaked_amount.into(),
+            unstaked_assets_amount: unstaked_assets_amount.into(),
+            unstaked_liabilities_covered,
+            total_base_stake_amount: self.validator_pool.total_base_stake_amount.into(),
+            validators_total_base_stake_amount: validators_total_base_stake_amount.into(),
+            base_stake_amount_consistent,
+            total_weight: self.validator_pool.total_weight,
+            validators_total_weight,
+            weight_consistent,
+            ft_price: self.ft_price(),
+            ft_price_valid,
+        }
+    }
+
+    /// Inner method to get the given account if it exists, either in the latest
+    /// format or the legacy format.
+    pub(crate) fn internal_find_account(&self, account_id: &AccountId) -> Option<Account> {
+        self.accounts
+            .get(account_id)
+            .map(|account| account.into())
+            .or_else(|| {
+                self.legacy_accounts
+                    .get(account_id)
+                    .map(|account| account.into())
+            })
+    }
+
     /// Inner method to get the given account or a new default value account.
     pub(crate) fn internal_get_account(&self, account_id: &AccountId) -> Account {
-        self.accounts.get(account_id).unwrap_or_default()
+        self.internal_find_account(account_id).unwrap_or_default()
     }
 
     /// Inner method to save the given account for a given account ID.
+    /// The account is always saved in the latest format.
     pub(crate) fn internal_save_account(&mut self, account_id: &AccountId, account: &Account) {
-        self.accounts.insert(account_id, account);
+        self.internal_checkpoint_balance(account_id, account.stake_shares);
+        let mut prev_unstaked = self
+            .accounts
+            .insert(account_id, &account.clone().into())
+            .map_or(0, |prev_account| Account::from(prev_account).unstaked);
+        if !self.legacy_accounts.is_empty() {
+            if let Some(legacy_account) = self.legacy_accounts.remove(account_id) {
+                // the unstaked balance of legacy accounts is counted in `migrate()`
+                prev_unstaked = legacy_account.unstaked;
+                // accounts registered before v1.7.0 paid the legacy storage balance
+                let storage_deposit = self.internal_get_storage_deposit(account_id);
+                self.internal_set_storage_deposit(
+                    account_id,
+                    storage_deposit + self.internal_legacy_storage_balance(),
+                );
+            }
+        }
+        self.total_unstaked_amount = self.total_unstaked_amount + account.unstaked - prev_unstaked;
+    }
+
+    /// Inner method to remove the given account in any format.
+    pub(crate) fn internal_remove_account(&mut self, account_id: &AccountId) -> Option<Account> {
+        let account: Option<Account> = self
+            .accounts
+            .remove(account_id)
+            .map(|account| account.into());
+        let legacy_account = if self.legacy_accounts.is_empty() {
+            None
+        } else {
+            self.legacy_accounts
+                .remove(account_id)
+                .map(|account| account.into())
+        };
+        let account = account.or(legacy_account);
+        if let Some(account) = &account {
+            self.total_unstaked_amount -= account.unstaked;
+        }
+        account
     }
 }
 
@@ -381,22 +743,10 @@ impl LiquidStakingContract {
 
 #[cfg(all(test, not(target_arch = "wasm32")))]
 mod tests {
-    use near_sdk::test_utils::{accounts, VMContextBuilder};
-    use near_sdk::testing_env;
+    use near_sdk::test_utils::accounts;
 
     use super::*;
-
-    fn new_contract() -> LiquidStakingContract {
-        let owner = accounts(1);
-        let mut context = VMContextBuilder::new();
-        context
-            .current_account_id(accounts(0))
-            .signer_account_id(owner.clone())
-            .predecessor_account_id(owner.clone())
-            .account_balance(20 * ONE_NEAR);
-        testing_env!(context.build());
-        LiquidStakingContract::new(owner)
-    }
+    use crate::test_utils::*;
 
     #[test]
     fn beneficiary_reward_mint_matches_existing_single_beneficiary_formula() {
@@ -438,4 +788,103 @@ mod tests {
             10 * ONE_NEAR + 2 * expected_shares
         );
     }
+
+    #[test]
+    fn check_invariants_detects_inconsistent_state() {
+        let mut contract = new_contract();
+        assert!(contract.internal_check_invariants().valid);
+
+        contract.total_staked_near_amount += 2 * MAX_ACCOUNTED_NEAR_AMOUNT_DIFF;
+        let report = contract.internal_check_invariants();
+        assert!(!report.valid);
+        assert!(report.identities_valid);
+        assert!(!report.staked_near_amount_consistent);
+        assert!(report.base_stake_amount_consistent);
+        assert!(report.weight_consistent);
+        assert!(report.ft_price_valid);
+
+        contract.total_staked_near_amount -= 2 * MAX_ACCOUNTED_NEAR_AMOUNT_DIFF;
+        contract.total_share_amount += ONE_NEAR;
+        let report = contract.internal_check_invariants();
+        assert!(!report.valid);
+        assert!(!report.identities_valid);
+        assert!(report.staked_near_amount_consistent);
+        assert!(!report.ft_price_valid);
+
+        // the amount to unstake exceeding the accounted amount is reported as negative
+        contract.total_share_amount -= ONE_NEAR;
+        contract.unstake_amount_to_settle = 100 * ONE_NEAR;
+        let report = contract.internal_check_invariants();
+        assert!(!report.valid);
+        assert!(!report.staked_near_amount_consistent);
+        assert!(report.accounted_near_amount.0 < 0);
+        contract.unstake_amount_to_settle = 0;
+
+        // the unstaked liabilities are not covered by the contract balance
+        contract.total_unstaked_amount = 100 * ONE_NEAR;
+        let report = contract.internal_check_invariants();
+        assert!(!report.valid);
+        assert!(report.staked_near_amount_consistent);
+        assert!(!report.unstaked_liabilities_covered);
+        contract.total_unstaked_amount = 0;
+
+        // the report is not valid while validators are executing actions
+        let mut validator = contract.validator_pool.add_validator(&accounts(2), 10);
+        validator.executing = true;
+        contract.validator_pool.save_validator(&validator);
+        let report = contract.internal_check_invariants();
+        assert!(!report.valid);
+        assert!(report.identities_valid);
+        assert_eq!(report.executing_validators_num, 1);
+    }
+
+    #[test]
+    fn previews_match_stake_and_unstake() {
+        let mut contract = new_contract();
+        let owner = accounts(1);
+        // make the LiNEAR price 1.1 NEAR so that the results are rounded
+        contract.total_staked_near_amount += ONE_NEAR;
+        contract.internal_deposit(&owner, None, 5 * ONE_NEAR);
+
+        let total_staked_near_amount = contract.total_staked_near_amount;
+        let preview = contract.preview_stake(U128(3 * ONE_NEAR));
+        contract.internal_stake(&owner, None, 3 * ONE_NEAR);
+        let account = contract.internal_get_account(&owner);
+        let minted_shares = preview.minted_stake_shares.0;
+        assert_eq!(account.stake_shares, minted_shares);
+        assert_eq!(account.unstaked, 5 * ONE_NEAR - preview.charged_amount.0);
+        // the charge is rounded down to the value of the minted shares, while the total staked
+        // amount grows by the rounded up value
+        assert_eq!(
+            preview.charged_amount.0,
+            contract.staked_amount_from_num_shares_rounded_down(minted_shares)
+        );
+        assert!(preview.charged_amount.0 <= 3 * ONE_NEAR);
+        assert_eq!(
+            contract.total_staked_near_amount,
+            total_staked_near_amount
+                + contract.staked_amount_from_num_shares_rounded_up(minted_shares)
+        );
+
+        let preview = contract.preview_unstake(U128(ONE_NEAR), Some(owner.clone()));
+        contract.internal_unstake(&owner, None, ONE_NEAR);
+        let unstaked = account.unstaked;
+        let account = contract.internal_get_account(&owner);
+        assert_eq!(
+            account.stake_shares,
+            minted_shares - preview.burnt_stake_shares.0
+        );
+        assert_eq!(account.unstaked, unstaked + preview.received_amount.0);
+        assert_eq!(
+            account.unstaked_available_epoch_height,
+            preview.unstaked_available_epoch_height
+        );
+
+        let preview = contract.preview_unstake_shares(U128(account.stake_shares), None);
+        assert!(preview.burnt_stake_shares.0 <= account.stake_shares);
+
+        let preview = contract.preview_withdraw(owner);
+        assert_eq!(preview.unstaked_balance.0, account.unstaked);
+        assert_eq!(preview.withdrawable_amount.0, 0);
+    }
 }
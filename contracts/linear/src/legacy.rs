@@ -13,6 +13,7 @@ use near_sdk::{
     serde::{Deserialize, Serialize},
     AccountId, Balance, EpochHeight, StorageUsage, Timestamp,
 };
+use std::collections::HashMap;
 
 /// Changes to root state in v1.6.0:
 /// - removed liquidity_pool
@@ -21,48 +22,71 @@ use near_sdk::{
 #[derive(BorshDeserialize, BorshSerialize)]
 pub struct ContractV1_6_0 {
     /// The account ID of the owner
-    owner_id: AccountId,
+    pub owner_id: AccountId,
     /// The accounts that are able to change key parameters and settings in the contract such as validator pool membership
-    managers: UnorderedSet<AccountId>,
+    pub managers: UnorderedSet<AccountId>,
     /// The account ID of the treasury that manages portion of the received fees and rewards.
-    treasury_id: AccountId,
+    pub treasury_id: AccountId,
     /// Total amount of LiNEAR that was minted (minus burned).
-    total_share_amount: ShareBalance,
+    pub total_share_amount: ShareBalance,
     /// Total amount of NEAR that was staked by users to this contract.         
     ///
     /// This is effectively 1) amount of NEAR that was deposited to this contract but hasn't yet been staked on any validators
     /// plus 2) amount of NEAR that has already been staked on validators.    
     /// Note that the amount of NEAR that is pending release or is already released by hasn't been withdrawn is not considered.
-    total_staked_near_amount: Balance,
+    pub total_staked_near_amount: Balance,
     /// Persistent map from an account ID to the corresponding account.
-    accounts: UnorderedMap<AccountId, Account>,
+    pub accounts: UnorderedMap<AccountId, AccountV1_6_0>,
     /// Pause the contract for maintenance, all user interactions are stopped. Only the owner can perform pause and resume.
     /// It doesn't affect the staking shares or reward distribution.
     /// The contract is not paused by default.
-    paused: bool,
+    pub paused: bool,
 
     /// The storage size in bytes for one account.
-    account_storage_usage: StorageUsage,
+    pub account_storage_usage: StorageUsage,
 
     /// Beneficiaries for staking rewards.
-    beneficiaries: UnorderedMap<AccountId, u32>,
+    pub beneficiaries: UnorderedMap<AccountId, u32>,
 
     // --- Validator Pool ---
     /// The validator pool that manage the actions against validators
-    validator_pool: ValidatorPool,
+    pub validator_pool: ValidatorPool,
     /// The whitelist contract ID, which controls the staking pool whitelist.
-    whitelist_account_id: Option<AccountId>,
+    pub whitelist_account_id: Option<AccountId>,
     /// Amount of NEAR that is requested to stake by all users during the last epoch
-    epoch_requested_stake_amount: Balance,
+    pub epoch_requested_stake_amount: Balance,
     /// Amount of NEAR that is requested to unstake by all users during the last epoch
-    epoch_requested_unstake_amount: Balance,
+    pub epoch_requested_unstake_amount: Balance,
 
     /// Amount of NEAR that needs to be settled by staking on validators
-    stake_amount_to_settle: Balance,
+    pub stake_amount_to_settle: Balance,
     /// Amount of NEAR that needs to be settled by unstaking from validators
-    unstake_amount_to_settle: Balance,
+    pub unstake_amount_to_settle: Balance,
     /// Last epoch height stake/unstake settlements were calculated
-    last_settlement_epoch: EpochHeight,
+    pub last_settlement_epoch: EpochHeight,
+}
+
+/// The Account struct until v1.6.0, which still carries the deprecated staking farm fields.
+/// Accounts have been stored as compact `VersionedAccount` since then.
+#[derive(BorshDeserialize, BorshSerialize, Default)]
+pub struct AccountV1_6_0 {
+    pub unstaked: Balance,
+    pub stake_shares: ShareBalance,
+    pub unstaked_available_epoch_height: EpochHeight,
+    /// [DEPRECATED] Farmed tokens that can be withdrawn from the farm.
+    pub amounts: HashMap<AccountId, Balance>,
+    /// [DEPRECATED] Last claimed reward for each active farm.
+    pub last_farm_reward_per_share: HashMap<u64, U256>,
+}
+
+impl From<AccountV1_6_0> for Account {
+    fn from(a: AccountV1_6_0) -> Self {
+        Account {
+            unstaked: a.unstaked,
+            stake_shares: a.stake_shares,
+            unstaked_available_epoch_height: a.unstaked_available_epoch_height,
+        }
+    }
 }
 
 /// The ValidatorPool struct has no change in v1.4.0 since v1.3.0
@@ -130,7 +154,7 @@ pub struct ContractV1_3_0 {
     /// Note that the amount of NEAR that is pending release or is already released by hasn't been withdrawn is not considered.
     pub total_staked_near_amount: Balance,
     /// Persistent map from an account ID to the corresponding account.
-    pub accounts: UnorderedMap<AccountId, Account>,
+    pub accounts: UnorderedMap<AccountId, AccountV1_6_0>,
     /// Pause the contract for maintenance, all user interactions are stopped. Only the owner can perform pause and resume.
     /// It doesn't affect the staking shares or reward distribution.
     /// The contract is not paused by default.
@@ -238,7 +262,7 @@ pub struct ContractV1_1_0 {
     /// Note that the amount of NEAR that is pending release or is already released by hasn't been withdrawn is not considered.
     pub total_staked_near_amount: Balance,
     /// Persistent map from an account ID to the corresponding account.
-    pub accounts: UnorderedMap<AccountId, Account>,
+    pub accounts: UnorderedMap<AccountId, AccountV1_6_0>,
     /// Whether the staking is paused.
     /// When paused, the account unstakes everything (stakes 0) and doesn't restake.
     /// It doesn't affect the staking shares or reward distribution.
@@ -304,7 +328,7 @@ pub struct ContractV1_0_0 {
     /// Note that the amount of NEAR that is pending release or is already released by hasn't been withdrawn is not considered.
     pub total_staked_near_amount: Balance,
     /// Persistent map from an account ID to the corresponding account.
-    pub accounts: UnorderedMap<AccountId, Account>,
+    pub accounts: UnorderedMap<AccountId, AccountV1_6_0>,
     /// Whether the staking is paused.
     /// When paused, the account unstakes everything (stakes 0) and doesn't restake.
     /// It doesn't affect the staking shares or reward distribution.
@@ -2,7 +2,7 @@
 
 use near_sdk::{
     borsh::{self, BorshDeserialize, BorshSerialize},
-    collections::{UnorderedMap, UnorderedSet},
+    collections::{LookupMap, LookupSet, TreeMap, UnorderedMap, UnorderedSet},
     env, ext_contract,
     json_types::U128,
     near_bindgen, require,
@@ -11,25 +11,45 @@ use near_sdk::{
 };
 
 mod account;
+mod checkpoints;
+mod claim;
 mod epoch_actions;
 mod errors;
 mod events;
 mod fungible_token;
 mod internal;
 mod legacy;
+mod limits;
 mod metadata;
+mod migration;
+mod omnibus;
 mod owner;
+mod security;
 mod stake;
+mod stats;
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod test_utils;
+mod timelock;
 mod types;
 mod upgrade;
 mod utils;
 mod validator_pool;
 mod view;
+mod wnear;
 
 use crate::account::*;
+use crate::checkpoints::*;
 use crate::errors::*;
 use crate::fungible_token::*;
+use crate::legacy::AccountV1_6_0;
+use crate::limits::*;
+use crate::migration::*;
+use crate::omnibus::*;
+use crate::security::*;
+use crate::stats::*;
+use crate::timelock::*;
 use crate::types::*;
+use crate::upgrade::*;
 use crate::utils::*;
 use crate::validator_pool::*;
 
@@ -49,6 +69,20 @@ pub(crate) enum StorageKey {
     AuthorizedFarmTokens,
     Managers,
     ValidatorsV1, // Used in v1.3.0 upgrade
+    StagedCode,
+    AccountsV1,
+    StorageDeposits,
+    Allowances,
+    AccountStats,
+    OmnibusAccounts,
+    SubAccounts,
+    AutoClaimAccounts,
+    ClaimQueue,
+    LockSchedules,
+    WithdrawalAllowlists,
+    BalanceCheckpoints,
+    TotalSupplyCheckpoints,
+    AllowanceSpenders,
 }
 
 #[near_bindgen]
@@ -69,7 +103,53 @@ pub struct LiquidStakingContract {
     /// Note that the amount of NEAR that is pending release or is already released by hasn't been withdrawn is not considered.
     total_staked_near_amount: Balance,
     /// Persistent map from an account ID to the corresponding account.
-    accounts: UnorderedMap<AccountId, Account>,
+    accounts: UnorderedMap<AccountId, VersionedAccount>,
+    /// Accounts saved by v1.6.0, which are moved to `accounts` when saved or migrated.
+    legacy_accounts: UnorderedMap<AccountId, AccountV1_6_0>,
+    /// Storage fee deposited by each account via `storage_deposit`. Accounts that are
+    /// registered on stake or beneficiary rewards don't have a record.
+    storage_deposits: LookupMap<AccountId, Balance>,
+    /// Total amount of storage fee recorded in `storage_deposits`
+    total_storage_deposit: Balance,
+    /// Total unstaked balance owed to accounts in the latest format, which
+    /// legacy accounts are added to once migrated
+    total_unstaked_amount: Balance,
+    /// LiNEAR allowances keyed by (owner, spender)
+    allowances: LookupMap<(AccountId, AccountId), Allowance>,
+    /// The spenders of the allowances of each owner
+    allowance_spenders: LookupMap<AccountId, Vec<AccountId>>,
+    /// Total number of allowances
+    allowances_num: u64,
+    /// Lifetime counters of the accounts that enabled them
+    account_stats: LookupMap<AccountId, AccountStats>,
+    /// Number of accounts that enabled lifetime counters
+    account_stats_num: u64,
+    /// Ledgers of omnibus accounts, whose balances are split into sub-accounts
+    omnibus_accounts: LookupMap<AccountId, OmnibusAccount>,
+    /// Number of omnibus accounts
+    omnibus_accounts_num: u64,
+    /// Sub-accounts keyed by (omnibus account, sub-account ID)
+    sub_accounts: LookupMap<(AccountId, SubAccountId), VersionedAccount>,
+    /// Total number of sub-accounts of all omnibus accounts
+    sub_accounts_num: u64,
+    /// Accounts that opted in to receive the unstaked NEAR automatically
+    auto_claim_accounts: LookupSet<AccountId>,
+    /// Number of accounts that opted in to auto-claim
+    auto_claim_accounts_num: u64,
+    /// Auto-claim accounts with unstaked NEAR, ordered by `unstaked_available_epoch_height`
+    claim_queue: TreeMap<(EpochHeight, AccountId), ()>,
+    /// Owner-created lock schedules of LiNEAR allocated to accounts
+    lock_schedules: LookupMap<AccountId, LockSchedule>,
+    /// Receiver allowlists of the accounts that enabled the security mode
+    withdrawal_allowlists: LookupMap<AccountId, WithdrawalAllowlist>,
+    /// Number of accounts that paid the storage of an allowlist
+    withdrawal_allowlists_num: u64,
+    /// Recent LiNEAR balance checkpoints of the accounts that opted in
+    balance_checkpoints: LookupMap<AccountId, BalanceCheckpoints>,
+    /// Number of accounts that opted in to balance checkpoints
+    balance_checkpoints_num: u64,
+    /// The total LiNEAR supply at the end of each epoch in which it changed
+    total_supply_checkpoints: TreeMap<EpochHeight, ShareBalance>,
     /// Pause the contract for maintenance, all user interactions are stopped. Only the owner can perform pause and resume.
     /// It doesn't affect the staking shares or reward distribution.
     /// The contract is not paused by default.
@@ -77,6 +157,20 @@ pub struct LiquidStakingContract {
 
     /// The storage size in bytes for one account.
     account_storage_usage: StorageUsage,
+    /// The storage size in bytes for one allowance.
+    allowance_storage_usage: StorageUsage,
+    /// The storage size in bytes for the lifetime counters of one account.
+    account_stats_storage_usage: StorageUsage,
+    /// The storage size in bytes for the ledger of one omnibus account.
+    omnibus_account_storage_usage: StorageUsage,
+    /// The storage size in bytes for one sub-account.
+    sub_account_storage_usage: StorageUsage,
+    /// The storage size in bytes for the auto-claim flag and queue entry of one account.
+    auto_claim_storage_usage: StorageUsage,
+    /// The storage size in bytes for the full allowlist and pending change of one account.
+    withdrawal_allowlist_storage_usage: StorageUsage,
+    /// The storage size in bytes for the full balance checkpoints of one account.
+    balance_checkpoints_storage_usage: StorageUsage,
 
     /// Beneficiaries for staking rewards.
     beneficiaries: UnorderedMap<AccountId, u32>,
@@ -86,10 +180,27 @@ pub struct LiquidStakingContract {
     validator_pool: ValidatorPool,
     /// The whitelist contract ID, which controls the staking pool whitelist.
     whitelist_account_id: Option<AccountId>,
+    /// The wNEAR token contract ID. wNEAR transferred to this contract is unwrapped and staked.
+    wnear_account_id: Option<AccountId>,
     /// Amount of NEAR that is requested to stake by all users during the last epoch
     epoch_requested_stake_amount: Balance,
     /// Amount of NEAR that is requested to unstake by all users during the last epoch
     epoch_requested_unstake_amount: Balance,
+    /// Amount of NEAR that is unstaked from the liquidity reserve by all users during the
+    /// last epoch, which is counted toward the per-epoch unstake limit
+    epoch_reserve_unstake_amount: Balance,
+    /// Owner-configurable limits of the total staked amount and the per-epoch
+    /// stake and unstake amounts
+    stake_limits: StakeLimits,
+    /// Owner-configurable minimum amounts of deposit, stake and unstake
+    min_amounts: MinAmounts,
+    /// Amount of NEAR that is counted in `total_staked_near_amount` but kept in the
+    /// contract balance, from which unstakes are served without waiting for validators
+    liquidity_reserve_amount: Balance,
+    /// The target ratio of `liquidity_reserve_amount` to `total_staked_near_amount`
+    /// in basis points, which is filled up from the amount to stake in epoch cleanup.
+    /// See `set_liquidity_reserve_bps()` for its cost to the LiNEAR APY
+    liquidity_reserve_bps: u32,
 
     /// Amount of NEAR that needs to be settled by staking on validators
     stake_amount_to_settle: Balance,
@@ -97,6 +208,12 @@ pub struct LiquidStakingContract {
     unstake_amount_to_settle: Balance,
     /// Last epoch height stake/unstake settlements were calculated
     last_settlement_epoch: EpochHeight,
+
+    /// The contract upgrade that has been staged and waits to be applied
+    staged_upgrade: Option<StagedUpgrade>,
+    /// The paginated state migration in progress. Records are also migrated lazily,
+    /// so only the operations removing records are blocked until it's completed.
+    migration: Option<MigrationState>,
 }
 
 #[near_bindgen]
@@ -131,21 +248,59 @@ impl LiquidStakingContract {
             treasury_id: owner_id.clone(),
             total_share_amount: 10 * ONE_NEAR,
             total_staked_near_amount: 10 * ONE_NEAR,
-            accounts: UnorderedMap::new(StorageKey::Accounts),
+            accounts: UnorderedMap::new(StorageKey::AccountsV1),
+            legacy_accounts: UnorderedMap::new(StorageKey::Accounts),
+            storage_deposits: LookupMap::new(StorageKey::StorageDeposits),
+            total_storage_deposit: 0,
+            total_unstaked_amount: 0,
+            allowances: LookupMap::new(StorageKey::Allowances),
+            allowance_spenders: LookupMap::new(StorageKey::AllowanceSpenders),
+            allowances_num: 0,
+            account_stats: LookupMap::new(StorageKey::AccountStats),
+            account_stats_num: 0,
+            omnibus_accounts: LookupMap::new(StorageKey::OmnibusAccounts),
+            omnibus_accounts_num: 0,
+            sub_accounts: LookupMap::new(StorageKey::SubAccounts),
+            sub_accounts_num: 0,
+            auto_claim_accounts: LookupSet::new(StorageKey::AutoClaimAccounts),
+            auto_claim_accounts_num: 0,
+            claim_queue: TreeMap::new(StorageKey::ClaimQueue),
+            lock_schedules: LookupMap::new(StorageKey::LockSchedules),
+            withdrawal_allowlists: LookupMap::new(StorageKey::WithdrawalAllowlists),
+            withdrawal_allowlists_num: 0,
+            balance_checkpoints: LookupMap::new(StorageKey::BalanceCheckpoints),
+            balance_checkpoints_num: 0,
+            total_supply_checkpoints: TreeMap::new(StorageKey::TotalSupplyCheckpoints),
             paused: false,
             account_storage_usage: 0,
+            allowance_storage_usage: 0,
+            account_stats_storage_usage: 0,
+            omnibus_account_storage_usage: 0,
+            sub_account_storage_usage: 0,
+            auto_claim_storage_usage: 0,
+            withdrawal_allowlist_storage_usage: 0,
+            balance_checkpoints_storage_usage: 0,
             beneficiaries: UnorderedMap::new(StorageKey::Beneficiaries),
             // Validator Pool
             validator_pool: ValidatorPool::new(),
             whitelist_account_id: None,
+            wnear_account_id: None,
             epoch_requested_stake_amount: 10 * ONE_NEAR,
             epoch_requested_unstake_amount: 0,
+            epoch_reserve_unstake_amount: 0,
+            stake_limits: StakeLimits::default(),
+            min_amounts: MinAmounts::default(),
+            liquidity_reserve_amount: 0,
+            liquidity_reserve_bps: 0,
             stake_amount_to_settle: 0,
             unstake_amount_to_settle: 0,
             last_settlement_epoch: 0,
+            staged_upgrade: None,
+            migration: None,
         };
         this.internal_add_manager(&owner_id);
         this.measure_account_storage_usage();
+        this.internal_checkpoint_total_supply();
         this
     }
 
@@ -156,9 +311,79 @@ impl LiquidStakingContract {
     fn measure_account_storage_usage(&mut self) {
         let initial_storage_usage = env::storage_usage();
         let tmp_account_id = AccountId::new_unchecked("a".repeat(64));
-        self.accounts.insert(&tmp_account_id, &Account::default());
+        self.accounts
+            .insert(&tmp_account_id, &Account::default().into());
+        self.storage_deposits.insert(&tmp_account_id, &0);
         self.account_storage_usage = env::storage_usage() - initial_storage_usage;
         self.accounts.remove(&tmp_account_id);
+        self.storage_deposits.remove(&tmp_account_id);
+
+        let initial_storage_usage = env::storage_usage();
+        let tmp_allowance_key = (tmp_account_id.clone(), tmp_account_id.clone());
+        self.allowances
+            .insert(&tmp_allowance_key, &Allowance::default());
+        self.allowance_spenders
+            .insert(&tmp_account_id, &vec![tmp_account_id.clone()]);
+        self.allowance_storage_usage = env::storage_usage() - initial_storage_usage;
+        self.allowances.remove(&tmp_allowance_key);
+        self.allowance_spenders.remove(&tmp_account_id);
+
+        let initial_storage_usage = env::storage_usage();
+        self.account_stats
+            .insert(&tmp_account_id, &AccountStats::default());
+        self.account_stats_storage_usage = env::storage_usage() - initial_storage_usage;
+        self.account_stats.remove(&tmp_account_id);
+
+        let initial_storage_usage = env::storage_usage();
+        self.omnibus_accounts
+            .insert(&tmp_account_id, &OmnibusAccount::default());
+        self.omnibus_account_storage_usage = env::storage_usage() - initial_storage_usage;
+        self.omnibus_accounts.remove(&tmp_account_id);
+
+        let initial_storage_usage = env::storage_usage();
+        let tmp_sub_account_key = (tmp_account_id, "a".repeat(MAX_SUB_ACCOUNT_ID_LEN));
+        self.sub_accounts
+            .insert(&tmp_sub_account_key, &Account::default().into());
+        self.sub_account_storage_usage = env::storage_usage() - initial_storage_usage;
+        self.sub_accounts.remove(&tmp_sub_account_key);
+
+        let initial_storage_usage = env::storage_usage();
+        let tmp_account_id = tmp_sub_account_key.0;
+        let tmp_claim_key = (EpochHeight::MAX, tmp_account_id.clone());
+        self.auto_claim_accounts.insert(&tmp_account_id);
+        self.claim_queue.insert(&tmp_claim_key, &());
+        self.auto_claim_storage_usage = env::storage_usage() - initial_storage_usage;
+        self.auto_claim_accounts.remove(&tmp_account_id);
+        self.claim_queue.remove(&tmp_claim_key);
+
+        let initial_storage_usage = env::storage_usage();
+        let tmp_allowlist = vec![tmp_account_id.clone(); MAX_WITHDRAWAL_ALLOWLIST_LEN];
+        self.withdrawal_allowlists.insert(
+            &tmp_account_id,
+            &WithdrawalAllowlist {
+                allowlist: tmp_allowlist.clone(),
+                pending_change: Some(AllowlistChange {
+                    allowlist: Some(tmp_allowlist),
+                    effective_epoch_height: EpochHeight::MAX,
+                }),
+            },
+        );
+        self.withdrawal_allowlist_storage_usage = env::storage_usage() - initial_storage_usage;
+        self.withdrawal_allowlists.remove(&tmp_account_id);
+
+        let initial_storage_usage = env::storage_usage();
+        let mut tmp_checkpoints = BalanceCheckpoints::default();
+        for epoch_height in 0..MAX_BALANCE_CHECKPOINTS as EpochHeight {
+            tmp_checkpoints.record(
+                epoch_height,
+                ShareBalance::MAX - Balance::from(epoch_height),
+            );
+        }
+        tmp_checkpoints.pin(0);
+        self.balance_checkpoints
+            .insert(&tmp_account_id, &tmp_checkpoints);
+        self.balance_checkpoints_storage_usage = env::storage_usage() - initial_storage_usage;
+        self.balance_checkpoints.remove(&tmp_account_id);
     }
 }
 
@@ -185,4 +410,20 @@ mod tests {
         // let contract = LiquidStakingContract::new();
         testing_env!(context.is_view(true).build());
     }
+
+    #[test]
+    fn test_storage_bounds_from_account_storage_usage() {
+        let mut context = get_context(accounts(1));
+        context.account_balance(20 * ONE_NEAR);
+        testing_env!(context.build());
+        let contract = LiquidStakingContract::new(accounts(1));
+        // the compact account and storage deposit record with a 64-byte account ID
+        assert_eq!(contract.account_storage_usage, 452);
+        // the lifetime counters with a 64-byte account ID
+        assert_eq!(contract.account_stats_storage_usage, 205);
+        assert_eq!(
+            contract.internal_min_storage_balance(),
+            452 * env::storage_byte_cost()
+        );
+    }
 }
@@ -0,0 +1,280 @@
+use crate::*;
+
+/// Owner-configurable limits of staking and unstaking, which cap the growth and
+/// outflow of the pool, e.g. during validator-set changes. `None` means unlimited.
+///
+/// The per-epoch limits cap `epoch_requested_stake_amount` and `epoch_requested_unstake_amount`
+/// separately, which are reset on epoch cleanup. Staking doesn't add to the unstake headroom,
+/// so the outflow of an epoch is capped regardless of the inflow. Restaking drained NEAR is
+/// requested the same as staking, so it's counted toward the stake limit.
+/// Unstakes served by the liquidity reserve are counted toward the unstake limit too,
+/// i.e. `epoch_reserve_unstake_amount`, so the reserve doesn't raise the outflow of an epoch.
+///
+/// The headroom is first-come first-served within an epoch, so a large holder could
+/// use up the unstake limit of an epoch by unstaking its own LiNEAR, and others need
+/// to wait for the next epoch.
+#[derive(BorshSerialize, BorshDeserialize, Default)]
+pub struct StakeLimits {
+    /// Max `total_staked_near_amount` that staking could increase to
+    pub max_total_staked_near_amount: Option<Balance>,
+    /// Max amount of NEAR that could be staked within an epoch
+    pub max_epoch_stake_amount: Option<Balance>,
+    /// Max amount of NEAR that could be unstaked within an epoch
+    pub max_epoch_unstake_amount: Option<Balance>,
+}
+
+/// The stake limits and the remaining headroom, `null` means unlimited
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct StakeLimitsView {
+    pub max_total_staked_near_amount: Option<U128>,
+    pub max_epoch_stake_amount: Option<U128>,
+    pub max_epoch_unstake_amount: Option<U128>,
+    /// Amount of NEAR that could be staked before reaching the max total staked amount
+    pub remaining_total_stake_amount: Option<U128>,
+    /// Amount of NEAR that could still be staked in the current epoch
+    pub remaining_epoch_stake_amount: Option<U128>,
+    /// Amount of NEAR that could still be unstaked in the current epoch
+    pub remaining_epoch_unstake_amount: Option<U128>,
+}
+
+/// Owner-configurable minimum amounts of NEAR per operation, which prevent dust
+/// accounts and unstaking small amounts dominated by rounding. `0` means no minimum.
+///
+/// Unstaking the full staked balance, e.g. `unstake_all()`, is exempted, and withdrawals
+/// are not limited, so small balances could always be taken out.
+#[derive(BorshSerialize, BorshDeserialize, Default)]
+pub struct MinAmounts {
+    pub min_deposit_amount: Balance,
+    pub min_stake_amount: Balance,
+    pub min_unstake_amount: Balance,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct MinAmountsView {
+    pub min_deposit_amount: U128,
+    pub min_stake_amount: U128,
+    pub min_unstake_amount: U128,
+}
+
+impl LiquidStakingContract {
+    pub(crate) fn internal_remaining_total_stake_amount(&self) -> Option<Balance> {
+        self.stake_limits
+            .max_total_staked_near_amount
+            .map(|max| max.saturating_sub(self.total_staked_near_amount))
+    }
+
+    pub(crate) fn internal_remaining_epoch_stake_amount(&self) -> Option<Balance> {
+        self.stake_limits
+            .max_epoch_stake_amount
+            .map(|max| max.saturating_sub(self.epoch_requested_stake_amount))
+    }
+
+    pub(crate) fn internal_remaining_epoch_unstake_amount(&self) -> Option<Balance> {
+        self.stake_limits.max_epoch_unstake_amount.map(|max| {
+            max.saturating_sub(self.epoch_requested_unstake_amount)
+                .saturating_sub(self.epoch_reserve_unstake_amount)
+        })
+    }
+
+    /// Asserts that `stake_amount` could be added to the total staked amount
+    pub(crate) fn assert_stake_limits(&self, stake_amount: Balance) {
+        require!(
+            stake_amount
+                <= self
+                    .internal_remaining_total_stake_amount()
+                    .unwrap_or(Balance::MAX),
+            ERR_EXCEED_MAX_TOTAL_STAKED_AMOUNT
+        );
+        require!(
+            stake_amount
+                <= self
+                    .internal_remaining_epoch_stake_amount()
+                    .unwrap_or(Balance::MAX),
+            ERR_EXCEED_MAX_EPOCH_STAKE_AMOUNT
+        );
+    }
+
+    /// Returns true if `stake_amount` could be added without exceeding the stake limits
+    pub(crate) fn internal_within_stake_limits(&self, stake_amount: Balance) -> bool {
+        stake_amount
+            <= std::cmp::min(
+                self.internal_remaining_total_stake_amount()
+                    .unwrap_or(Balance::MAX),
+                self.internal_remaining_epoch_stake_amount()
+                    .unwrap_or(Balance::MAX),
+            )
+    }
+
+    /// Asserts that `unstake_amount` could be removed from the total staked amount
+    pub(crate) fn assert_unstake_limits(&self, unstake_amount: Balance) {
+        require!(
+            unstake_amount
+                <= self
+                    .internal_remaining_epoch_unstake_amount()
+                    .unwrap_or(Balance::MAX),
+            ERR_EXCEED_MAX_EPOCH_UNSTAKE_AMOUNT
+        );
+    }
+
+    pub(crate) fn assert_min_deposit_amount(&self, amount: Balance) {
+        require!(
+            amount >= self.min_amounts.min_deposit_amount,
+            ERR_DEPOSIT_AMOUNT_TOO_SMALL
+        );
+    }
+
+    pub(crate) fn assert_min_stake_amount(&self, amount: Balance) {
+        require!(
+            amount >= self.min_amounts.min_stake_amount,
+            ERR_STAKE_AMOUNT_TOO_SMALL
+        );
+    }
+
+    /// Asserts the minimum unstake amount unless the full `stake_shares` are unstaked
+    pub(crate) fn assert_min_unstake_amount(&self, amount: Balance, stake_shares: ShareBalance) {
+        let full_amount = self.staked_amount_from_num_shares_rounded_down(stake_shares);
+        require!(
+            amount >= std::cmp::min(self.min_amounts.min_unstake_amount, full_amount),
+            ERR_UNSTAKE_AMOUNT_TOO_SMALL
+        );
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::accounts;
+
+    use super::*;
+    use crate::test_utils::*;
+
+    fn new_contract_without_requested_stake() -> LiquidStakingContract {
+        let mut contract = new_contract();
+        // the initial stake is requested in the first epoch
+        contract.epoch_requested_stake_amount = 0;
+        contract
+    }
+
+    #[test]
+    fn remaining_headroom() {
+        let mut contract = new_contract_without_requested_stake();
+        let limits = contract.get_stake_limits();
+        assert!(limits.remaining_total_stake_amount.is_none());
+        assert!(limits.remaining_epoch_stake_amount.is_none());
+
+        contract.set_stake_limits(
+            Some(U128(15 * ONE_NEAR)),
+            Some(U128(3 * ONE_NEAR)),
+            Some(U128(2 * ONE_NEAR)),
+        );
+        set_context(&accounts(2), 2 * ONE_NEAR);
+        contract.deposit_and_stake();
+        set_context(&accounts(2), 0);
+        contract.unstake(U128(ONE_NEAR));
+
+        // the stake and unstake within the epoch are counted separately
+        let limits = contract.get_stake_limits();
+        assert_eq!(limits.remaining_total_stake_amount.unwrap().0, 4 * ONE_NEAR);
+        assert_eq!(limits.remaining_epoch_stake_amount.unwrap().0, ONE_NEAR);
+        assert_eq!(limits.remaining_epoch_unstake_amount.unwrap().0, ONE_NEAR);
+    }
+
+    #[test]
+    #[should_panic(expected = "The unstaked amount in this epoch would exceed the maximum")]
+    fn stake_within_epoch_does_not_raise_unstake_limit() {
+        let mut contract = new_contract_without_requested_stake();
+        contract.set_stake_limits(None, None, Some(U128(2 * ONE_NEAR)));
+        set_context(&accounts(2), 5 * ONE_NEAR);
+        contract.deposit_and_stake();
+        set_context(&accounts(2), 0);
+        contract.unstake(U128(5 * ONE_NEAR));
+    }
+
+    #[test]
+    #[should_panic(expected = "The total staked amount would exceed the maximum")]
+    fn stake_over_max_total_staked_amount() {
+        let mut contract = new_contract_without_requested_stake();
+        contract.set_stake_limits(Some(U128(11 * ONE_NEAR)), None, None);
+        set_context(&accounts(2), 2 * ONE_NEAR);
+        contract.deposit_and_stake();
+    }
+
+    #[test]
+    #[should_panic(expected = "The staked amount in this epoch would exceed the maximum")]
+    fn stake_over_max_epoch_stake_amount() {
+        let mut contract = new_contract_without_requested_stake();
+        contract.set_stake_limits(None, Some(U128(ONE_NEAR)), None);
+        set_context(&accounts(2), 2 * ONE_NEAR);
+        contract.deposit_and_stake();
+    }
+
+    #[test]
+    #[should_panic(expected = "The unstaked amount in this epoch would exceed the maximum")]
+    fn unstake_over_max_epoch_unstake_amount() {
+        let mut contract = new_contract_without_requested_stake();
+        contract.set_stake_limits(None, None, Some(U128(ONE_NEAR)));
+        set_context(&accounts(2), 2 * ONE_NEAR);
+        contract.deposit_and_stake();
+        // staked in an earlier epoch
+        contract.epoch_requested_stake_amount = 0;
+        set_context(&accounts(2), 0);
+        contract.unstake(U128(2 * ONE_NEAR));
+    }
+
+    #[test]
+    #[should_panic(expected = "The total staked amount would exceed the maximum")]
+    fn donate_over_max_total_staked_amount() {
+        let mut contract = new_contract_without_requested_stake();
+        contract.set_stake_limits(Some(U128(11 * ONE_NEAR)), None, None);
+        set_context(&accounts(2), 2 * ONE_NEAR);
+        contract.donate();
+    }
+
+    #[test]
+    #[should_panic(expected = "The deposit amount is less than the minimum")]
+    fn deposit_below_min_amount() {
+        let mut contract = new_contract_without_requested_stake();
+        contract.set_min_amounts(U128(ONE_NEAR), U128(0), U128(0));
+        set_context(&accounts(2), ONE_NEAR - 1);
+        contract.deposit();
+    }
+
+    #[test]
+    #[should_panic(expected = "The stake amount is less than the minimum")]
+    fn stake_below_min_amount() {
+        let mut contract = new_contract_without_requested_stake();
+        contract.set_min_amounts(U128(0), U128(ONE_NEAR), U128(0));
+        set_context(&accounts(2), 2 * ONE_NEAR);
+        contract.deposit();
+        set_context(&accounts(2), 0);
+        contract.stake(U128(ONE_NEAR / 2));
+    }
+
+    #[test]
+    fn unstake_all_exempted_from_min_amount() {
+        let mut contract = new_contract_without_requested_stake();
+        contract.set_min_amounts(U128(0), U128(0), U128(5 * ONE_NEAR));
+        set_context(&accounts(2), 2 * ONE_NEAR);
+        contract.deposit_and_stake();
+
+        set_context(&accounts(2), 0);
+        contract.unstake_all();
+        assert_eq!(contract.get_account_staked_balance(accounts(2)).0, 0);
+        assert_eq!(
+            contract.get_account_unstaked_balance(accounts(2)).0,
+            2 * ONE_NEAR
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "The unstake amount is less than the minimum")]
+    fn unstake_below_min_amount() {
+        let mut contract = new_contract_without_requested_stake();
+        contract.set_min_amounts(U128(0), U128(0), U128(5 * ONE_NEAR));
+        set_context(&accounts(2), 10 * ONE_NEAR);
+        contract.deposit_and_stake();
+        set_context(&accounts(2), 0);
+        contract.unstake(U128(ONE_NEAR));
+    }
+}
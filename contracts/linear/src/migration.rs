@@ -0,0 +1,508 @@
+//! Paginated state migration for per-record format changes.
+//!
+//! `migrate()` runs within a single call, so it should only convert the root state.
+//! When the format of records stored in collections (e.g. `Account` or `Validator`)
+//! changes, `migrate()` starts a migration via `internal_start_migration()`, and
+//! the records are then migrated in batches by calling `migrate_batch()` until
+//! the migration is completed.
+//!
+//! The migration is lazy, so user operations are not blocked while it's in progress:
+//! - Every record is converted to the latest format when read (`internal_find_account()`,
+//!   `ValidatorPool::get_validator()`), so no operation ever sees an old format.
+//! - Stake, unstake, withdraw and LiNEAR transfers only update accounts in place via
+//!   `internal_save_account()`, which writes the latest format and moves a legacy account
+//!   to `accounts`. Updating an account keeps its index, and a moved or new account is
+//!   appended after the cursor, so `migrate_batch()` neither skips nor repeats a record.
+//! - The unstaked balance of legacy accounts is already counted in `total_unstaked_amount`
+//!   by `migrate()`, and moving an account replaces its legacy balance rather than adding it.
+//! - Epoch and validator actions update validators in place via `save_validator()`.
+//!
+//! Only the operations removing a record, `storage_unregister()` and `remove_validator()`,
+//! are blocked, since removing a record moves the last one to its index, which could
+//! make `migrate_batch()` skip it.
+//!
+//! The data of removed features, which is no longer referenced by the root state,
+//! could be removed in batches via `cleanup_legacy_storage()`.
+use crate::events::Event;
+use crate::*;
+use near_sdk::StorageUsage;
+
+/// The cursor of the migration in progress
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Default, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct MigrationState {
+    /// Number of legacy accounts that have been moved to the latest format.
+    /// Legacy accounts are migrated from the end of `legacy_accounts`.
+    pub legacy_accounts_migrated: u64,
+    /// Index of the next account to migrate
+    pub accounts_cursor: u64,
+    /// Index of the next validator to migrate
+    pub validators_cursor: u64,
+}
+
+/// The legacy storage to clean up, which is left by the removed features
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+#[serde(rename_all = "snake_case")]
+pub enum LegacyStorage {
+    /// Liquidity pool shares stored in `LookupMap<AccountId, Balance>`, which could not be
+    /// iterated, so the account IDs of liquidity providers have to be provided
+    Shares { account_ids: Vec<AccountId> },
+    /// Staking farms stored in `Vector<Farm>`
+    Farms { from_index: u64, limit: u64 },
+    /// Authorized farm tokens stored in `UnorderedSet<AccountId>`
+    AuthorizedFarmTokens { from_index: u64, limit: u64 },
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct LegacyStorageCleanupResult {
+    /// Number of removed entries
+    pub removed_entries: u64,
+    /// Storage usage in bytes that has been freed
+    pub freed_bytes: StorageUsage,
+    /// Amount of NEAR that has been released from storage staking
+    pub freed_near: U128,
+}
+
+#[near_bindgen]
+impl LiquidStakingContract {
+    /// Migrate at most `limit` records of the migration in progress.
+    /// Legacy accounts are migrated first, followed by accounts and validators.
+    /// Anyone can call this method to push the migration forward.
+    /// Returns true if the migration is completed.
+    pub fn migrate_batch(&mut self, limit: u32) -> bool {
+        let mut migration = self.migration.clone().expect(ERR_NO_MIGRATION_IN_PROGRESS);
+        require!(limit > 0, ERR_NON_POSITIVE_MIGRATION_LIMIT);
+
+        let mut remaining = limit as u64;
+        while remaining > 0 && !self.legacy_accounts.is_empty() {
+            let account_id = self
+                .legacy_accounts
+                .keys_as_vector()
+                .get(self.legacy_accounts.len() - 1)
+                .unwrap();
+            self.internal_migrate_legacy_account(&account_id);
+            migration.legacy_accounts_migrated += 1;
+            remaining -= 1;
+        }
+
+        while remaining > 0 && migration.accounts_cursor < self.accounts.len() {
+            let account_id = self
+                .accounts
+                .keys_as_vector()
+                .get(migration.accounts_cursor)
+                .unwrap();
+            self.internal_migrate_account(&account_id);
+            migration.accounts_cursor += 1;
+            remaining -= 1;
+        }
+
+        while remaining > 0 && migration.validators_cursor < self.validator_pool.count() {
+            let validator_id = self
+                .validator_pool
+                .validators
+                .keys_as_vector()
+                .get(migration.validators_cursor)
+                .unwrap();
+            self.internal_migrate_validator(&validator_id);
+            migration.validators_cursor += 1;
+            remaining -= 1;
+        }
+
+        let completed = self.legacy_accounts.is_empty()
+            && migration.accounts_cursor >= self.accounts.len()
+            && migration.validators_cursor >= self.validator_pool.count();
+        if completed {
+            self.migration = None;
+            Event::MigrationCompleted {
+                accounts_num: migration.accounts_cursor,
+                validators_num: migration.validators_cursor,
+            }
+            .emit();
+        } else {
+            self.migration = Some(migration);
+        }
+        completed
+    }
+
+    /// Get the cursor of the migration in progress, or None if there's no migration
+    pub fn get_migration_state(&self) -> Option<MigrationState> {
+        self.migration.clone()
+    }
+
+    /// Remove the leftover entries of the removed liquidity pool and staking farms.
+    /// Only manager can perform this action.
+    pub fn cleanup_legacy_storage(&mut self, target: LegacyStorage) -> LegacyStorageCleanupResult {
+        self.assert_manager();
+        let initial_storage_usage = env::storage_usage();
+
+        let (storage_name, removed_entries) = match target {
+            LegacyStorage::Shares { account_ids } => {
+                let prefix = StorageKey::Shares.try_to_vec().unwrap();
+                let removed_entries = account_ids
+                    .iter()
+                    .filter(|account_id| {
+                        let key = [prefix.clone(), account_id.try_to_vec().unwrap()].concat();
+                        env::storage_remove(&key)
+                    })
+                    .count();
+                ("shares", removed_entries as u64)
+            }
+            LegacyStorage::Farms { from_index, limit } => {
+                let prefix = StorageKey::Farms.try_to_vec().unwrap();
+                let removed_entries = (from_index..from_index.saturating_add(limit))
+                    .filter(|index| {
+                        let key = [prefix.clone(), index.to_le_bytes().to_vec()].concat();
+                        env::storage_remove(&key)
+                    })
+                    .count();
+                ("farms", removed_entries as u64)
+            }
+            LegacyStorage::AuthorizedFarmTokens { from_index, limit } => {
+                let prefix = StorageKey::AuthorizedFarmTokens.try_to_vec().unwrap();
+                let elements_prefix = [prefix.clone(), vec![b'e']].concat();
+                let index_prefix = [prefix, vec![b'i']].concat();
+                let removed_entries = (from_index..from_index.saturating_add(limit))
+                    .filter(|index| {
+                        let key = [elements_prefix.clone(), index.to_le_bytes().to_vec()].concat();
+                        // the element is the serialized token ID, which is also the key of index map
+                        match env::storage_read(&key) {
+                            Some(element) => {
+                                env::storage_remove(&key);
+                                env::storage_remove(&[index_prefix.clone(), element].concat());
+                                true
+                            }
+                            None => false,
+                        }
+                    })
+                    .count();
+                ("authorized_farm_tokens", removed_entries as u64)
+            }
+        };
+
+        let freed_bytes = initial_storage_usage - env::storage_usage();
+        let freed_near = Balance::from(freed_bytes) * env::storage_byte_cost();
+
+        Event::LegacyStorageCleanup {
+            storage: storage_name,
+            removed_entries,
+            freed_bytes,
+            freed_near: &U128(freed_near),
+        }
+        .emit();
+
+        LegacyStorageCleanupResult {
+            removed_entries,
+            freed_bytes,
+            freed_near: freed_near.into(),
+        }
+    }
+}
+
+impl LiquidStakingContract {
+    pub(crate) fn assert_not_migrating(&self) {
+        require!(self.migration.is_none(), ERR_MIGRATION_IN_PROGRESS);
+    }
+
+    /// Start a new migration from the first record.
+    /// Should only be called in `migrate()`.
+    pub(crate) fn internal_start_migration(&mut self) {
+        self.migration = Some(MigrationState::default());
+    }
+
+    /// Move the legacy account to `accounts` in the latest format
+    fn internal_migrate_legacy_account(&mut self, account_id: &AccountId) {
+        if let Some(account) = self.legacy_accounts.get(account_id) {
+            // removes the legacy account and counts its unstaked balance
+            self.internal_save_account(account_id, &account.into());
+        }
+    }
+
+    /// Migrate the account to the latest format.
+    ///
+    /// This is a stub of the framework: `VersionedAccount::Current` is the only variant
+    /// of `accounts` for now, so there's nothing to convert. When the account format
+    /// changes, add the previous format as a variant with its conversion into `Account`,
+    /// which is then applied here.
+    fn internal_migrate_account(&mut self, account_id: &AccountId) {
+        if let Some(account) = self.accounts.get(account_id) {
+            #[allow(unreachable_patterns)]
+            if !matches!(account, VersionedAccount::Current(_)) {
+                self.internal_save_account(account_id, &account.into());
+            }
+        }
+    }
+
+    /// Migrate the validator to the latest format
+    fn internal_migrate_validator(&mut self, validator_id: &AccountId) {
+        if let Some(validator) = self.validator_pool.validators.get(validator_id) {
+            if !matches!(validator, VersionedValidator::Current(_)) {
+                self.validator_pool.save_validator(&validator.into());
+            }
+        }
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_contract_standards::storage_management::StorageManagement;
+    use near_sdk::collections::{LookupMap, Vector};
+    use near_sdk::test_utils::accounts;
+
+    use super::*;
+    use crate::legacy::{AccountV1_6_0, ValidatorV1_4_0};
+    use crate::test_utils::*;
+    use crate::view::AccountsCursor;
+
+    fn insert_legacy_validator(contract: &mut LiquidStakingContract, validator_id: &AccountId) {
+        let validator = ValidatorV1_4_0 {
+            account_id: validator_id.clone(),
+            weight: 1,
+            staked_amount: ONE_NEAR,
+            unstaked_amount: 0,
+            base_stake_amount: 0,
+            unstake_fired_epoch: 0,
+            last_unstake_fired_epoch: 0,
+            draining: false,
+        };
+        contract
+            .validator_pool
+            .validators
+            .insert(validator_id, &VersionedValidator::V2(validator));
+    }
+
+    #[test]
+    fn migrate_in_batches() {
+        let mut contract = new_contract();
+        for i in 2..5 {
+            let account = Account {
+                unstaked: ONE_NEAR,
+                ..Default::default()
+            };
+            contract.internal_save_account(&accounts(i), &account);
+        }
+        insert_legacy_validator(&mut contract, &accounts(5));
+        insert_legacy_validator(&mut contract, &accounts(0));
+        contract.internal_start_migration();
+
+        assert!(!contract.migrate_batch(2));
+        let state = contract.get_migration_state().unwrap();
+        assert_eq!(state.accounts_cursor, 2);
+        assert_eq!(state.validators_cursor, 0);
+
+        assert!(!contract.migrate_batch(2));
+        let state = contract.get_migration_state().unwrap();
+        assert_eq!(state.accounts_cursor, 3);
+        assert_eq!(state.validators_cursor, 1);
+
+        assert!(contract.migrate_batch(2));
+        assert!(contract.get_migration_state().is_none());
+
+        for validator in contract.validator_pool.validators.values() {
+            assert!(matches!(validator, VersionedValidator::Current(_)));
+        }
+        for i in 2..5 {
+            assert_eq!(
+                contract.internal_get_account(&accounts(i)).unstaked,
+                ONE_NEAR
+            );
+        }
+    }
+
+    #[test]
+    fn migrate_legacy_accounts() {
+        let mut contract = new_contract();
+        for i in 2..5 {
+            let account = AccountV1_6_0 {
+                unstaked: ONE_NEAR,
+                stake_shares: i as u128,
+                ..Default::default()
+            };
+            contract.legacy_accounts.insert(&accounts(i), &account);
+            // counted by `migrate()`
+            contract.total_unstaked_amount += account.unstaked;
+        }
+        contract.internal_start_migration();
+
+        // legacy accounts are readable before migrated
+        assert_eq!(contract.get_number_of_accounts(), 3);
+        assert_eq!(contract.internal_get_account(&accounts(2)).stake_shares, 2);
+
+        // saved account is moved to the latest format
+        let mut account = contract.internal_get_account(&accounts(2));
+        account.unstaked = 0;
+        contract.internal_save_account(&accounts(2), &account);
+        assert!(contract.legacy_accounts.get(&accounts(2)).is_none());
+        assert_eq!(contract.get_number_of_accounts(), 3);
+        assert_eq!(contract.total_unstaked_amount, 2 * ONE_NEAR);
+
+        assert!(!contract.migrate_batch(1));
+        let state = contract.get_migration_state().unwrap();
+        assert_eq!(state.legacy_accounts_migrated, 1);
+        assert_eq!(state.accounts_cursor, 0);
+
+        assert!(contract.migrate_batch(10));
+        assert!(contract.legacy_accounts.is_empty());
+        assert_eq!(contract.accounts.len(), 3);
+        assert_eq!(contract.total_unstaked_amount, 2 * ONE_NEAR);
+        assert_eq!(
+            contract.internal_get_storage_deposit(&accounts(3)),
+            contract.internal_legacy_storage_balance()
+        );
+        for i in 2..5 {
+            let account = contract.internal_get_account(&accounts(i));
+            assert_eq!(account.stake_shares, i as u128);
+            assert_eq!(account.unstaked, if i == 2 { 0 } else { ONE_NEAR });
+        }
+    }
+
+    #[test]
+    fn list_accounts_during_migration() {
+        let mut contract = new_contract();
+        contract.internal_register_account(&accounts(5));
+        for i in 2..5 {
+            let account = AccountV1_6_0 {
+                stake_shares: i as u128,
+                ..Default::default()
+            };
+            contract.legacy_accounts.insert(&accounts(i), &account);
+        }
+        contract.internal_start_migration();
+
+        let page = contract.get_accounts_page(None, 2);
+        let mut listed: Vec<AccountId> = page.accounts.into_iter().map(|a| a.account_id).collect();
+        assert_eq!(listed, vec![accounts(4), accounts(3)]);
+        assert_eq!(page.next_cursor, Some(AccountsCursor::LegacyAccounts(0)));
+
+        // migrating the first legacy account moves the last one into its index
+        assert!(!contract.migrate_batch(1));
+        let account = contract.internal_get_account(&accounts(2));
+        contract.internal_save_account(&accounts(2), &account);
+
+        let mut cursor = page.next_cursor;
+        while cursor.is_some() {
+            let page = contract.get_accounts_page(cursor, 2);
+            listed.extend(page.accounts.into_iter().map(|a| a.account_id));
+            cursor = page.next_cursor;
+        }
+        for i in 2..6 {
+            assert!(listed.contains(&accounts(i)));
+        }
+    }
+
+    #[test]
+    fn cleanup_legacy_storage() {
+        let mut contract = new_contract();
+
+        let mut shares: LookupMap<AccountId, Balance> = LookupMap::new(StorageKey::Shares);
+        shares.insert(&accounts(2), &ONE_NEAR);
+        shares.insert(&accounts(3), &ONE_NEAR);
+        let mut farms: Vector<u128> = Vector::new(StorageKey::Farms);
+        farms.push(&1);
+        farms.push(&2);
+        farms.push(&3);
+        let mut tokens: UnorderedSet<AccountId> =
+            UnorderedSet::new(StorageKey::AuthorizedFarmTokens);
+        tokens.insert(&accounts(4));
+        tokens.insert(&accounts(5));
+
+        let result = contract.cleanup_legacy_storage(LegacyStorage::Shares {
+            account_ids: vec![accounts(2), accounts(3), accounts(4)],
+        });
+        assert_eq!(result.removed_entries, 2);
+        assert!(result.freed_bytes > 0);
+        assert_eq!(
+            result.freed_near.0,
+            result.freed_bytes as u128 * env::storage_byte_cost()
+        );
+        assert!(!shares.contains_key(&accounts(2)));
+        assert!(!shares.contains_key(&accounts(3)));
+
+        let result = contract.cleanup_legacy_storage(LegacyStorage::Farms {
+            from_index: 1,
+            limit: 10,
+        });
+        assert_eq!(result.removed_entries, 2);
+        let farm_key = |index: u64| {
+            [
+                StorageKey::Farms.try_to_vec().unwrap(),
+                index.to_le_bytes().to_vec(),
+            ]
+            .concat()
+        };
+        assert!(env::storage_has_key(&farm_key(0)));
+        assert!(!env::storage_has_key(&farm_key(1)));
+        assert!(!env::storage_has_key(&farm_key(2)));
+
+        let result = contract.cleanup_legacy_storage(LegacyStorage::AuthorizedFarmTokens {
+            from_index: 0,
+            limit: 10,
+        });
+        assert_eq!(result.removed_entries, 2);
+        assert!(!tokens.contains(&accounts(4)));
+        assert!(!tokens.contains(&accounts(5)));
+    }
+
+    #[test]
+    #[should_panic(expected = "Only manager can perform this action")]
+    fn cleanup_legacy_storage_by_non_manager() {
+        let mut contract = new_contract();
+        set_context(&accounts(2), 0);
+        contract.cleanup_legacy_storage(LegacyStorage::Farms {
+            from_index: 0,
+            limit: 10,
+        });
+    }
+
+    #[test]
+    fn user_operations_during_migration() {
+        let mut contract = new_contract();
+        let account = AccountV1_6_0 {
+            unstaked: ONE_NEAR,
+            ..Default::default()
+        };
+        contract.legacy_accounts.insert(&accounts(2), &account);
+        contract.total_unstaked_amount += account.unstaked;
+        contract.internal_start_migration();
+
+        // the legacy account is moved to the latest format when saved
+        contract.internal_deposit(&accounts(2), None, ONE_NEAR);
+        assert!(contract.legacy_accounts.is_empty());
+        assert_eq!(
+            contract.internal_get_account(&accounts(2)).unstaked,
+            2 * ONE_NEAR
+        );
+
+        assert!(contract.migrate_batch(10));
+        assert_eq!(
+            contract.internal_get_account(&accounts(2)).unstaked,
+            2 * ONE_NEAR
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "State migration is in progress")]
+    fn storage_unregister_blocked_during_migration() {
+        let mut contract = new_contract();
+        contract.internal_register_account(&accounts(2));
+        contract.internal_start_migration();
+        set_context(&accounts(2), 1);
+        contract.storage_unregister(None);
+    }
+
+    #[test]
+    #[should_panic(expected = "State migration is in progress")]
+    fn remove_validator_blocked_during_migration() {
+        let mut contract = new_contract();
+        contract.internal_start_migration();
+        contract.remove_validator(accounts(3));
+    }
+
+    #[test]
+    #[should_panic(expected = "There is no migration in progress")]
+    fn migrate_batch_without_migration() {
+        let mut contract = new_contract();
+        contract.migrate_batch(10);
+    }
+}
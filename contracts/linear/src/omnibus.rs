@@ -0,0 +1,794 @@
+use crate::events::Event;
+use crate::*;
+use near_contract_standards::storage_management::StorageBalance;
+use near_sdk::{assert_one_yocto, near_bindgen, Promise, PromiseOrValue};
+
+/// The ledger of an omnibus account, e.g. an exchange that stakes on behalf of its customers.
+/// `unstaked` and `stake_shares` are the sums of all its sub-accounts, which always equal
+/// the balances of the account itself.
+#[derive(BorshSerialize, BorshDeserialize, Default)]
+pub struct OmnibusAccount {
+    /// Number of sub-accounts with positive balance
+    pub sub_accounts_num: u64,
+    /// Sum of the unstaked balance of all sub-accounts
+    pub unstaked: Balance,
+    /// Sum of the "stake" shares of all sub-accounts
+    pub stake_shares: ShareBalance,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct OmnibusAccountView {
+    pub account_id: AccountId,
+    pub sub_accounts_num: u64,
+    /// Sum of the unstaked balance of all sub-accounts
+    pub unstaked_balance: U128,
+    /// Sum of the LiNEAR balance of all sub-accounts
+    pub stake_shares: U128,
+    /// Whether the sums of sub-accounts equal the balances of the account
+    pub consistent: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct SubAccountView {
+    pub account_id: AccountId,
+    pub sub_account_id: SubAccountId,
+    /// The unstaked balance that can be withdrawn or staked.
+    pub unstaked_balance: U128,
+    /// The amount balance staked at the current "stake" share price.
+    pub staked_balance: U128,
+    /// The LiNEAR balance
+    pub stake_shares: U128,
+    /// The minimum epoch height when the withdrawn is allowed.
+    pub unstaked_available_epoch_height: EpochHeight,
+    /// Whether the unstaked balance is available for withdrawal now.
+    pub can_withdraw: bool,
+}
+
+#[near_bindgen]
+impl LiquidStakingContract {
+    /// Turn the predecessor account into an omnibus account, whose balances are split
+    /// into sub-accounts. The account should be registered without any balance.
+    /// The attached deposit pays for the omnibus ledger, see `internal_charge_feature_storage()`.
+    #[payable]
+    pub fn enable_sub_accounts(&mut self) {
+        self.assert_running();
+        let account_id = env::predecessor_account_id();
+        let account = self
+            .internal_find_account(&account_id)
+            .expect(ERR_ACCOUNT_NOT_REGISTERED);
+        require!(
+            !self.internal_is_omnibus(&account_id),
+            ERR_SUB_ACCOUNTS_ALREADY_ENABLED
+        );
+        require!(
+            account.unstaked == 0 && account.stake_shares == 0,
+            ERR_OMNIBUS_POSITIVE_BALANCE
+        );
+        require!(
+            self.beneficiaries.get(&account_id).is_none(),
+            ERR_OMNIBUS_BENEFICIARY
+        );
+
+        self.internal_charge_feature_storage(
+            &account_id,
+            self.internal_omnibus_account_storage_cost(),
+            ERR_NO_ENOUGH_OMNIBUS_STORAGE_DEPOSIT,
+        );
+
+        self.omnibus_accounts
+            .insert(&account_id, &OmnibusAccount::default());
+        self.omnibus_accounts_num += 1;
+
+        Event::EnableSubAccounts {
+            account_id: &account_id,
+        }
+        .emit();
+    }
+
+    /// Turn the omnibus account back into a regular account and refund the storage
+    /// of the omnibus ledger. All sub-accounts should be emptied first.
+    /// Requires exactly 1 yocto NEAR.
+    #[payable]
+    pub fn disable_sub_accounts(&mut self) {
+        assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+        self.internal_remove_omnibus_account(&account_id);
+
+        self.internal_refund_feature_storage(
+            &account_id,
+            self.internal_omnibus_account_storage_cost(),
+        );
+
+        Event::DisableSubAccounts {
+            account_id: &account_id,
+        }
+        .emit();
+    }
+
+    /// Adds the attached deposit to the storage balance of the omnibus account, which pays
+    /// the storage of sub-accounts created by LiNEAR transferred from others.
+    /// The available storage balance could be withdrawn by `storage_withdraw()`.
+    #[payable]
+    pub fn sub_account_storage_deposit(&mut self) -> StorageBalance {
+        let account_id = env::predecessor_account_id();
+        require!(
+            self.internal_is_omnibus(&account_id),
+            ERR_NOT_OMNIBUS_ACCOUNT
+        );
+        let storage_deposit = self.internal_get_storage_deposit(&account_id);
+        self.internal_set_storage_deposit(&account_id, storage_deposit + env::attached_deposit());
+        self.internal_storage_balance_of(&account_id).unwrap()
+    }
+
+    /// Deposits the attached amount into the sub-account of the predecessor.
+    /// If the sub-account doesn't exist, its storage cost is deducted from the attached
+    /// amount and added to the storage deposit of the predecessor.
+    #[payable]
+    pub fn sub_account_deposit(&mut self, sub_account_id: SubAccountId) {
+        let account_id = env::predecessor_account_id();
+        let amount =
+            self.internal_ensure_sub_account(&account_id, &sub_account_id, env::attached_deposit());
+        self.internal_deposit(&account_id, Some(&sub_account_id), amount);
+    }
+
+    /// Deposits the attached amount into the sub-account of the predecessor and stakes it.
+    /// The storage cost of a new sub-account is deducted the same as `sub_account_deposit()`.
+    /// Returns the received LiNEAR amount
+    #[payable]
+    pub fn sub_account_deposit_and_stake(&mut self, sub_account_id: SubAccountId) -> U128 {
+        let account_id = env::predecessor_account_id();
+        let amount =
+            self.internal_ensure_sub_account(&account_id, &sub_account_id, env::attached_deposit());
+        self.internal_deposit(&account_id, Some(&sub_account_id), amount);
+        self.internal_stake(&account_id, Some(&sub_account_id), amount)
+            .into()
+    }
+
+    /// Stakes the given amount from the unstaked balance of the sub-account.
+    /// Returns the received LiNEAR amount
+    pub fn sub_account_stake(&mut self, sub_account_id: SubAccountId, amount: U128) -> U128 {
+        let account_id = env::predecessor_account_id();
+        self.internal_stake(&account_id, Some(&sub_account_id), amount.into())
+            .into()
+    }
+
+    /// Unstakes the given amount from the staked balance of the sub-account.
+    /// The unstaked balance will be available for withdrawal in four epochs.
+    pub fn sub_account_unstake(&mut self, sub_account_id: SubAccountId, amount: U128) {
+        let account_id = env::predecessor_account_id();
+        self.internal_unstake(&account_id, Some(&sub_account_id), amount.into());
+    }
+
+    /// Unstakes all staked balance of the sub-account.
+    pub fn sub_account_unstake_all(&mut self, sub_account_id: SubAccountId) {
+        let account_id = env::predecessor_account_id();
+        let sub_account = self.internal_get_sub_account(&account_id, &sub_account_id);
+        let amount = self.staked_amount_from_num_shares_rounded_down(sub_account.stake_shares);
+        self.internal_unstake(&account_id, Some(&sub_account_id), amount);
+    }
+
+    /// Withdraws the unstaked balance of the sub-account to the predecessor.
+    /// It's only allowed if the sub-account didn't unstake in the four most recent epochs.
+    pub fn sub_account_withdraw(&mut self, sub_account_id: SubAccountId, amount: U128) {
+        let account_id = env::predecessor_account_id();
+        self.internal_withdraw(
+            &account_id,
+            Some(&sub_account_id),
+            &account_id,
+            amount.into(),
+        );
+    }
+
+    /// Withdraws the entire unstaked balance of the sub-account to the predecessor.
+    pub fn sub_account_withdraw_all(&mut self, sub_account_id: SubAccountId) {
+        let account_id = env::predecessor_account_id();
+        let sub_account = self.internal_get_sub_account(&account_id, &sub_account_id);
+        self.internal_withdraw(
+            &account_id,
+            Some(&sub_account_id),
+            &account_id,
+            sub_account.unstaked,
+        );
+    }
+
+    /// Withdraws the unstaked balance of the sub-account to `receiver_id`.
+    /// The balance is restored if the transfer fails, e.g. `receiver_id` doesn't exist.
+    pub fn sub_account_withdraw_to(
+        &mut self,
+        sub_account_id: SubAccountId,
+        receiver_id: AccountId,
+        amount: U128,
+    ) -> Promise {
+        let account_id = env::predecessor_account_id();
+        self.internal_withdraw_to(account_id, Some(sub_account_id), receiver_id, amount.into())
+    }
+
+    /// Moves LiNEAR between two sub-accounts of the predecessor, the balance of the
+    /// omnibus account is not changed.
+    /// If the receiver sub-account doesn't exist, the attached deposit should cover its
+    /// storage and the exceeding deposit is refunded. Otherwise requires exactly 1 yocto NEAR.
+    #[payable]
+    pub fn sub_account_transfer(
+        &mut self,
+        from_sub_account_id: SubAccountId,
+        to_sub_account_id: SubAccountId,
+        amount: U128,
+    ) {
+        self.assert_running();
+        let account_id = env::predecessor_account_id();
+        let amount: ShareBalance = amount.into();
+        require!(
+            from_sub_account_id != to_sub_account_id,
+            ERR_SELF_SUB_ACCOUNT_TRANSFER
+        );
+        require!(amount > 0, ERR_NON_POSITIVE_TRANSFER_AMOUNT);
+
+        if self.internal_sub_account_exists(&account_id, &to_sub_account_id) {
+            assert_one_yocto();
+        } else {
+            let refund = self.internal_ensure_sub_account(
+                &account_id,
+                &to_sub_account_id,
+                env::attached_deposit(),
+            );
+            if refund > 0 {
+                Promise::new(account_id.clone()).transfer(refund);
+            }
+        }
+
+        self.internal_update_sub_account(&account_id, Some(&from_sub_account_id), |sub_account| {
+            require!(
+                sub_account.stake_shares >= amount,
+                ERR_NO_ENOUGH_SUB_ACCOUNT_BALANCE
+            );
+            sub_account.stake_shares -= amount;
+        });
+        self.internal_update_sub_account(&account_id, Some(&to_sub_account_id), |sub_account| {
+            sub_account.stake_shares += amount;
+        });
+
+        Event::SubAccountTransfer {
+            account_id: &account_id,
+            from_sub_account_id: &from_sub_account_id,
+            to_sub_account_id: &to_sub_account_id,
+            stake_shares: &U128(amount),
+        }
+        .emit();
+    }
+
+    /// Transfers LiNEAR from the sub-account of the predecessor to `receiver_id`.
+    /// If the receiver is an omnibus account, the memo names its sub-account.
+    /// Requires exactly 1 yocto NEAR.
+    #[payable]
+    pub fn ft_transfer_from_sub_account(
+        &mut self,
+        sub_account_id: SubAccountId,
+        receiver_id: AccountId,
+        amount: U128,
+        memo: Option<String>,
+    ) {
+        assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+        self.assert_allowed_receiver(&account_id, &receiver_id);
+        let receiver_sub_account_id = self.internal_receiver_sub_account(&receiver_id, &memo);
+        self.internal_ft_transfer(
+            &account_id,
+            Some(&sub_account_id),
+            &receiver_id,
+            receiver_sub_account_id.as_ref(),
+            amount.into(),
+            memo,
+        );
+    }
+
+    /// Transfers LiNEAR from the sub-account of the predecessor to `receiver_id` and calls
+    /// `ft_on_transfer` on the receiver, the unused amount is refunded to the sub-account.
+    /// Requires exactly 1 yocto NEAR and the same gas as `ft_transfer_call()`.
+    #[payable]
+    pub fn ft_transfer_call_from_sub_account(
+        &mut self,
+        sub_account_id: SubAccountId,
+        receiver_id: AccountId,
+        amount: U128,
+        memo: Option<String>,
+        msg: String,
+    ) -> PromiseOrValue<U128> {
+        assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+        self.internal_ft_transfer_call(
+            account_id,
+            Some(sub_account_id),
+            receiver_id,
+            amount.into(),
+            memo,
+            msg,
+        )
+    }
+
+    // --- View Functions ---
+
+    /// Returns the ledger summary of the omnibus account
+    pub fn get_omnibus_account(&self, account_id: AccountId) -> Option<OmnibusAccountView> {
+        self.omnibus_accounts.get(&account_id).map(|omnibus| {
+            let account = self.internal_get_account(&account_id);
+            OmnibusAccountView {
+                account_id,
+                sub_accounts_num: omnibus.sub_accounts_num,
+                unstaked_balance: omnibus.unstaked.into(),
+                stake_shares: omnibus.stake_shares.into(),
+                consistent: omnibus.unstaked == account.unstaked
+                    && omnibus.stake_shares == account.stake_shares,
+            }
+        })
+    }
+
+    /// Returns the sub-account of the omnibus account, sub-accounts without
+    /// any balance don't exist.
+    pub fn get_sub_account(
+        &self,
+        account_id: AccountId,
+        sub_account_id: SubAccountId,
+    ) -> Option<SubAccountView> {
+        if !self.internal_sub_account_exists(&account_id, &sub_account_id) {
+            return None;
+        }
+        let sub_account = self.internal_get_sub_account(&account_id, &sub_account_id);
+        Some(SubAccountView {
+            account_id,
+            sub_account_id,
+            unstaked_balance: sub_account.unstaked.into(),
+            staked_balance: self
+                .staked_amount_from_num_shares_rounded_down(sub_account.stake_shares)
+                .into(),
+            stake_shares: sub_account.stake_shares.into(),
+            unstaked_available_epoch_height: sub_account.unstaked_available_epoch_height,
+            can_withdraw: sub_account.unstaked_available_epoch_height <= get_epoch_height(),
+        })
+    }
+}
+
+impl LiquidStakingContract {
+    /// The storage fee of the ledger of one omnibus account
+    pub(crate) fn internal_omnibus_account_storage_cost(&self) -> Balance {
+        Balance::from(self.omnibus_account_storage_usage) * env::storage_byte_cost()
+    }
+
+    /// The storage fee of one sub-account, which is charged when it's created and
+    /// kept in the storage deposit of the omnibus account after it's emptied
+    pub(crate) fn internal_sub_account_storage_cost(&self) -> Balance {
+        Balance::from(self.sub_account_storage_usage) * env::storage_byte_cost()
+    }
+
+    /// The storage balance of the omnibus ledger and sub-accounts of the account
+    pub(crate) fn internal_omnibus_storage_balance(&self, account_id: &AccountId) -> Balance {
+        match self.omnibus_accounts.get(account_id) {
+            Some(omnibus) => {
+                self.internal_omnibus_account_storage_cost()
+                    + self.internal_sub_account_storage_cost()
+                        * Balance::from(omnibus.sub_accounts_num)
+            }
+            None => 0,
+        }
+    }
+
+    pub(crate) fn internal_is_omnibus(&self, account_id: &AccountId) -> bool {
+        self.omnibus_accounts.contains_key(account_id)
+    }
+
+    /// The sub-account credited by a LiNEAR transfer to `receiver_id`. Omnibus accounts
+    /// can only receive LiNEAR into the sub-account named by the memo, e.g. the deposit
+    /// ID of an exchange customer.
+    pub(crate) fn internal_receiver_sub_account(
+        &self,
+        receiver_id: &AccountId,
+        memo: &Option<String>,
+    ) -> Option<SubAccountId> {
+        if self.internal_is_omnibus(receiver_id) {
+            Some(memo.clone().expect(ERR_NO_RECEIVER_SUB_ACCOUNT))
+        } else {
+            None
+        }
+    }
+
+    /// Omnibus accounts can only be operated via sub-accounts, so that their
+    /// balances always equal the sums of sub-accounts.
+    pub(crate) fn assert_not_omnibus(&self, account_id: &AccountId) {
+        require!(!self.internal_is_omnibus(account_id), ERR_OMNIBUS_ACCOUNT);
+    }
+
+    /// Remove the ledger of the omnibus account, which should have no sub-accounts
+    pub(crate) fn internal_remove_omnibus_account(&mut self, account_id: &AccountId) {
+        let omnibus = self
+            .omnibus_accounts
+            .get(account_id)
+            .expect(ERR_NOT_OMNIBUS_ACCOUNT);
+        require!(omnibus.sub_accounts_num == 0, ERR_OMNIBUS_HAS_SUB_ACCOUNTS);
+        self.omnibus_accounts.remove(account_id);
+        self.omnibus_accounts_num -= 1;
+    }
+
+    pub(crate) fn internal_sub_account_exists(
+        &self,
+        account_id: &AccountId,
+        sub_account_id: &SubAccountId,
+    ) -> bool {
+        self.sub_accounts
+            .contains_key(&(account_id.clone(), sub_account_id.clone()))
+    }
+
+    /// Inner method to get the given sub-account or a new default value sub-account.
+    pub(crate) fn internal_get_sub_account(
+        &self,
+        account_id: &AccountId,
+        sub_account_id: &SubAccountId,
+    ) -> Account {
+        self.sub_accounts
+            .get(&(account_id.clone(), sub_account_id.clone()))
+            .map(|sub_account| sub_account.into())
+            .unwrap_or_default()
+    }
+
+    /// Create the sub-account if it doesn't exist, whose storage is paid from `deposit`
+    /// and added to the storage deposit of the omnibus account.
+    /// Returns the remaining deposit.
+    pub(crate) fn internal_ensure_sub_account(
+        &mut self,
+        account_id: &AccountId,
+        sub_account_id: &SubAccountId,
+        deposit: Balance,
+    ) -> Balance {
+        assert_valid_sub_account_id(sub_account_id);
+        if self.internal_sub_account_exists(account_id, sub_account_id) {
+            return deposit;
+        }
+        let storage_cost = self.internal_sub_account_storage_cost();
+        require!(
+            deposit >= storage_cost,
+            ERR_NO_ENOUGH_SUB_ACCOUNT_STORAGE_DEPOSIT
+        );
+        let storage_deposit = self.internal_get_storage_deposit(account_id);
+        self.internal_set_storage_deposit(account_id, storage_deposit + storage_cost);
+        self.internal_insert_sub_account(account_id, sub_account_id);
+
+        deposit - storage_cost
+    }
+
+    /// Create the sub-account if it doesn't exist, whose storage is paid from the available
+    /// storage balance of the omnibus account, see `sub_account_storage_deposit()`.
+    /// It's used when LiNEAR is transferred to the sub-account by others.
+    pub(crate) fn internal_ensure_sub_account_from_storage_balance(
+        &mut self,
+        account_id: &AccountId,
+        sub_account_id: &SubAccountId,
+    ) {
+        assert_valid_sub_account_id(sub_account_id);
+        if self.internal_sub_account_exists(account_id, sub_account_id) {
+            return;
+        }
+        require!(
+            self.internal_get_storage_deposit(account_id)
+                >= self.internal_account_min_storage_balance(account_id)
+                    + self.internal_sub_account_storage_cost(),
+            ERR_NO_ENOUGH_SUB_ACCOUNT_STORAGE_BALANCE
+        );
+        self.internal_insert_sub_account(account_id, sub_account_id);
+    }
+
+    /// Create the sub-account emptied by a failed withdrawal again without charging
+    /// its storage, whose deposit is kept by the omnibus account unless withdrawn.
+    pub(crate) fn internal_restore_sub_account(
+        &mut self,
+        account_id: &AccountId,
+        sub_account_id: &SubAccountId,
+    ) {
+        if !self.internal_sub_account_exists(account_id, sub_account_id) {
+            self.internal_insert_sub_account(account_id, sub_account_id);
+        }
+    }
+
+    fn internal_insert_sub_account(
+        &mut self,
+        account_id: &AccountId,
+        sub_account_id: &SubAccountId,
+    ) {
+        let mut omnibus = self
+            .omnibus_accounts
+            .get(account_id)
+            .expect(ERR_NOT_OMNIBUS_ACCOUNT);
+        self.sub_accounts.insert(
+            &(account_id.clone(), sub_account_id.clone()),
+            &Account::default().into(),
+        );
+        omnibus.sub_accounts_num += 1;
+        self.omnibus_accounts.insert(account_id, &omnibus);
+        self.sub_accounts_num += 1;
+    }
+
+    /// Apply the same change made to the balances of `account_id` to its sub-account,
+    /// so the omnibus account always equals the sum of its sub-accounts.
+    /// Without `sub_account_id`, the account must not be an omnibus account.
+    /// Sub-accounts without any balance are removed.
+    pub(crate) fn internal_update_sub_account<F>(
+        &mut self,
+        account_id: &AccountId,
+        sub_account_id: Option<&SubAccountId>,
+        f: F,
+    ) where
+        F: FnOnce(&mut Account),
+    {
+        let sub_account_id = match sub_account_id {
+            Some(sub_account_id) => sub_account_id,
+            None => {
+                self.assert_not_omnibus(account_id);
+                return;
+            }
+        };
+        let mut omnibus = self
+            .omnibus_accounts
+            .get(account_id)
+            .expect(ERR_NOT_OMNIBUS_ACCOUNT);
+        let key = (account_id.clone(), sub_account_id.clone());
+        let mut sub_account: Account = self
+            .sub_accounts
+            .get(&key)
+            .expect(ERR_SUB_ACCOUNT_NOT_EXIST)
+            .into();
+
+        omnibus.unstaked -= sub_account.unstaked;
+        omnibus.stake_shares -= sub_account.stake_shares;
+        f(&mut sub_account);
+        omnibus.unstaked += sub_account.unstaked;
+        omnibus.stake_shares += sub_account.stake_shares;
+
+        if sub_account.unstaked == 0 && sub_account.stake_shares == 0 {
+            self.sub_accounts.remove(&key);
+            omnibus.sub_accounts_num -= 1;
+            self.sub_accounts_num -= 1;
+        } else {
+            self.sub_accounts.insert(&key, &sub_account.into());
+        }
+        self.omnibus_accounts.insert(account_id, &omnibus);
+    }
+}
+
+fn assert_valid_sub_account_id(sub_account_id: &SubAccountId) {
+    require!(
+        !sub_account_id.is_empty() && sub_account_id.len() <= MAX_SUB_ACCOUNT_ID_LEN,
+        ERR_INVALID_SUB_ACCOUNT_ID
+    );
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_contract_standards::fungible_token::core::FungibleTokenCore;
+    use near_contract_standards::storage_management::StorageManagement;
+    use near_sdk::test_utils::accounts;
+    use near_sdk::{testing_env, PromiseResult, RuntimeFeesConfig, VMConfig};
+
+    use super::*;
+    use crate::test_utils::*;
+
+    fn new_contract_with_omnibus(account_id: &AccountId) -> LiquidStakingContract {
+        let mut contract = new_contract();
+        set_context(account_id, contract.internal_min_storage_balance());
+        contract.storage_deposit(None, None);
+        set_context(account_id, contract.internal_omnibus_account_storage_cost());
+        contract.enable_sub_accounts();
+        contract
+    }
+
+    fn assert_consistent(contract: &LiquidStakingContract, account_id: &AccountId) {
+        assert!(
+            contract
+                .get_omnibus_account(account_id.clone())
+                .unwrap()
+                .consistent
+        );
+    }
+
+    #[test]
+    fn sub_accounts_stake_transfer_and_unstake() {
+        let exchange = accounts(2);
+        let mut contract = new_contract_with_omnibus(&exchange);
+        let (alice, bob) = ("alice".to_string(), "bob".to_string());
+        let sub_account_cost = contract.internal_sub_account_storage_cost();
+
+        set_context(&exchange, 10 * ONE_NEAR + sub_account_cost);
+        contract.sub_account_deposit_and_stake(alice.clone());
+        set_context(&exchange, 5 * ONE_NEAR + sub_account_cost);
+        contract.sub_account_deposit(bob.clone());
+        set_context(&exchange, 0);
+        contract.sub_account_stake(bob.clone(), U128(2 * ONE_NEAR));
+        assert_consistent(&contract, &exchange);
+
+        set_context(&exchange, 1);
+        contract.sub_account_transfer(alice.clone(), bob.clone(), U128(4 * ONE_NEAR));
+        contract.sub_account_unstake(bob.clone(), U128(ONE_NEAR));
+        assert_consistent(&contract, &exchange);
+
+        let alice_view = contract
+            .get_sub_account(exchange.clone(), alice.clone())
+            .unwrap();
+        assert_eq!(alice_view.stake_shares.0, 6 * ONE_NEAR);
+        let bob_view = contract
+            .get_sub_account(exchange.clone(), bob.clone())
+            .unwrap();
+        assert_eq!(bob_view.stake_shares.0, 5 * ONE_NEAR);
+        assert_eq!(bob_view.unstaked_balance.0, 4 * ONE_NEAR);
+        assert_eq!(contract.ft_balance_of(exchange.clone()).0, 11 * ONE_NEAR);
+
+        // emptied sub-account is removed and its storage could be withdrawn
+        contract.sub_account_transfer(alice.clone(), bob, U128(6 * ONE_NEAR));
+        assert!(contract.get_sub_account(exchange.clone(), alice).is_none());
+        assert_consistent(&contract, &exchange);
+        let omnibus = contract.get_omnibus_account(exchange.clone()).unwrap();
+        assert_eq!(omnibus.sub_accounts_num, 1);
+        assert_eq!(
+            contract.storage_balance_of(exchange).unwrap().available.0,
+            sub_account_cost
+        );
+    }
+
+    #[test]
+    fn restore_sub_account_after_failed_withdraw_to() {
+        let exchange = accounts(2);
+        let mut contract = new_contract_with_omnibus(&exchange);
+        let alice = "alice".to_string();
+        let sub_account_cost = contract.internal_sub_account_storage_cost();
+
+        set_context(&exchange, 5 * ONE_NEAR + sub_account_cost);
+        contract.sub_account_deposit(alice.clone());
+        set_context(&exchange, 0);
+        contract.sub_account_withdraw_to(alice.clone(), accounts(3), U128(5 * ONE_NEAR));
+        assert!(contract
+            .get_sub_account(exchange.clone(), alice.clone())
+            .is_none());
+
+        // the transfer failed, the emptied sub-account is created again
+        contract.internal_restore_withdraw(&exchange, Some(&alice), 5 * ONE_NEAR);
+        let alice_view = contract.get_sub_account(exchange.clone(), alice).unwrap();
+        assert_eq!(alice_view.unstaked_balance.0, 5 * ONE_NEAR);
+        assert_eq!(
+            contract.get_account_unstaked_balance(exchange.clone()).0,
+            5 * ONE_NEAR
+        );
+        assert_consistent(&contract, &exchange);
+        assert_eq!(
+            contract
+                .get_omnibus_account(exchange)
+                .unwrap()
+                .sub_accounts_num,
+            1
+        );
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "The account is an omnibus account, which can only be operated via sub-accounts"
+    )]
+    fn omnibus_account_cannot_stake_directly() {
+        let exchange = accounts(2);
+        let mut contract = new_contract_with_omnibus(&exchange);
+        set_context(&exchange, ONE_NEAR);
+        contract.deposit_and_stake();
+    }
+
+    #[test]
+    fn ft_transfer_to_and_from_sub_accounts() {
+        let exchange = accounts(2);
+        let bob = accounts(3);
+        let mut contract = new_contract_with_omnibus(&exchange);
+        let alice = "alice".to_string();
+        set_context(&bob, 2 * ONE_NEAR);
+        contract.deposit_and_stake();
+        set_context(&exchange, contract.internal_sub_account_storage_cost());
+        contract.sub_account_storage_deposit();
+
+        // the deposit of the exchange customer is credited to the sub-account in the memo
+        set_context(&bob, 1);
+        contract.ft_transfer(exchange.clone(), U128(ONE_NEAR), Some(alice.clone()));
+        let alice_view = contract
+            .get_sub_account(exchange.clone(), alice.clone())
+            .unwrap();
+        assert_eq!(alice_view.stake_shares.0, ONE_NEAR);
+        assert_eq!(
+            contract
+                .storage_balance_of(exchange.clone())
+                .unwrap()
+                .available
+                .0,
+            0
+        );
+        assert_consistent(&contract, &exchange);
+
+        set_context(&exchange, 1);
+        contract.ft_transfer_from_sub_account(alice.clone(), bob.clone(), U128(ONE_NEAR), None);
+        assert!(contract.get_sub_account(exchange.clone(), alice).is_none());
+        assert_eq!(contract.ft_balance_of(bob).0, 2 * ONE_NEAR);
+        assert_consistent(&contract, &exchange);
+    }
+
+    #[test]
+    fn refund_ft_transfer_call_to_sub_account() {
+        let exchange = accounts(2);
+        let mut contract = new_contract_with_omnibus(&exchange);
+        let alice = "alice".to_string();
+        set_context(
+            &exchange,
+            ONE_NEAR + contract.internal_sub_account_storage_cost(),
+        );
+        contract.sub_account_deposit_and_stake(alice.clone());
+        set_context(&accounts(3), contract.internal_min_storage_balance());
+        contract.storage_deposit(None, None);
+
+        set_context(&exchange, 1);
+        contract.internal_ft_transfer(&exchange, Some(&alice), &accounts(3), None, ONE_NEAR, None);
+        assert!(contract
+            .get_sub_account(exchange.clone(), alice.clone())
+            .is_none());
+
+        // `ft_on_transfer()` failed, the emptied sub-account is refunded
+        testing_env!(
+            get_context(&accounts(0), 0).build(),
+            VMConfig::test(),
+            RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Failed]
+        );
+        let used = contract.ft_resolve_sub_account_transfer(
+            exchange.clone(),
+            Some(alice.clone()),
+            accounts(3),
+            None,
+            U128(ONE_NEAR),
+        );
+        assert_eq!(used.0, 0);
+        let alice_view = contract.get_sub_account(exchange.clone(), alice).unwrap();
+        assert_eq!(alice_view.stake_shares.0, ONE_NEAR);
+        assert_eq!(contract.ft_balance_of(accounts(3)).0, 0);
+        assert_consistent(&contract, &exchange);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Transfers to an omnibus account should name the receiver sub-account in the memo"
+    )]
+    fn ft_transfer_to_omnibus_account_without_memo() {
+        let exchange = accounts(2);
+        let mut contract = new_contract_with_omnibus(&exchange);
+        set_context(&accounts(3), ONE_NEAR);
+        contract.deposit_and_stake();
+
+        set_context(&accounts(3), 1);
+        contract.ft_transfer(exchange, U128(ONE_NEAR), None);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "The available storage balance of the omnibus account is less than the storage cost of the sub-account"
+    )]
+    fn ft_transfer_to_new_sub_account_without_storage_balance() {
+        let exchange = accounts(2);
+        let mut contract = new_contract_with_omnibus(&exchange);
+        set_context(&accounts(3), ONE_NEAR);
+        contract.deposit_and_stake();
+
+        set_context(&accounts(3), 1);
+        contract.ft_transfer(exchange, U128(ONE_NEAR), Some("alice".to_string()));
+    }
+
+    #[test]
+    #[should_panic(expected = "The sub-account doesn't have enough LiNEAR balance")]
+    fn transfer_more_than_sub_account_balance() {
+        let exchange = accounts(2);
+        let mut contract = new_contract_with_omnibus(&exchange);
+        let sub_account_cost = contract.internal_sub_account_storage_cost();
+        set_context(&exchange, ONE_NEAR + sub_account_cost);
+        contract.sub_account_deposit_and_stake("alice".to_string());
+
+        set_context(&exchange, sub_account_cost);
+        contract.sub_account_transfer("alice".to_string(), "bob".to_string(), U128(2 * ONE_NEAR));
+    }
+}
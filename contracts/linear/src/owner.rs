@@ -43,6 +43,10 @@ impl LiquidStakingContract {
     pub fn set_beneficiary(&mut self, account_id: AccountId, bps: u32) {
         self.assert_running();
         self.assert_owner();
+        require!(
+            !self.internal_is_omnibus(&account_id),
+            ERR_OMNIBUS_BENEFICIARY
+        );
 
         if self.beneficiaries.len() == MAX_BENEFICIARIES
             && self.beneficiaries.get(&account_id).is_none()
@@ -100,6 +104,75 @@ impl LiquidStakingContract {
         .emit();
     }
 
+    /// Set the wNEAR token account ID, whose transfers are accepted and staked
+    pub fn set_wnear_account_id(&mut self, account_id: AccountId) {
+        self.assert_running();
+        self.assert_owner();
+        self.wnear_account_id = Some(account_id.clone());
+        Event::SetWnear {
+            account_id: &account_id,
+        }
+        .emit();
+    }
+
+    /// Set the max total staked amount and the max amounts staked and unstaked per epoch.
+    /// `None` removes the limit. Lowering a limit below the current amount only blocks
+    /// further staking or unstaking.
+    pub fn set_stake_limits(
+        &mut self,
+        max_total_staked_near_amount: Option<U128>,
+        max_epoch_stake_amount: Option<U128>,
+        max_epoch_unstake_amount: Option<U128>,
+    ) {
+        self.assert_owner();
+        self.stake_limits = StakeLimits {
+            max_total_staked_near_amount: max_total_staked_near_amount.map(|v| v.0),
+            max_epoch_stake_amount: max_epoch_stake_amount.map(|v| v.0),
+            max_epoch_unstake_amount: max_epoch_unstake_amount.map(|v| v.0),
+        };
+        Event::SetStakeLimits {
+            max_total_staked_near_amount: max_total_staked_near_amount.as_ref(),
+            max_epoch_stake_amount: max_epoch_stake_amount.as_ref(),
+            max_epoch_unstake_amount: max_epoch_unstake_amount.as_ref(),
+        }
+        .emit();
+    }
+
+    /// Set the minimum amounts of NEAR to deposit, stake and unstake. `0` removes the minimum.
+    pub fn set_min_amounts(
+        &mut self,
+        min_deposit_amount: U128,
+        min_stake_amount: U128,
+        min_unstake_amount: U128,
+    ) {
+        self.assert_owner();
+        self.min_amounts = MinAmounts {
+            min_deposit_amount: min_deposit_amount.0,
+            min_stake_amount: min_stake_amount.0,
+            min_unstake_amount: min_unstake_amount.0,
+        };
+        Event::SetMinAmounts {
+            min_deposit_amount: &min_deposit_amount,
+            min_stake_amount: &min_stake_amount,
+            min_unstake_amount: &min_unstake_amount,
+        }
+        .emit();
+    }
+
+    /// Set the target ratio of NEAR kept unstaked in the contract balance to serve
+    /// unstakes immediately, in basis points of the total staked amount.
+    /// The reserve is adjusted towards the target in the next epoch cleanup.
+    ///
+    /// The reserve earns no staking rewards but is still counted in the LiNEAR price,
+    /// so the LiNEAR APY is lowered by the same ratio, e.g. a 20% reserve turns
+    /// a 10% validator APY into 8%.
+    pub fn set_liquidity_reserve_bps(&mut self, bps: u32) {
+        self.assert_owner();
+        require!(bps <= FULL_BASIS_POINTS, ERR_INVALID_LIQUIDITY_RESERVE_BPS);
+        self.liquidity_reserve_bps = bps;
+        Event::SetLiquidityReserve { bps: &bps }.emit();
+    }
+
     // --- Pause ---
 
     pub fn pause(&mut self) {
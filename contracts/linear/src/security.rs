@@ -0,0 +1,420 @@
+use crate::events::Event;
+use crate::*;
+use near_sdk::{assert_one_yocto, near_bindgen};
+
+/// The security mode of an account, which restricts the receivers of its withdrawals
+/// and LiNEAR transfers to an allowlist. Changes of the allowlist only take effect
+/// after `NUM_EPOCHS_TO_CHANGE_ALLOWLIST`, so a compromised key cannot move the funds
+/// out before the owner notices the change and cancels it.
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct WithdrawalAllowlist {
+    /// The receivers allowed before the pending change takes effect
+    pub allowlist: Vec<AccountId>,
+    pub pending_change: Option<AllowlistChange>,
+}
+
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct AllowlistChange {
+    /// The new allowlist, `None` if the security mode is disabled
+    pub allowlist: Option<Vec<AccountId>>,
+    /// The epoch height from which the change takes effect
+    pub effective_epoch_height: EpochHeight,
+}
+
+impl WithdrawalAllowlist {
+    /// The allowlist in effect at the epoch height, `None` if the security mode is disabled
+    pub fn effective_allowlist(&self, epoch_height: EpochHeight) -> Option<&Vec<AccountId>> {
+        match &self.pending_change {
+            Some(change) if change.effective_epoch_height <= epoch_height => {
+                change.allowlist.as_ref()
+            }
+            _ => Some(&self.allowlist),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct WithdrawalAllowlistView {
+    pub account_id: AccountId,
+    /// The receivers allowed now
+    pub allowlist: Vec<AccountId>,
+    /// The allowlist that takes effect at `pending_effective_epoch_height`
+    pub pending_allowlist: Option<Vec<AccountId>>,
+    /// Whether the security mode is disabled at `pending_effective_epoch_height`
+    pub pending_disable: bool,
+    pub pending_effective_epoch_height: Option<EpochHeight>,
+}
+
+#[near_bindgen]
+impl LiquidStakingContract {
+    /// Enable the security mode of the predecessor, which takes effect immediately.
+    /// Withdrawals and LiNEAR transfers are only allowed to the accounts in `allowlist`,
+    /// which should include the account itself for plain `withdraw()`.
+    /// The attached deposit pays for the allowlist of up to `MAX_WITHDRAWAL_ALLOWLIST_LEN`
+    /// receivers with a pending change, see `internal_charge_feature_storage()`.
+    #[payable]
+    pub fn enable_withdrawal_allowlist(&mut self, allowlist: Vec<AccountId>) {
+        self.assert_running();
+        let account_id = env::predecessor_account_id();
+        self.internal_find_account(&account_id)
+            .expect(ERR_ACCOUNT_NOT_REGISTERED);
+        require!(
+            self.internal_apply_withdrawal_allowlist_change(&account_id)
+                .is_none(),
+            ERR_WITHDRAWAL_ALLOWLIST_ALREADY_ENABLED
+        );
+        assert_valid_allowlist(&allowlist);
+
+        self.internal_charge_feature_storage(
+            &account_id,
+            self.internal_withdrawal_allowlist_storage_cost(),
+            ERR_NO_ENOUGH_WITHDRAWAL_ALLOWLIST_STORAGE_DEPOSIT,
+        );
+
+        self.withdrawal_allowlists.insert(
+            &account_id,
+            &WithdrawalAllowlist {
+                allowlist: allowlist.clone(),
+                pending_change: None,
+            },
+        );
+        self.withdrawal_allowlists_num += 1;
+
+        Event::EnableWithdrawalAllowlist {
+            account_id: &account_id,
+            allowlist: &allowlist,
+        }
+        .emit();
+    }
+
+    /// Replace the allowlist of the predecessor after `NUM_EPOCHS_TO_CHANGE_ALLOWLIST`.
+    /// It overrides the pending change if any. Requires exactly 1 yocto NEAR.
+    /// Returns the epoch height from which the new allowlist takes effect.
+    #[payable]
+    pub fn update_withdrawal_allowlist(&mut self, allowlist: Vec<AccountId>) -> EpochHeight {
+        assert_one_yocto();
+        assert_valid_allowlist(&allowlist);
+        self.internal_request_withdrawal_allowlist_change(Some(allowlist))
+    }
+
+    /// Disable the security mode of the predecessor after `NUM_EPOCHS_TO_CHANGE_ALLOWLIST`.
+    /// It overrides the pending change if any. Requires exactly 1 yocto NEAR.
+    /// Returns the epoch height from which the security mode is disabled.
+    #[payable]
+    pub fn disable_withdrawal_allowlist(&mut self) -> EpochHeight {
+        assert_one_yocto();
+        self.internal_request_withdrawal_allowlist_change(None)
+    }
+
+    /// Cancel the allowlist change of the predecessor that hasn't taken effect yet.
+    /// Requires exactly 1 yocto NEAR.
+    #[payable]
+    pub fn cancel_withdrawal_allowlist_change(&mut self) {
+        assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+        let mut record = self
+            .internal_apply_withdrawal_allowlist_change(&account_id)
+            .expect(ERR_WITHDRAWAL_ALLOWLIST_NOT_ENABLED);
+        require!(
+            record.pending_change.take().is_some(),
+            ERR_NO_WITHDRAWAL_ALLOWLIST_CHANGE
+        );
+        self.withdrawal_allowlists.insert(&account_id, &record);
+
+        Event::CancelWithdrawalAllowlistChange {
+            account_id: &account_id,
+        }
+        .emit();
+    }
+
+    /// Persist the allowlist change of the account that has taken effect. It could be
+    /// called by anyone, the storage deposit is refunded if the security mode is disabled.
+    /// Effective changes are enforced without calling this method.
+    pub fn apply_withdrawal_allowlist_change(&mut self, account_id: AccountId) {
+        let record = self
+            .withdrawal_allowlists
+            .get(&account_id)
+            .expect(ERR_WITHDRAWAL_ALLOWLIST_NOT_ENABLED);
+        let epoch_height = get_epoch_height();
+        require!(
+            matches!(
+                record.pending_change,
+                Some(change) if change.effective_epoch_height <= epoch_height
+            ),
+            ERR_WITHDRAWAL_ALLOWLIST_CHANGE_NOT_EFFECTIVE
+        );
+        self.internal_apply_withdrawal_allowlist_change(&account_id);
+    }
+
+    /// Returns the allowlist in effect and the pending change of the account,
+    /// `None` if the security mode is disabled
+    pub fn get_withdrawal_allowlist(
+        &self,
+        account_id: AccountId,
+    ) -> Option<WithdrawalAllowlistView> {
+        let record = self.withdrawal_allowlists.get(&account_id)?;
+        let epoch_height = get_epoch_height();
+        let allowlist = record.effective_allowlist(epoch_height)?.clone();
+        let pending_change = record
+            .pending_change
+            .filter(|change| change.effective_epoch_height > epoch_height);
+        Some(WithdrawalAllowlistView {
+            account_id,
+            allowlist,
+            pending_disable: matches!(
+                pending_change,
+                Some(AllowlistChange {
+                    allowlist: None,
+                    ..
+                })
+            ),
+            pending_effective_epoch_height: pending_change
+                .as_ref()
+                .map(|change| change.effective_epoch_height),
+            pending_allowlist: pending_change.and_then(|change| change.allowlist),
+        })
+    }
+}
+
+impl LiquidStakingContract {
+    /// The storage fee of the allowlist of one account
+    pub(crate) fn internal_withdrawal_allowlist_storage_cost(&self) -> Balance {
+        Balance::from(self.withdrawal_allowlist_storage_usage) * env::storage_byte_cost()
+    }
+
+    /// Whether the account has paid the storage of an allowlist, which is kept
+    /// until the disabling change is applied
+    pub(crate) fn internal_has_withdrawal_allowlist(&self, account_id: &AccountId) -> bool {
+        self.withdrawal_allowlists.contains_key(account_id)
+    }
+
+    /// Asserts that the account could withdraw or transfer LiNEAR to the receiver
+    pub(crate) fn assert_allowed_receiver(&self, account_id: &AccountId, receiver_id: &AccountId) {
+        require!(
+            self.internal_is_allowed_receiver(account_id, receiver_id),
+            ERR_RECEIVER_NOT_ALLOWLISTED
+        );
+    }
+
+    pub(crate) fn internal_is_allowed_receiver(
+        &self,
+        account_id: &AccountId,
+        receiver_id: &AccountId,
+    ) -> bool {
+        self.withdrawal_allowlists
+            .get(account_id)
+            .and_then(|record| {
+                record
+                    .effective_allowlist(get_epoch_height())
+                    .map(|allowlist| allowlist.contains(receiver_id))
+            })
+            .unwrap_or(true)
+    }
+
+    /// Removes the allowlist of the account without refunding the storage.
+    /// Returns false if the account doesn't have one.
+    pub(crate) fn internal_remove_withdrawal_allowlist(&mut self, account_id: &AccountId) -> bool {
+        if self.withdrawal_allowlists.remove(account_id).is_none() {
+            return false;
+        }
+        self.withdrawal_allowlists_num -= 1;
+        true
+    }
+
+    fn internal_request_withdrawal_allowlist_change(
+        &mut self,
+        allowlist: Option<Vec<AccountId>>,
+    ) -> EpochHeight {
+        self.assert_running();
+        let account_id = env::predecessor_account_id();
+        let mut record = self
+            .internal_apply_withdrawal_allowlist_change(&account_id)
+            .expect(ERR_WITHDRAWAL_ALLOWLIST_NOT_ENABLED);
+        let effective_epoch_height = get_epoch_height() + NUM_EPOCHS_TO_CHANGE_ALLOWLIST;
+
+        Event::RequestWithdrawalAllowlistChange {
+            account_id: &account_id,
+            allowlist: allowlist.as_ref(),
+            effective_epoch_height: &effective_epoch_height,
+        }
+        .emit();
+
+        record.pending_change = Some(AllowlistChange {
+            allowlist,
+            effective_epoch_height,
+        });
+        self.withdrawal_allowlists.insert(&account_id, &record);
+        effective_epoch_height
+    }
+
+    /// Persists the pending change of the account if it has taken effect. The allowlist
+    /// is removed and its storage refunded if the security mode is disabled.
+    /// Returns the allowlist in effect, `None` if the security mode is disabled.
+    fn internal_apply_withdrawal_allowlist_change(
+        &mut self,
+        account_id: &AccountId,
+    ) -> Option<WithdrawalAllowlist> {
+        let mut record = self.withdrawal_allowlists.get(account_id)?;
+        let change = match record.pending_change {
+            Some(ref change) if change.effective_epoch_height <= get_epoch_height() => {
+                record.pending_change.take().unwrap()
+            }
+            _ => return Some(record),
+        };
+
+        Event::ApplyWithdrawalAllowlistChange {
+            account_id,
+            allowlist: change.allowlist.as_ref(),
+        }
+        .emit();
+
+        match change.allowlist {
+            Some(allowlist) => {
+                record.allowlist = allowlist;
+                self.withdrawal_allowlists.insert(account_id, &record);
+                Some(record)
+            }
+            None => {
+                self.internal_remove_withdrawal_allowlist(account_id);
+                self.internal_refund_feature_storage(
+                    account_id,
+                    self.internal_withdrawal_allowlist_storage_cost(),
+                );
+                None
+            }
+        }
+    }
+}
+
+fn assert_valid_allowlist(allowlist: &[AccountId]) {
+    require!(
+        !allowlist.is_empty()
+            && allowlist.len() <= MAX_WITHDRAWAL_ALLOWLIST_LEN
+            && allowlist
+                .iter()
+                .enumerate()
+                .all(|(i, account_id)| !allowlist[..i].contains(account_id)),
+        ERR_INVALID_WITHDRAWAL_ALLOWLIST
+    );
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_contract_standards::fungible_token::core::FungibleTokenCore;
+    use near_sdk::test_utils::accounts;
+
+    use super::*;
+    use crate::test_utils::*;
+
+    /// Alice stakes 5 NEAR and only allows transfers to herself and bob
+    fn new_contract_with_allowlist() -> LiquidStakingContract {
+        let mut contract = new_contract();
+        let (alice, bob) = (accounts(2), accounts(3));
+        for account_id in [&bob, &accounts(4)] {
+            contract.internal_register_account(account_id);
+        }
+        set_context(&alice, 5 * ONE_NEAR);
+        contract.deposit_and_stake();
+        set_context(
+            &alice,
+            contract.internal_withdrawal_allowlist_storage_cost(),
+        );
+        contract.enable_withdrawal_allowlist(vec![alice, bob]);
+        contract
+    }
+
+    #[test]
+    fn transfer_to_allowlist() {
+        let mut contract = new_contract_with_allowlist();
+        let (alice, bob) = (accounts(2), accounts(3));
+        assert_eq!(
+            contract.internal_get_storage_deposit(&alice),
+            contract.internal_withdrawal_allowlist_storage_cost()
+        );
+
+        set_context(&alice, 1);
+        contract.ft_transfer(bob.clone(), U128(ONE_NEAR), None);
+        assert_eq!(contract.ft_balance_of(bob).0, ONE_NEAR);
+    }
+
+    #[test]
+    #[should_panic(expected = "The receiver is not in the withdrawal allowlist of the account")]
+    fn transfer_out_of_allowlist() {
+        let mut contract = new_contract_with_allowlist();
+        set_context(&accounts(2), 1);
+        contract.ft_transfer(accounts(4), U128(ONE_NEAR), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "The receiver is not in the withdrawal allowlist of the account")]
+    fn withdraw_out_of_allowlist() {
+        let mut contract = new_contract_with_allowlist();
+        set_context(&accounts(2), 0);
+        contract.unstake(U128(ONE_NEAR));
+        contract.set_epoch_height(20);
+        contract.withdraw_to(accounts(4), U128(ONE_NEAR));
+    }
+
+    #[test]
+    fn change_takes_effect_after_delay() {
+        let mut contract = new_contract_with_allowlist();
+        let (alice, carol) = (accounts(2), accounts(4));
+        set_context(&alice, 1);
+        let effective_epoch_height = contract.update_withdrawal_allowlist(vec![carol.clone()]);
+        assert_eq!(
+            effective_epoch_height,
+            get_epoch_height() + NUM_EPOCHS_TO_CHANGE_ALLOWLIST
+        );
+        let view = contract.get_withdrawal_allowlist(alice.clone()).unwrap();
+        assert_eq!(view.allowlist.len(), 2);
+        assert_eq!(view.pending_allowlist, Some(vec![carol.clone()]));
+        assert!(!contract.internal_is_allowed_receiver(&alice, &carol));
+
+        contract.set_epoch_height(effective_epoch_height);
+        assert!(contract.internal_is_allowed_receiver(&alice, &carol));
+        assert!(!contract.internal_is_allowed_receiver(&alice, &alice));
+        let view = contract.get_withdrawal_allowlist(alice.clone()).unwrap();
+        assert_eq!(view.allowlist, vec![carol]);
+        assert_eq!(view.pending_effective_epoch_height, None);
+    }
+
+    #[test]
+    fn cancel_and_disable() {
+        let mut contract = new_contract_with_allowlist();
+        let (alice, carol) = (accounts(2), accounts(4));
+        set_context(&alice, 1);
+        contract.update_withdrawal_allowlist(vec![carol.clone()]);
+        contract.cancel_withdrawal_allowlist_change();
+        assert!(contract
+            .get_withdrawal_allowlist(alice.clone())
+            .unwrap()
+            .pending_allowlist
+            .is_none());
+
+        let effective_epoch_height = contract.disable_withdrawal_allowlist();
+        assert!(
+            contract
+                .get_withdrawal_allowlist(alice.clone())
+                .unwrap()
+                .pending_disable
+        );
+        contract.set_epoch_height(effective_epoch_height);
+        assert!(contract.get_withdrawal_allowlist(alice.clone()).is_none());
+        assert!(contract.internal_is_allowed_receiver(&alice, &carol));
+
+        // the storage is refunded once the change is applied
+        contract.apply_withdrawal_allowlist_change(alice.clone());
+        assert!(!contract.internal_has_withdrawal_allowlist(&alice));
+        assert_eq!(contract.internal_get_storage_deposit(&alice), 0);
+        assert_eq!(contract.withdrawal_allowlists_num, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "The allowlist should have 1 to 10 distinct accounts")]
+    fn duplicated_allowlist() {
+        let mut contract = new_contract_with_allowlist();
+        set_context(&accounts(2), 1);
+        contract.update_withdrawal_allowlist(vec![accounts(3), accounts(3)]);
+    }
+}
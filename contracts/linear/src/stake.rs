@@ -1,4 +1,17 @@
+use crate::events::Event;
 use crate::*;
+use near_sdk::{is_promise_success, Promise, PromiseOrValue};
+
+#[ext_contract(ext_self_withdraw_cb)]
+trait WithdrawCallbacks {
+    fn withdraw_to_callback(
+        &mut self,
+        account_id: AccountId,
+        sub_account_id: Option<SubAccountId>,
+        receiver_id: AccountId,
+        amount: U128,
+    );
+}
 
 /// -- Staking Pool change methods
 
@@ -11,17 +24,77 @@ impl LiquidStakingContract {
     /// Deposits the attached amount into the inner account of the predecessor.
     #[payable]
     pub fn deposit(&mut self) {
+        let account_id = env::predecessor_account_id();
         let amount = env::attached_deposit();
-        self.internal_deposit(amount);
+        self.internal_deposit(&account_id, None, amount);
     }
 
     /// Deposits the attached amount into the inner account of the predecessor and stakes it.
     /// - (since v1.3.0) Returns the received LiNEAR amount
     #[payable]
     pub fn deposit_and_stake(&mut self) -> U128 {
+        let account_id = env::predecessor_account_id();
+        let amount = env::attached_deposit();
+        self.internal_deposit(&account_id, None, amount);
+        self.internal_stake(&account_id, None, amount).into()
+    }
+
+    /// Deposits the attached amount into the inner account of `receiver_id`,
+    /// which is registered if not yet.
+    #[payable]
+    pub fn deposit_for(&mut self, receiver_id: AccountId) {
+        let amount = env::attached_deposit();
+        self.internal_deposit(&receiver_id, None, amount);
+    }
+
+    /// Deposits the attached amount into the inner account of `receiver_id` and stakes it,
+    /// the account is registered if not yet.
+    /// Returns the LiNEAR amount received by `receiver_id`
+    #[payable]
+    pub fn deposit_and_stake_for(&mut self, receiver_id: AccountId) -> U128 {
+        let amount = env::attached_deposit();
+        self.internal_deposit(&receiver_id, None, amount);
+        self.internal_stake(&receiver_id, None, amount).into()
+    }
+
+    /// Deposits the attached amount into the inner account of the predecessor, stakes it
+    /// and forwards the minted LiNEAR to `receiver_id` via `ft_transfer_call()`.
+    /// The unused LiNEAR is refunded to the predecessor by `ft_resolve_transfer()`.
+    /// - Requires the same gas as `ft_transfer_call()`
+    /// - Returns the LiNEAR amount used by the receiver
+    #[payable]
+    pub fn deposit_and_stake_call(
+        &mut self,
+        receiver_id: AccountId,
+        msg: String,
+        memo: Option<String>,
+    ) -> PromiseOrValue<U128> {
+        let account_id = env::predecessor_account_id();
         let amount = env::attached_deposit();
-        self.internal_deposit(amount);
-        self.internal_stake(amount).into()
+        self.internal_deposit(&account_id, None, amount);
+        let shares = self.internal_stake(&account_id, None, amount);
+        self.internal_ft_transfer_call(account_id, None, receiver_id, shares, memo, msg)
+    }
+
+    /// Donates the attached amount to the pool, which is staked without minting LiNEAR,
+    /// so the LiNEAR price increases for all holders.
+    /// The attached amount should be at least `MIN_DONATION_AMOUNT`, and is subject to
+    /// the stake limits the same as staking.
+    #[payable]
+    pub fn donate(&mut self) {
+        self.assert_running();
+        let amount = env::attached_deposit();
+        require!(amount >= MIN_DONATION_AMOUNT, ERR_DONATION_AMOUNT_TOO_SMALL);
+        self.assert_stake_limits(amount);
+
+        self.total_staked_near_amount += amount;
+        self.epoch_requested_stake_amount += amount;
+
+        Event::Donation {
+            account_id: &env::predecessor_account_id(),
+            amount: &U128(amount),
+        }
+        .emit();
     }
 
     /// Withdraws the entire unstaked balance from the predecessor account.
@@ -29,14 +102,30 @@ impl LiquidStakingContract {
     pub fn withdraw_all(&mut self) {
         let account_id = env::predecessor_account_id();
         let account = self.internal_get_account(&account_id);
-        self.internal_withdraw(account.unstaked);
+        self.internal_withdraw(&account_id, None, &account_id, account.unstaked);
     }
 
     /// Withdraws the non staked balance for given account.
     /// It's only allowed if the `unstake` action was not performed in the four most recent epochs.
     pub fn withdraw(&mut self, amount: U128) {
+        let account_id = env::predecessor_account_id();
         let amount: Balance = amount.into();
-        self.internal_withdraw(amount);
+        self.internal_withdraw(&account_id, None, &account_id, amount);
+    }
+
+    /// Withdraws the entire unstaked balance from the predecessor account to `receiver_id`.
+    /// The balance is restored if the transfer fails, e.g. `receiver_id` doesn't exist.
+    pub fn withdraw_all_to(&mut self, receiver_id: AccountId) -> Promise {
+        let account_id = env::predecessor_account_id();
+        let account = self.internal_get_account(&account_id);
+        self.internal_withdraw_to(account_id, None, receiver_id, account.unstaked)
+    }
+
+    /// Withdraws the non staked balance from the predecessor account to `receiver_id`.
+    /// The balance is restored if the transfer fails, e.g. `receiver_id` doesn't exist.
+    pub fn withdraw_to(&mut self, receiver_id: AccountId, amount: U128) -> Promise {
+        let account_id = env::predecessor_account_id();
+        self.internal_withdraw_to(account_id, None, receiver_id, amount.into())
     }
 
     /// Stakes all available unstaked balance from the inner account of the predecessor.
@@ -44,15 +133,17 @@ impl LiquidStakingContract {
     pub fn stake_all(&mut self) -> U128 {
         let account_id = env::predecessor_account_id();
         let account = self.internal_get_account(&account_id);
-        self.internal_stake(account.unstaked).into()
+        self.internal_stake(&account_id, None, account.unstaked)
+            .into()
     }
 
     /// Stakes the given amount from the inner account of the predecessor.
     /// The inner account should have enough unstaked balance.
     /// - (since v1.3.0) Returns the received LiNEAR amount
     pub fn stake(&mut self, amount: U128) -> U128 {
+        let account_id = env::predecessor_account_id();
         let amount: Balance = amount.into();
-        self.internal_stake(amount).into()
+        self.internal_stake(&account_id, None, amount).into()
     }
 
     /// Unstakes all staked balance from the inner account of the predecessor.
@@ -61,14 +152,66 @@ impl LiquidStakingContract {
         let account_id = env::predecessor_account_id();
         let account = self.internal_get_account(&account_id);
         let amount = self.staked_amount_from_num_shares_rounded_down(account.stake_shares);
-        self.internal_unstake(amount);
+        self.internal_unstake(&account_id, None, amount);
     }
 
     /// Unstakes the given amount from the inner account of the predecessor.
     /// The inner account should have enough staked balance.
     /// The new total unstaked balance will be available for withdrawal in four epochs.
     pub fn unstake(&mut self, amount: U128) {
+        let account_id = env::predecessor_account_id();
         let amount: Balance = amount.into();
-        self.internal_unstake(amount);
+        self.internal_unstake(&account_id, None, amount);
+    }
+}
+
+impl LiquidStakingContract {
+    pub(crate) fn internal_withdraw_to(
+        &mut self,
+        account_id: AccountId,
+        sub_account_id: Option<SubAccountId>,
+        receiver_id: AccountId,
+        amount: Balance,
+    ) -> Promise {
+        self.internal_withdraw(&account_id, sub_account_id.as_ref(), &receiver_id, amount)
+            .then(ext_self_withdraw_cb::withdraw_to_callback(
+                account_id,
+                sub_account_id,
+                receiver_id,
+                amount.into(),
+                env::current_account_id(),
+                NO_DEPOSIT,
+                GAS_CB_WITHDRAW_TO,
+            ))
+    }
+}
+
+#[near_bindgen]
+impl LiquidStakingContract {
+    /// Restores the unstaked balance of the account, or the sub-account, if the transfer
+    /// to receiver failed
+    #[private]
+    pub fn withdraw_to_callback(
+        &mut self,
+        account_id: AccountId,
+        sub_account_id: Option<SubAccountId>,
+        receiver_id: AccountId,
+        amount: U128,
+    ) {
+        if is_promise_success() {
+            return;
+        }
+
+        let new_unstaked_balance =
+            self.internal_restore_withdraw(&account_id, sub_account_id.as_ref(), amount.0);
+
+        Event::WithdrawToFailed {
+            account_id: &account_id,
+            sub_account_id: sub_account_id.as_ref(),
+            receiver_id: &receiver_id,
+            amount: &amount,
+            new_unstaked_balance: &U128(new_unstaked_balance),
+        }
+        .emit();
     }
 }
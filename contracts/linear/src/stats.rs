@@ -0,0 +1,298 @@
+use crate::events::Event;
+use crate::*;
+use near_sdk::{assert_one_yocto, near_bindgen};
+
+/// Lifetime counters of an account, which are only maintained after the account
+/// enables them. The storage of the counters is paid through NEP-145 storage deposit.
+#[derive(BorshSerialize, BorshDeserialize, Default)]
+pub struct AccountStats {
+    /// Total amount of NEAR deposited, including the unwrapped wNEAR
+    pub total_deposited: Balance,
+    /// Total amount of NEAR withdrawn
+    pub total_withdrawn: Balance,
+    /// Total amount of LiNEAR minted by staking or received by transfers
+    pub total_received_shares: ShareBalance,
+    /// Total amount of LiNEAR burnt by unstaking or sent by transfers
+    pub total_sent_shares: ShareBalance,
+    /// The NEAR value of the LiNEAR held when it was received, i.e. the charged amount
+    /// for staking or the value at the LiNEAR price for transfers. It's reduced
+    /// proportionally when LiNEAR is unstaked or sent.
+    pub cost_basis: Balance,
+    /// Rewards realized by unstaking or sending LiNEAR, which is the NEAR value
+    /// received minus the cost basis of the LiNEAR
+    pub realized_rewards: Balance,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AccountStatsView {
+    pub account_id: AccountId,
+    pub total_deposited: U128,
+    pub total_withdrawn: U128,
+    pub total_received_shares: U128,
+    pub total_sent_shares: U128,
+    pub cost_basis: U128,
+    pub realized_rewards: U128,
+    /// The NEAR value of the LiNEAR held at the current price
+    pub staked_balance: U128,
+    /// The staked balance minus the cost basis
+    pub unrealized_rewards: U128,
+}
+
+#[near_bindgen]
+impl LiquidStakingContract {
+    /// Start maintaining lifetime counters for the predecessor account, whose storage
+    /// is paid by the attached deposit, see `internal_charge_feature_storage()`.
+    /// The cost basis starts from the current value of the LiNEAR held.
+    #[payable]
+    pub fn enable_account_stats(&mut self) {
+        self.assert_running();
+        let account_id = env::predecessor_account_id();
+        let account = self
+            .internal_find_account(&account_id)
+            .expect(ERR_ACCOUNT_NOT_REGISTERED);
+        require!(
+            self.account_stats.get(&account_id).is_none(),
+            ERR_ACCOUNT_STATS_ALREADY_ENABLED
+        );
+
+        self.internal_charge_feature_storage(
+            &account_id,
+            self.internal_account_stats_storage_cost(),
+            ERR_NO_ENOUGH_ACCOUNT_STATS_STORAGE_DEPOSIT,
+        );
+
+        self.account_stats.insert(
+            &account_id,
+            &AccountStats {
+                cost_basis: self.staked_amount_from_num_shares_rounded_down(account.stake_shares),
+                ..Default::default()
+            },
+        );
+        self.account_stats_num += 1;
+
+        Event::EnableAccountStats {
+            account_id: &account_id,
+        }
+        .emit();
+    }
+
+    /// Remove the lifetime counters of the predecessor account and refund their storage.
+    /// Requires exactly 1 yocto NEAR.
+    #[payable]
+    pub fn disable_account_stats(&mut self) {
+        assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+        self.internal_remove_account_stats(&account_id)
+            .expect(ERR_ACCOUNT_STATS_NOT_ENABLED);
+
+        self.internal_refund_feature_storage(
+            &account_id,
+            self.internal_account_stats_storage_cost(),
+        );
+
+        Event::DisableAccountStats {
+            account_id: &account_id,
+        }
+        .emit();
+    }
+
+    /// Returns the lifetime counters of the account if they're enabled
+    pub fn get_account_stats(&self, account_id: AccountId) -> Option<AccountStatsView> {
+        self.account_stats.get(&account_id).map(|stats| {
+            let account = self.internal_get_account(&account_id);
+            let staked_balance =
+                self.staked_amount_from_num_shares_rounded_down(account.stake_shares);
+            AccountStatsView {
+                account_id,
+                total_deposited: stats.total_deposited.into(),
+                total_withdrawn: stats.total_withdrawn.into(),
+                total_received_shares: stats.total_received_shares.into(),
+                total_sent_shares: stats.total_sent_shares.into(),
+                cost_basis: stats.cost_basis.into(),
+                realized_rewards: stats.realized_rewards.into(),
+                staked_balance: staked_balance.into(),
+                unrealized_rewards: staked_balance.saturating_sub(stats.cost_basis).into(),
+            }
+        })
+    }
+}
+
+impl LiquidStakingContract {
+    /// The storage fee of the counters of one account
+    pub(crate) fn internal_account_stats_storage_cost(&self) -> Balance {
+        Balance::from(self.account_stats_storage_usage) * env::storage_byte_cost()
+    }
+
+    pub(crate) fn internal_has_account_stats(&self, account_id: &AccountId) -> bool {
+        self.account_stats.contains_key(account_id)
+    }
+
+    pub(crate) fn internal_remove_account_stats(
+        &mut self,
+        account_id: &AccountId,
+    ) -> Option<AccountStats> {
+        let stats = self.account_stats.remove(account_id);
+        if stats.is_some() {
+            self.account_stats_num -= 1;
+        }
+        stats
+    }
+
+    /// Update the counters of the account if they're enabled
+    fn internal_update_account_stats<F>(&mut self, account_id: &AccountId, f: F)
+    where
+        F: FnOnce(&mut AccountStats),
+    {
+        if let Some(mut stats) = self.account_stats.get(account_id) {
+            f(&mut stats);
+            self.account_stats.insert(account_id, &stats);
+        }
+    }
+
+    pub(crate) fn internal_record_deposit(&mut self, account_id: &AccountId, amount: Balance) {
+        self.internal_update_account_stats(account_id, |stats| {
+            stats.total_deposited += amount;
+        });
+    }
+
+    pub(crate) fn internal_record_withdraw(&mut self, account_id: &AccountId, amount: Balance) {
+        self.internal_update_account_stats(account_id, |stats| {
+            stats.total_withdrawn += amount;
+        });
+    }
+
+    /// Reverts the withdrawal recorded for a failed transfer
+    pub(crate) fn internal_record_withdraw_failed(
+        &mut self,
+        account_id: &AccountId,
+        amount: Balance,
+    ) {
+        self.internal_update_account_stats(account_id, |stats| {
+            stats.total_withdrawn = stats.total_withdrawn.saturating_sub(amount);
+        });
+    }
+
+    /// Record `shares` of LiNEAR received at the NEAR `value`
+    pub(crate) fn internal_record_shares_in(
+        &mut self,
+        account_id: &AccountId,
+        shares: ShareBalance,
+        value: Balance,
+    ) {
+        self.internal_update_account_stats(account_id, |stats| {
+            stats.total_received_shares += shares;
+            stats.cost_basis += value;
+        });
+    }
+
+    /// Record `shares` of LiNEAR sent at the NEAR `value`, out of the `prev_shares`
+    /// the account held before
+    pub(crate) fn internal_record_shares_out(
+        &mut self,
+        account_id: &AccountId,
+        shares: ShareBalance,
+        value: Balance,
+        prev_shares: ShareBalance,
+    ) {
+        self.internal_update_account_stats(account_id, |stats| {
+            let cost = if prev_shares == 0 {
+                stats.cost_basis
+            } else {
+                (U256::from(stats.cost_basis) * U256::from(shares) / U256::from(prev_shares))
+                    .as_u128()
+            };
+            stats.total_sent_shares += shares;
+            stats.cost_basis -= cost;
+            stats.realized_rewards += value.saturating_sub(cost);
+        });
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_contract_standards::fungible_token::core::FungibleTokenCore;
+    use near_contract_standards::storage_management::StorageManagement;
+    use near_sdk::test_utils::accounts;
+
+    use super::*;
+    use crate::test_utils::*;
+
+    #[test]
+    fn track_stake_transfer_and_unstake() {
+        let mut contract = new_contract();
+        let (alice, bob) = (accounts(2), accounts(3));
+        contract.internal_register_account(&alice);
+        contract.internal_register_account(&bob);
+        let storage_cost = contract.internal_account_stats_storage_cost();
+
+        set_context(&alice, storage_cost + 1);
+        contract.enable_account_stats();
+        assert_eq!(contract.internal_get_storage_deposit(&alice), storage_cost);
+
+        set_context(&alice, 10 * ONE_NEAR);
+        contract.deposit_and_stake();
+        // LiNEAR price doubles
+        contract.total_staked_near_amount = 2 * contract.total_share_amount;
+
+        set_context(&alice, 1);
+        contract.ft_transfer(bob, U128(5 * ONE_NEAR), None);
+        contract.unstake(U128(4 * ONE_NEAR));
+
+        let stats = contract.get_account_stats(alice.clone()).unwrap();
+        assert_eq!(stats.total_deposited.0, 10 * ONE_NEAR);
+        assert_eq!(stats.total_received_shares.0, 10 * ONE_NEAR);
+        assert_eq!(stats.total_sent_shares.0, 7 * ONE_NEAR);
+        assert_eq!(stats.cost_basis.0, 3 * ONE_NEAR);
+        // 14 NEAR value of 7 LiNEAR costing 7 NEAR
+        assert_eq!(stats.realized_rewards.0, 7 * ONE_NEAR);
+        assert_eq!(stats.staked_balance.0, 6 * ONE_NEAR);
+        assert_eq!(stats.unrealized_rewards.0, 3 * ONE_NEAR);
+        assert_eq!(contract.ft_balance_of(alice.clone()).0, 3 * ONE_NEAR);
+
+        // the burnt LiNEAR is sent without receiving anything
+        contract.burn_to_pool(U128(ONE_NEAR));
+        let stats = contract.get_account_stats(alice.clone()).unwrap();
+        assert_eq!(stats.total_sent_shares.0, 8 * ONE_NEAR);
+        assert_eq!(stats.cost_basis.0, 2 * ONE_NEAR);
+        assert_eq!(stats.realized_rewards.0, 7 * ONE_NEAR);
+
+        // the storage of the counters is refunded
+        contract.disable_account_stats();
+        assert!(contract.get_account_stats(alice.clone()).is_none());
+        assert_eq!(contract.internal_get_storage_deposit(&alice), 0);
+        assert_eq!(contract.account_stats_num, 0);
+    }
+
+    #[test]
+    fn stats_storage_included_in_storage_balance() {
+        let mut contract = new_contract();
+        let alice = accounts(2);
+        let min_balance = contract.internal_min_storage_balance();
+        let storage_cost = contract.internal_account_stats_storage_cost();
+
+        set_context(&alice, min_balance);
+        contract.storage_deposit(None, None);
+        set_context(&alice, storage_cost);
+        contract.enable_account_stats();
+
+        let storage_balance = contract.storage_balance_of(alice.clone()).unwrap();
+        assert_eq!(storage_balance.total.0, min_balance + storage_cost);
+        assert_eq!(storage_balance.available.0, 0);
+        assert_eq!(contract.get_account_storage_debt(alice).0, 0);
+        assert_eq!(contract.get_storage_debt().storage_debt.0, 0);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "The attached deposit is less than the storage cost of the account stats"
+    )]
+    fn enable_stats_without_storage_deposit() {
+        let mut contract = new_contract();
+        let alice = accounts(2);
+        contract.internal_register_account(&alice);
+
+        set_context(&alice, 1);
+        contract.enable_account_stats();
+    }
+}
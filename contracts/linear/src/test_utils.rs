@@ -0,0 +1,26 @@
+//! Shared fixtures of the unit tests
+use crate::*;
+use near_sdk::test_utils::{accounts, VMContextBuilder};
+use near_sdk::testing_env;
+
+/// The context of calling the contract `accounts(0)`, which holds 30 NEAR
+pub(crate) fn get_context(predecessor: &AccountId, deposit: Balance) -> VMContextBuilder {
+    let mut context = VMContextBuilder::new();
+    context
+        .current_account_id(accounts(0))
+        .signer_account_id(predecessor.clone())
+        .predecessor_account_id(predecessor.clone())
+        .attached_deposit(deposit)
+        .account_balance(30 * ONE_NEAR);
+    context
+}
+
+pub(crate) fn set_context(predecessor: &AccountId, deposit: Balance) {
+    testing_env!(get_context(predecessor, deposit).build());
+}
+
+/// The contract owned by `accounts(1)`
+pub(crate) fn new_contract() -> LiquidStakingContract {
+    set_context(&accounts(1), 0);
+    LiquidStakingContract::new(accounts(1))
+}
@@ -0,0 +1,275 @@
+use crate::events::Event;
+use crate::*;
+use near_sdk::{assert_one_yocto, json_types::U64, near_bindgen, Timestamp};
+
+/// The lock schedule of LiNEAR allocated to an account, e.g. a partner or team member.
+/// All the shares are locked before the cliff, and then released linearly from the start
+/// to the end. Locked shares cannot be transferred or unstaked, but keep earning rewards
+/// since they are LiNEAR in the account.
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct LockSchedule {
+    /// Amount of LiNEAR locked by the schedule
+    pub total_shares: ShareBalance,
+    /// The timestamp in nanoseconds when the linear release starts
+    pub start_timestamp: Timestamp,
+    /// The timestamp in nanoseconds before which no shares are released
+    pub cliff_timestamp: Timestamp,
+    /// The timestamp in nanoseconds when all the shares are released
+    pub end_timestamp: Timestamp,
+}
+
+impl LockSchedule {
+    /// Amount of LiNEAR that is still locked at the timestamp
+    pub fn locked_shares(&self, timestamp: Timestamp) -> ShareBalance {
+        if timestamp < self.cliff_timestamp {
+            self.total_shares
+        } else if timestamp >= self.end_timestamp {
+            0
+        } else {
+            let released = U256::from(self.total_shares)
+                * U256::from(timestamp - self.start_timestamp)
+                / U256::from(self.end_timestamp - self.start_timestamp);
+            self.total_shares - released.as_u128()
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct LockScheduleView {
+    pub account_id: AccountId,
+    pub total_shares: U128,
+    pub start_timestamp: U64,
+    pub cliff_timestamp: U64,
+    pub end_timestamp: U64,
+    /// Amount of LiNEAR that cannot be transferred or unstaked now
+    pub locked_shares: U128,
+    /// Amount of LiNEAR the account could transfer or unstake now
+    pub transferable_shares: U128,
+    /// The NEAR value of the locked LiNEAR
+    pub locked_balance: U128,
+    /// The NEAR value of the transferable LiNEAR
+    pub transferable_balance: U128,
+}
+
+#[near_bindgen]
+impl LiquidStakingContract {
+    /// Transfer `amount` of LiNEAR from the owner to `account_id` and lock it by the schedule.
+    /// The account is registered if not yet. Each account could have one lock schedule.
+    /// Requires exactly 1 yocto NEAR.
+    #[payable]
+    pub fn create_lock_schedule(
+        &mut self,
+        account_id: AccountId,
+        amount: U128,
+        start_timestamp: U64,
+        cliff_timestamp: U64,
+        end_timestamp: U64,
+    ) {
+        assert_one_yocto();
+        self.assert_owner();
+        require!(
+            self.lock_schedules.get(&account_id).is_none(),
+            ERR_LOCK_SCHEDULE_ALREADY_EXISTS
+        );
+        require!(
+            start_timestamp.0 <= cliff_timestamp.0
+                && cliff_timestamp.0 <= end_timestamp.0
+                && start_timestamp.0 < end_timestamp.0,
+            ERR_INVALID_LOCK_SCHEDULE
+        );
+
+        if self.internal_find_account(&account_id).is_none() {
+            self.internal_register_account(&account_id);
+        }
+        self.internal_ft_transfer(
+            &env::predecessor_account_id(),
+            None,
+            &account_id,
+            None,
+            amount.0,
+            Some("lock schedule".to_string()),
+        );
+        self.lock_schedules.insert(
+            &account_id,
+            &LockSchedule {
+                total_shares: amount.0,
+                start_timestamp: start_timestamp.0,
+                cliff_timestamp: cliff_timestamp.0,
+                end_timestamp: end_timestamp.0,
+            },
+        );
+
+        Event::CreateLockSchedule {
+            account_id: &account_id,
+            amount: &amount,
+            start_timestamp: &start_timestamp,
+            cliff_timestamp: &cliff_timestamp,
+            end_timestamp: &end_timestamp,
+        }
+        .emit();
+    }
+
+    /// Remove the lock schedule of `account_id` and transfer the LiNEAR that is
+    /// still locked back to the owner. Requires exactly 1 yocto NEAR.
+    #[payable]
+    pub fn terminate_lock_schedule(&mut self, account_id: AccountId) {
+        assert_one_yocto();
+        self.assert_owner();
+        let locked_shares = self
+            .lock_schedules
+            .remove(&account_id)
+            .expect(ERR_LOCK_SCHEDULE_NOT_EXIST)
+            .locked_shares(env::block_timestamp());
+        if locked_shares > 0 {
+            self.internal_ft_transfer(
+                &account_id,
+                None,
+                &env::predecessor_account_id(),
+                None,
+                locked_shares,
+                Some("terminate lock schedule".to_string()),
+            );
+        }
+
+        Event::TerminateLockSchedule {
+            account_id: &account_id,
+            returned_shares: &U128(locked_shares),
+        }
+        .emit();
+    }
+
+    /// Returns the lock schedule of the account with the locked and transferable balances
+    pub fn get_lock_schedule(&self, account_id: AccountId) -> Option<LockScheduleView> {
+        self.lock_schedules.get(&account_id).map(|schedule| {
+            let stake_shares = self.internal_get_account(&account_id).stake_shares;
+            let locked_shares =
+                std::cmp::min(schedule.locked_shares(env::block_timestamp()), stake_shares);
+            let transferable_shares = stake_shares - locked_shares;
+            LockScheduleView {
+                account_id,
+                total_shares: schedule.total_shares.into(),
+                start_timestamp: schedule.start_timestamp.into(),
+                cliff_timestamp: schedule.cliff_timestamp.into(),
+                end_timestamp: schedule.end_timestamp.into(),
+                locked_shares: locked_shares.into(),
+                transferable_shares: transferable_shares.into(),
+                locked_balance: self
+                    .staked_amount_from_num_shares_rounded_down(locked_shares)
+                    .into(),
+                transferable_balance: self
+                    .staked_amount_from_num_shares_rounded_down(transferable_shares)
+                    .into(),
+            }
+        })
+    }
+}
+
+impl LiquidStakingContract {
+    /// Amount of LiNEAR of the account that is locked now
+    pub(crate) fn internal_locked_shares(&self, account_id: &AccountId) -> ShareBalance {
+        self.lock_schedules
+            .get(account_id)
+            .map(|schedule| schedule.locked_shares(env::block_timestamp()))
+            .unwrap_or_default()
+    }
+
+    /// Asserts that the LiNEAR balance left in the account covers the locked shares
+    pub(crate) fn assert_not_locked(&self, account_id: &AccountId, new_stake_shares: ShareBalance) {
+        require!(
+            new_stake_shares >= self.internal_locked_shares(account_id),
+            ERR_SHARES_LOCKED
+        );
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_contract_standards::fungible_token::core::FungibleTokenCore;
+    use near_sdk::test_utils::accounts;
+    use near_sdk::testing_env;
+
+    use super::*;
+    use crate::test_utils::*;
+
+    const ONE_DAY: Timestamp = 86_400_000_000_000;
+
+    fn set_context_at(predecessor: &AccountId, deposit: Balance, timestamp: Timestamp) {
+        testing_env!(get_context(predecessor, deposit)
+            .block_timestamp(timestamp)
+            .build());
+    }
+
+    /// The owner stakes 10 NEAR and locks 8 LiNEAR of it to alice,
+    /// with a cliff at day 100 and the linear release from day 0 to day 400.
+    fn new_contract_with_schedule() -> LiquidStakingContract {
+        let owner = accounts(1);
+        let mut contract = new_contract();
+        set_context(&owner, 10 * ONE_NEAR);
+        contract.deposit_and_stake();
+        set_context(&owner, 1);
+        contract.create_lock_schedule(
+            accounts(2),
+            U128(8 * ONE_NEAR),
+            U64(0),
+            U64(100 * ONE_DAY),
+            U64(400 * ONE_DAY),
+        );
+        contract
+    }
+
+    #[test]
+    fn release_after_cliff() {
+        let mut contract = new_contract_with_schedule();
+        let (alice, bob) = (accounts(2), accounts(3));
+        contract.internal_register_account(&bob);
+
+        set_context_at(&alice, 1, 50 * ONE_DAY);
+        let view = contract.get_lock_schedule(alice.clone()).unwrap();
+        assert_eq!(view.locked_shares.0, 8 * ONE_NEAR);
+        assert_eq!(view.transferable_shares.0, 0);
+
+        // a quarter is released after the cliff
+        set_context_at(&alice, 1, 100 * ONE_DAY);
+        let view = contract.get_lock_schedule(alice.clone()).unwrap();
+        assert_eq!(view.locked_shares.0, 6 * ONE_NEAR);
+        contract.ft_transfer(bob.clone(), U128(ONE_NEAR), None);
+        contract.unstake(U128(ONE_NEAR));
+        assert_eq!(contract.ft_balance_of(alice.clone()).0, 6 * ONE_NEAR);
+
+        // locked shares keep earning rewards
+        contract.total_staked_near_amount += contract.total_share_amount;
+        let view = contract.get_lock_schedule(alice).unwrap();
+        assert_eq!(view.locked_balance.0, 12 * ONE_NEAR);
+        assert_eq!(view.transferable_balance.0, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "The LiNEAR balance is locked by the lock schedule")]
+    fn transfer_locked_shares() {
+        let mut contract = new_contract_with_schedule();
+        let bob = accounts(3);
+        contract.internal_register_account(&bob);
+        set_context_at(&accounts(2), 1, 100 * ONE_DAY);
+        contract.ft_transfer(bob, U128(3 * ONE_NEAR), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "The LiNEAR balance is locked by the lock schedule")]
+    fn unstake_locked_shares() {
+        let mut contract = new_contract_with_schedule();
+        set_context_at(&accounts(2), 0, 50 * ONE_DAY);
+        contract.unstake(U128(ONE_NEAR));
+    }
+
+    #[test]
+    fn terminate_returns_locked_shares() {
+        let mut contract = new_contract_with_schedule();
+        let (owner, alice) = (accounts(1), accounts(2));
+        set_context_at(&owner, 1, 200 * ONE_DAY);
+        contract.terminate_lock_schedule(alice.clone());
+        assert!(contract.get_lock_schedule(alice.clone()).is_none());
+        assert_eq!(contract.ft_balance_of(alice).0, 4 * ONE_NEAR);
+        assert_eq!(contract.ft_balance_of(owner).0, 6 * ONE_NEAR);
+    }
+}
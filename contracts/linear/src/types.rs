@@ -21,6 +21,22 @@ pub const ONE_NEAR: Balance = 1_000_000_000_000_000_000_000_000;
 /// when the unstaking promise can arrive at the next epoch, while the inner state is already
 /// updated in the previous epoch. It will not unlock the funds for 4 epochs.
 pub const NUM_EPOCHS_TO_UNLOCK: EpochHeight = 4;
+/// The number of epochs the staged contract code has to wait before it could be applied,
+/// which gives the community time to review the code hash.
+pub const NUM_EPOCHS_TO_ACTIVATE_UPGRADE: EpochHeight = 4;
+/// The number of epochs a change of the withdrawal allowlist has to wait before it
+/// takes effect, which gives the account owner time to notice and cancel it.
+pub const NUM_EPOCHS_TO_CHANGE_ALLOWLIST: EpochHeight = 4;
+/// The max number of receivers in the withdrawal allowlist of one account
+pub const MAX_WITHDRAWAL_ALLOWLIST_LEN: usize = 10;
+/// The max number of LiNEAR balance checkpoints kept for one account
+pub const MAX_BALANCE_CHECKPOINTS: usize = 128;
+/// The max number of total LiNEAR supply checkpoints kept, about one year of epochs
+pub const MAX_TOTAL_SUPPLY_CHECKPOINTS: u64 = 730;
+/// The min amount of NEAR that could be donated
+pub const MIN_DONATION_AMOUNT: Balance = ONE_NEAR;
+/// The max length in bytes of sub-account IDs
+pub const MAX_SUB_ACCOUNT_ID_LEN: usize = 64;
 /// Full basis points, i.e. 10,000
 pub const FULL_BASIS_POINTS: u32 = 10_000;
 
@@ -28,6 +44,11 @@ pub const FULL_BASIS_POINTS: u32 = 10_000;
 /// storage and contract call fees.
 pub const CONTRACT_MIN_RESERVE_BALANCE: Balance = ONE_NEAR;
 
+/// Max allowed difference between the NEAR amount accounted by validators and
+/// `total_staked_near_amount`, which covers the accumulated rounding errors of
+/// staking pools and balance syncs.
+pub const MAX_ACCOUNTED_NEAR_AMOUNT_DIFF: Balance = ONE_NEAR;
+
 /// Zero address is implicit address that doesn't have a key for it.
 /// Used for burning tokens.
 // pub const ZERO_ADDRESS: &str = "0000000000000000000000000000000000000000000000000000000000000000";
@@ -59,12 +80,19 @@ pub const GAS_CB_VALIDATOR_GET_BALANCE: Gas = Gas(25 * TGAS);
 pub const GAS_CB_VALIDATOR_SYNC_BALANCE: Gas = Gas(25 * TGAS);
 pub const GAS_CB_VALIDATOR_WITHDRAW: Gas = Gas(25 * TGAS);
 pub const GAS_CB_WHITELIST: Gas = Gas(15 * TGAS);
+pub const GAS_CB_WITHDRAW_TO: Gas = Gas(10 * TGAS);
+pub const GAS_CB_CLAIM: Gas = Gas(10 * TGAS);
+pub const GAS_EXT_NEAR_WITHDRAW: Gas = Gas(10 * TGAS);
+pub const GAS_CB_WNEAR_WITHDRAW: Gas = Gas(25 * TGAS);
 
 // -- COMMON TYPES
 
 /// Type for stake share (LiNEAR) balance
 pub type ShareBalance = u128;
 
+/// Opaque ID of a sub-account under an omnibus account, e.g. a customer ID or memo
+pub type SubAccountId = String;
+
 construct_uint! {
     /// 256-bit unsigned integer.
     #[derive(BorshSerialize, BorshDeserialize)]
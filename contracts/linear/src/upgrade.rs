@@ -1,18 +1,228 @@
-// use crate::legacy::*;
+use crate::events::Event;
+use crate::legacy::*;
 use crate::*;
+use near_sdk::{json_types::Base58CryptoHash, CryptoHash, Promise};
+
+/// The upgrade that has been staged and waits to be applied
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct StagedUpgrade {
+    /// sha256 hash of the staged contract code
+    pub code_hash: CryptoHash,
+    /// Size of the staged contract code in bytes
+    pub code_size: u64,
+    /// Epoch height when the code was staged
+    pub staged_epoch: EpochHeight,
+    /// The earliest epoch height when the staged code could be applied
+    pub activation_epoch: EpochHeight,
+    /// The deposit attached by the owner for the storage of the staged code,
+    /// which is refunded to the owner when the code is removed
+    pub storage_deposit: Balance,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct StagedUpgradeInfo {
+    pub code_hash: Base58CryptoHash,
+    pub code_size: u64,
+    pub staged_epoch: EpochHeight,
+    pub activation_epoch: EpochHeight,
+    pub storage_deposit: U128,
+}
+
+impl From<&StagedUpgrade> for StagedUpgradeInfo {
+    fn from(staged: &StagedUpgrade) -> Self {
+        Self {
+            code_hash: staged.code_hash.into(),
+            code_size: staged.code_size,
+            staged_epoch: staged.staged_epoch,
+            activation_epoch: staged.activation_epoch,
+            storage_deposit: staged.storage_deposit.into(),
+        }
+    }
+}
+
+/// Raw storage key of the staged contract code
+pub(crate) fn staged_code_key() -> Vec<u8> {
+    StorageKey::StagedCode.try_to_vec().unwrap()
+}
 
 #[near_bindgen]
 impl LiquidStakingContract {
     /// Should only be called by this contract on migration.
-    /// This is NOOP implementation. KEEP IT if you haven't changed contract state.
-    /// If you have changed state, you need to implement migration from old state (keep the old
-    /// struct with different name to deserialize it first).
-    /// After migration goes live, revert back to this implementation for next updates.
+    /// Migrate from v1.6.0 state, which adds `staged_upgrade`, `migration`, `storage_deposits`,
+    /// `total_unstaked_amount`, `allowances`, `wnear_account_id`, `account_stats`, omnibus `sub_accounts`, `stake_limits`,
+    /// `min_amounts`, the liquidity reserve, auto-claim, `lock_schedules`, `withdrawal_allowlists`,
+    /// balance checkpoints and stores accounts as compact `VersionedAccount`.
+    /// The account storage usage is re-measured, which the storage balance bounds are derived from.
+    /// `total_unstaked_amount` is summed up from the legacy accounts, which reads each of them
+    /// once, so enough gas should be attached for the number of accounts.
+    /// If the format of accounts or validators changes, call `internal_start_migration()`
+    /// here and migrate the records in batches via `migrate_batch()`. The records are also
+    /// migrated lazily when saved, so user operations don't wait for the batches.
+    /// After migration goes live, revert back to the NOOP implementation for next updates.
     #[init(ignore_state)]
     #[private]
     pub fn migrate() -> Self {
-        let contract: LiquidStakingContract = env::state_read().expect("ERR_NOT_INITIALIZED");
-        contract
+        let contract: ContractV1_6_0 = env::state_read().expect("ERR_NOT_INITIALIZED");
+        let total_unstaked_amount = contract
+            .accounts
+            .values()
+            .map(|account| account.unstaked)
+            .sum();
+        let mut this = Self {
+            owner_id: contract.owner_id,
+            managers: contract.managers,
+            treasury_id: contract.treasury_id,
+            total_share_amount: contract.total_share_amount,
+            total_staked_near_amount: contract.total_staked_near_amount,
+            accounts: UnorderedMap::new(StorageKey::AccountsV1),
+            legacy_accounts: contract.accounts,
+            storage_deposits: LookupMap::new(StorageKey::StorageDeposits),
+            total_storage_deposit: 0,
+            total_unstaked_amount,
+            allowances: LookupMap::new(StorageKey::Allowances),
+            allowance_spenders: LookupMap::new(StorageKey::AllowanceSpenders),
+            allowances_num: 0,
+            account_stats: LookupMap::new(StorageKey::AccountStats),
+            account_stats_num: 0,
+            omnibus_accounts: LookupMap::new(StorageKey::OmnibusAccounts),
+            omnibus_accounts_num: 0,
+            sub_accounts: LookupMap::new(StorageKey::SubAccounts),
+            sub_accounts_num: 0,
+            auto_claim_accounts: LookupSet::new(StorageKey::AutoClaimAccounts),
+            auto_claim_accounts_num: 0,
+            claim_queue: TreeMap::new(StorageKey::ClaimQueue),
+            lock_schedules: LookupMap::new(StorageKey::LockSchedules),
+            withdrawal_allowlists: LookupMap::new(StorageKey::WithdrawalAllowlists),
+            withdrawal_allowlists_num: 0,
+            balance_checkpoints: LookupMap::new(StorageKey::BalanceCheckpoints),
+            balance_checkpoints_num: 0,
+            total_supply_checkpoints: TreeMap::new(StorageKey::TotalSupplyCheckpoints),
+            paused: contract.paused,
+            account_storage_usage: contract.account_storage_usage,
+            allowance_storage_usage: 0,
+            account_stats_storage_usage: 0,
+            omnibus_account_storage_usage: 0,
+            sub_account_storage_usage: 0,
+            auto_claim_storage_usage: 0,
+            withdrawal_allowlist_storage_usage: 0,
+            balance_checkpoints_storage_usage: 0,
+            beneficiaries: contract.beneficiaries,
+            validator_pool: contract.validator_pool,
+            whitelist_account_id: contract.whitelist_account_id,
+            wnear_account_id: None,
+            epoch_requested_stake_amount: contract.epoch_requested_stake_amount,
+            epoch_requested_unstake_amount: contract.epoch_requested_unstake_amount,
+            epoch_reserve_unstake_amount: 0,
+            stake_limits: StakeLimits::default(),
+            min_amounts: MinAmounts::default(),
+            liquidity_reserve_amount: 0,
+            liquidity_reserve_bps: 0,
+            stake_amount_to_settle: contract.stake_amount_to_settle,
+            unstake_amount_to_settle: contract.unstake_amount_to_settle,
+            last_settlement_epoch: contract.last_settlement_epoch,
+            staged_upgrade: None,
+            migration: None,
+        };
+        // legacy accounts are moved to the compact format when saved,
+        // and the remaining ones in batches
+        this.internal_start_migration();
+        this.measure_account_storage_usage();
+        this.internal_checkpoint_total_supply();
+        this
+    }
+
+    /// Cancel the staged upgrade and remove the staged code, the storage deposit is refunded
+    pub fn cancel_upgrade(&mut self) {
+        self.assert_owner();
+        let staged = self.staged_upgrade.take().expect(ERR_NO_STAGED_UPGRADE);
+        self.internal_remove_staged_code(&staged);
+
+        Event::CancelUpgrade {
+            code_hash: &staged.code_hash.into(),
+        }
+        .emit();
+    }
+
+    /// Should only be called by this contract as the last action of `apply_upgrade()`,
+    /// after the new code is deployed and the state is migrated and validated.
+    /// Removes the staged code, which is kept until now so a failed upgrade could be retried.
+    #[private]
+    pub fn complete_upgrade(&mut self, code_hash: Base58CryptoHash) {
+        if let Some(staged) = self.staged_upgrade.take() {
+            self.internal_remove_staged_code(&staged);
+        }
+
+        Event::ApplyUpgrade {
+            code_hash: &code_hash,
+        }
+        .emit();
+    }
+
+    /// Get the staged upgrade, so the code hash could be reviewed before it's applied
+    pub fn get_staged_upgrade(&self) -> Option<StagedUpgradeInfo> {
+        self.staged_upgrade.as_ref().map(|staged| staged.into())
+    }
+}
+
+impl LiquidStakingContract {
+    /// The attached deposit should cover the storage of the staged code, so it's not paid
+    /// from the pooled NEAR. The exceeding deposit is refunded.
+    pub(crate) fn internal_stage_upgrade(&mut self, code_hash: CryptoHash, code_size: u64) {
+        self.assert_owner();
+        require!(self.staged_upgrade.is_none(), ERR_UPGRADE_ALREADY_STAGED);
+        require!(code_size > 0, ERR_EMPTY_UPGRADE_CODE);
+
+        let storage_deposit = Balance::from(code_size) * env::storage_byte_cost();
+        let attached_deposit = env::attached_deposit();
+        require!(
+            attached_deposit >= storage_deposit,
+            ERR_NO_ENOUGH_UPGRADE_STORAGE_DEPOSIT
+        );
+        let refund = attached_deposit - storage_deposit;
+        if refund > 0 {
+            Promise::new(env::predecessor_account_id()).transfer(refund);
+        }
+
+        let staged_epoch = get_epoch_height();
+        let activation_epoch = staged_epoch + NUM_EPOCHS_TO_ACTIVATE_UPGRADE;
+        self.staged_upgrade = Some(StagedUpgrade {
+            code_hash,
+            code_size,
+            staged_epoch,
+            activation_epoch,
+            storage_deposit,
+        });
+
+        Event::StageUpgrade {
+            code_hash: &code_hash.into(),
+            code_size,
+            activation_epoch,
+        }
+        .emit();
+    }
+
+    /// Removes the staged code and refunds its storage deposit to the owner
+    fn internal_remove_staged_code(&self, staged: &StagedUpgrade) {
+        env::storage_remove(&staged_code_key());
+        if staged.storage_deposit > 0 {
+            Promise::new(self.owner_id.clone()).transfer(staged.storage_deposit);
+        }
+    }
+
+    /// Returns the code hash of the staged upgrade if it's ready to be applied.
+    /// The staged upgrade is kept until `complete_upgrade()`.
+    pub(crate) fn internal_activated_upgrade_code_hash(&self) -> CryptoHash {
+        self.assert_owner();
+        let staged = self.staged_upgrade.as_ref().expect(ERR_NO_STAGED_UPGRADE);
+        require!(
+            get_epoch_height() >= staged.activation_epoch,
+            format!(
+                "{}. activation epoch: {}",
+                ERR_UPGRADE_NOT_ACTIVATED, staged.activation_epoch
+            )
+        );
+        staged.code_hash
     }
 }
 
@@ -25,34 +235,97 @@ mod upgrade {
 
     /// Gas for completing the upgrade call
     pub const GAS_FOR_COMPLETING_UPGRADE_CALL: Gas = Gas(10 * TGAS);
-    /// Minimum gas for calling state migration call. Please notice the gas cost will be higher
-    /// if the number of accounts and validator pools grows.
+    /// Minimum gas for calling state migration call. `migrate()` should only convert the
+    /// root state, the accounts and validators should be migrated via `migrate_batch()`.
     pub const MIN_GAS_FOR_MIGRATE_CALL: Gas = Gas(10 * TGAS);
-    /// Gas for calling `get_summary` method
-    pub const GAS_FOR_GET_SUMMARY_CALL: Gas = Gas(15 * TGAS);
+    /// Gas for calling `check_invariants` method. Please notice the gas cost will be higher
+    /// if the number of validator pools grows.
+    pub const GAS_FOR_CHECK_INVARIANTS_CALL: Gas = Gas(40 * TGAS);
+    /// Gas for calling `complete_upgrade` method
+    pub const GAS_FOR_COMPLETE_UPGRADE_CALL: Gas = Gas(10 * TGAS);
 
-    /// Self upgrade and call migrate, optimizes gas by not loading into memory the code.
+    /// Register used to hold the contract code
+    const CODE_REGISTER: u64 = 0;
+    /// Register used to hold the code hash
+    const HASH_REGISTER: u64 = 1;
+    /// Register used to hold the evicted storage value, which is not used
+    const EVICTED_REGISTER: u64 = 2;
+
+    /// Read the sha256 hash of the code in register 0
+    unsafe fn code_hash() -> CryptoHash {
+        sys::sha256(u64::MAX, CODE_REGISTER, HASH_REGISTER);
+        let mut hash = CryptoHash::default();
+        sys::read_register(HASH_REGISTER, hash.as_mut_ptr() as _);
+        hash
+    }
+
+    /// Stage the contract code for upgrade, optimizes gas by not loading into memory the code.
     /// Takes as input non serialized set of bytes of the code.
+    /// The staged code could only be applied after `NUM_EPOCHS_TO_ACTIVATE_UPGRADE` epochs
+    /// via `apply_upgrade()`. The attached deposit should cover the storage of the code.
+    #[no_mangle]
+    pub fn stage_upgrade() {
+        env::setup_panic_hook();
+        let mut contract: LiquidStakingContract =
+            env::state_read().expect("ERR_CONTRACT_IS_NOT_INITIALIZED");
+        let key = staged_code_key();
+        unsafe {
+            // Load input (wasm code) into register 0.
+            sys::input(CODE_REGISTER);
+            let code_size = sys::register_len(CODE_REGISTER);
+            contract.internal_stage_upgrade(code_hash(), code_size);
+            // Store the code from register 0, which will be removed when applied or cancelled
+            sys::storage_write(
+                key.len() as _,
+                key.as_ptr() as _,
+                u64::MAX,
+                CODE_REGISTER,
+                EVICTED_REGISTER,
+            );
+        }
+        env::state_write(&contract);
+    }
+
+    /// Self upgrade with the staged code and call migrate, optimizes gas by not loading
+    /// into memory the code. The staged code must have passed the activation epoch.
+    /// The staged code is kept if the upgrade fails, so it could be retried.
     #[no_mangle]
-    pub fn upgrade() {
+    pub fn apply_upgrade() {
         env::setup_panic_hook();
         let contract: LiquidStakingContract =
             env::state_read().expect("ERR_CONTRACT_IS_NOT_INITIALIZED");
-        contract.assert_owner();
+        let staged_code_hash = contract.internal_activated_upgrade_code_hash();
+
         let current_id = env::current_account_id().as_bytes().to_vec();
+        let key = staged_code_key();
         let migrate_method_name = b"migrate".to_vec();
-        let get_summary_method_name = b"get_summary".to_vec();
+        let check_invariants_method_name = b"check_invariants".to_vec();
+        let check_invariants_args = br#"{"assert_identities_valid":true}"#.to_vec();
+        let complete_upgrade_method_name = b"complete_upgrade".to_vec();
+        let complete_upgrade_args =
+            near_sdk::serde_json::json!({ "code_hash": Base58CryptoHash::from(staged_code_hash) })
+                .to_string()
+                .into_bytes();
         unsafe {
-            // Load input (wasm code) into register 0.
-            sys::input(0);
+            // Load staged code into register 0
+            require!(
+                sys::storage_read(key.len() as _, key.as_ptr() as _, CODE_REGISTER) == 1,
+                ERR_NO_STAGED_UPGRADE
+            );
+            require!(
+                code_hash() == staged_code_hash,
+                ERR_UPGRADE_CODE_HASH_MISMATCH
+            );
             // Create batch action promise for the current contract ID
             let promise_id =
                 sys::promise_batch_create(current_id.len() as _, current_id.as_ptr() as _);
             // 1st batch action in the Tx: "deploy contract" (code is taken from register 0)
-            sys::promise_batch_action_deploy_contract(promise_id, u64::MAX as _, 0);
+            sys::promise_batch_action_deploy_contract(promise_id, u64::MAX as _, CODE_REGISTER);
             // 2nd batch action in the Tx: call `migrate()` in the contract with sufficient gas
-            let required_gas =
-                env::used_gas() + GAS_FOR_COMPLETING_UPGRADE_CALL + GAS_FOR_GET_SUMMARY_CALL;
+            let required_gas = env::used_gas()
+                + GAS_FOR_COMPLETING_UPGRADE_CALL
+                + GAS_FOR_CHECK_INVARIANTS_CALL
+                + GAS_FOR_COMPLETE_UPGRADE_CALL;
             require!(
                 env::prepaid_gas() >= required_gas + MIN_GAS_FOR_MIGRATE_CALL,
                 "Not enough gas to complete contract state migration"
@@ -67,20 +340,109 @@ mod upgrade {
                 0 as _,
                 migrate_attached_gas.0,
             );
-            // 3rd batch action in the Tx: call `get_summary()` in the contract to validate
-            // the contract state. If the validation failed, the entire `upgrade()` method
-            // will be rolled back. The `get_summary()` view call will access most of the
-            // states in the contract, so should guarantee the contract is working as expected
+            // 3rd batch action in the Tx: call `check_invariants()` in the contract to validate
+            // the contract state. If the validation failed, the entire batch will be rolled
+            // back. Only the structural identities are asserted, so the upgrade isn't blocked
+            // by validator actions in flight, and could fix a drift of the balances.
             sys::promise_batch_action_function_call(
                 promise_id,
-                get_summary_method_name.len() as _,
-                get_summary_method_name.as_ptr() as _,
-                0 as _,
+                check_invariants_method_name.len() as _,
+                check_invariants_method_name.as_ptr() as _,
+                check_invariants_args.len() as _,
+                check_invariants_args.as_ptr() as _,
                 0 as _,
+                GAS_FOR_CHECK_INVARIANTS_CALL.0,
+            );
+            // 4th batch action in the Tx: call `complete_upgrade()` in the contract to remove
+            // the staged code. If any action above failed, the staged code is still there
+            // and the upgrade could be applied again.
+            sys::promise_batch_action_function_call(
+                promise_id,
+                complete_upgrade_method_name.len() as _,
+                complete_upgrade_method_name.as_ptr() as _,
+                complete_upgrade_args.len() as _,
+                complete_upgrade_args.as_ptr() as _,
                 0 as _,
-                GAS_FOR_GET_SUMMARY_CALL.0,
+                GAS_FOR_COMPLETE_UPGRADE_CALL.0,
             );
             sys::promise_return(promise_id);
         }
     }
 }
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::accounts;
+
+    use super::*;
+    use crate::test_utils::*;
+
+    #[test]
+    fn stage_and_apply_upgrade_after_activation() {
+        let mut contract = new_contract();
+        let code_hash = [1u8; 32];
+        set_context(&accounts(1), 100 * env::storage_byte_cost());
+        contract.internal_stage_upgrade(code_hash, 100);
+
+        let staged = contract.get_staged_upgrade().unwrap();
+        assert_eq!(staged.code_size, 100);
+        assert_eq!(staged.storage_deposit.0, 100 * env::storage_byte_cost());
+        assert_eq!(staged.staged_epoch, get_epoch_height());
+        assert_eq!(
+            staged.activation_epoch,
+            get_epoch_height() + NUM_EPOCHS_TO_ACTIVATE_UPGRADE
+        );
+
+        // fast-forward to activation epoch
+        contract.staged_upgrade.as_mut().unwrap().activation_epoch = get_epoch_height();
+        env::storage_write(&staged_code_key(), &[0u8; 100]);
+        assert_eq!(contract.internal_activated_upgrade_code_hash(), code_hash);
+        // the staged code is kept until the upgrade completes
+        assert!(contract.get_staged_upgrade().is_some());
+
+        set_context(&accounts(0), 0);
+        contract.complete_upgrade(code_hash.into());
+        assert!(contract.get_staged_upgrade().is_none());
+        assert!(!env::storage_has_key(&staged_code_key()));
+    }
+
+    #[test]
+    #[should_panic(expected = "The staged upgrade is not activated yet")]
+    fn apply_upgrade_before_activation() {
+        let mut contract = new_contract();
+        set_context(&accounts(1), 100 * env::storage_byte_cost());
+        contract.internal_stage_upgrade([1u8; 32], 100);
+        contract.internal_activated_upgrade_code_hash();
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "The attached deposit is less than the storage cost of the staged code"
+    )]
+    fn stage_upgrade_without_storage_deposit() {
+        let mut contract = new_contract();
+        contract.internal_stage_upgrade([1u8; 32], 100);
+    }
+
+    #[test]
+    #[should_panic(expected = "There is already a staged upgrade")]
+    fn stage_upgrade_twice() {
+        let mut contract = new_contract();
+        set_context(&accounts(1), 100 * env::storage_byte_cost());
+        contract.internal_stage_upgrade([1u8; 32], 100);
+        contract.internal_stage_upgrade([2u8; 32], 100);
+    }
+
+    #[test]
+    #[should_panic(expected = "There is no staged upgrade")]
+    fn apply_cancelled_upgrade() {
+        let mut contract = new_contract();
+        set_context(&accounts(1), 100 * env::storage_byte_cost());
+        contract.internal_stage_upgrade([1u8; 32], 100);
+        env::storage_write(&staged_code_key(), &[0u8; 100]);
+        contract.cancel_upgrade();
+        assert!(!env::storage_has_key(&staged_code_key()));
+        assert!(contract.get_staged_upgrade().is_none());
+        contract.internal_activated_upgrade_code_hash();
+    }
+}
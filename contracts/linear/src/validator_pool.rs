@@ -527,6 +527,9 @@ impl LiquidStakingContract {
     pub fn remove_validator(&mut self, validator_id: AccountId) -> Validator {
         self.assert_running();
         self.assert_manager();
+        // removing a validator moves the last one to its index, which could make
+        // `migrate_batch()` skip it
+        self.assert_not_migrating();
         self.validator_pool.remove_validator(&validator_id)
     }
 
@@ -1,5 +1,8 @@
 use crate::*;
-use near_sdk::{json_types::U128, near_bindgen, AccountId};
+use near_sdk::{
+    json_types::{I128, U128},
+    near_bindgen, AccountId,
+};
 use std::collections::HashMap;
 
 /// The human readable summary of the liquid staking contract
@@ -27,6 +30,162 @@ pub struct Summary {
     pub epoch_requested_stake_amount: U128,
     /// Amount of NEAR that is requested to unstake by all users during the last epoch
     pub epoch_requested_unstake_amount: U128,
+    /// Amount of NEAR kept in the contract balance to serve unstakes immediately
+    pub liquidity_reserve_amount: U128,
+    /// Amount of NEAR the liquidity reserve is filled up to in epoch cleanup
+    pub liquidity_reserve_target: U128,
+    /// The target ratio of the liquidity reserve to the total staked amount in basis points
+    pub liquidity_reserve_bps: u32,
+}
+
+/// The storage fee of registered accounts that is not covered by their storage deposits
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct StorageDebt {
+    /// The measured storage size in bytes for one account
+    pub account_storage_usage: u64,
+    /// The storage balance that one account should deposit
+    pub storage_balance_per_account: U128,
+    /// Number of registered accounts
+    pub accounts_num: u64,
+    /// Total number of allowances
+    pub allowances_num: u64,
+    /// Number of accounts that enabled lifetime counters
+    pub account_stats_num: u64,
+    /// Number of accounts that enabled auto-claim
+    pub auto_claim_accounts_num: u64,
+    /// Number of accounts that paid the storage of a withdrawal allowlist
+    pub withdrawal_allowlists_num: u64,
+    /// Number of accounts that enabled balance checkpoints
+    pub balance_checkpoints_num: u64,
+    /// Number of omnibus accounts
+    pub omnibus_accounts_num: u64,
+    /// Total number of sub-accounts of all omnibus accounts
+    pub sub_accounts_num: u64,
+    /// Total storage balance that all registered accounts should deposit,
+    /// including the storage of allowances, lifetime counters, auto-claim, allowlists,
+    /// balance checkpoints and sub-accounts
+    pub total_storage_required: U128,
+    /// Total storage balance that was actually deposited by accounts
+    pub total_storage_deposit: U128,
+    /// Storage balance that is not covered by deposits and is paid by the contract.
+    /// Deposits exceeding the bounds are not withdrawn yet, so they are counted against it.
+    pub storage_debt: U128,
+}
+
+/// The report of checking the core accounting invariants of the contract
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct InvariantsReport {
+    /// Whether all the invariants hold. It's false while validators are executing
+    /// actions, since the validator balances cannot be verified then.
+    pub valid: bool,
+    /// Whether the structural identities hold, which are the base stake amount, the weight
+    /// and the LiNEAR price. They don't depend on validator balances, so they're asserted
+    /// after upgrade even if validator actions are in flight.
+    pub identities_valid: bool,
+    /// Number of validators that are executing actions
+    pub executing_validators_num: u64,
+
+    /// Total amount of NEAR that was staked by users to this contract.
+    pub total_staked_near_amount: U128,
+    /// Amount of NEAR accounted by validators, the liquidity reserve and to-settle amounts,
+    /// which is the staked amount on validators (plus unstaked amount of draining validators)
+    /// plus the liquidity reserve plus the amount to stake minus the amount to unstake.
+    /// It's negative if the amount to unstake exceeds the others.
+    pub accounted_near_amount: I128,
+    /// Whether `accounted_near_amount` is consistent with `total_staked_near_amount`
+    pub staked_near_amount_consistent: bool,
+
+    /// Total unstaked balance owed to accounts
+    pub unstaked_liabilities_amount: U128,
+    /// Amount of NEAR available for the unstaked liabilities, which is the contract balance
+    /// not locked for storage plus the unstaked amount of validators (except draining ones)
+    /// plus the amount to unstake minus the amount to stake minus the liquidity reserve.
+    /// It's negative if the amount to stake and the reserve exceed the others.
+    pub unstaked_assets_amount: I128,
+    /// Whether `unstaked_assets_amount` covers `unstaked_liabilities_amount`
+    pub unstaked_liabilities_covered: bool,
+
+    /// Total base stake amount of NEAR recorded in validator pool
+    pub total_base_stake_amount: U128,
+    /// Sum of base stake amount of all validators
+    pub validators_total_base_stake_amount: U128,
+    /// Whether `total_base_stake_amount` matches the sum of all validators
+    pub base_stake_amount_consistent: bool,
+
+    /// Total weight recorded in validator pool
+    pub total_weight: u16,
+    /// Sum of weights of all validators
+    pub validators_total_weight: u64,
+    /// Whether `total_weight` matches the sum of all validators
+    pub weight_consistent: bool,
+
+    /// LiNEAR price
+    pub ft_price: U128,
+    /// Whether LiNEAR price is at least 1 NEAR
+    pub ft_price_valid: bool,
+}
+
+/// The preview of staking some amount of NEAR
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct StakePreview {
+    /// Amount of LiNEAR that will be minted, rounded down
+    pub minted_stake_shares: U128,
+    /// Amount of NEAR that will be charged from the unstaked balance.
+    /// It's the value of the minted LiNEAR rounded down, so it never exceeds the staked amount.
+    pub charged_amount: U128,
+}
+
+/// The preview of unstaking some amount of NEAR or LiNEAR
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct UnstakePreview {
+    /// Amount of LiNEAR that will be burnt, rounded up
+    pub burnt_stake_shares: U128,
+    /// Amount of NEAR that will be added to the unstaked balance, rounded up
+    pub received_amount: U128,
+    /// The epoch height when the unstaked NEAR could be withdrawn. With an account,
+    /// it's no earlier than the unlock epoch of the pending unstaked balance of the account.
+    pub unstaked_available_epoch_height: EpochHeight,
+    /// Whether the unstake is served by the liquidity reserve, which could be withdrawn
+    /// immediately unless earlier unstaked NEAR of the account is still locked.
+    /// Such unstakes are counted toward the per-epoch unstake cap the same as others.
+    pub from_liquidity_reserve: bool,
+}
+
+/// The preview of withdrawing the unstaked balance of an account
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct WithdrawPreview {
+    /// The unstaked balance of the account
+    pub unstaked_balance: U128,
+    /// The epoch height when the unstaked NEAR could be withdrawn
+    pub unstaked_available_epoch_height: EpochHeight,
+    /// Amount of NEAR that could be withdrawn now, which is limited by the
+    /// available balance of the contract
+    pub withdrawable_amount: U128,
+}
+
+/// The position of `get_accounts_page()` in one of the account maps
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+#[serde(rename_all = "snake_case")]
+pub enum AccountsCursor {
+    /// Index of the next account in `legacy_accounts`, which are listed from the end
+    LegacyAccounts(u64),
+    /// Index of the next account in `accounts`
+    Accounts(u64),
+}
+
+/// A page of accounts listed by `get_accounts_page()`
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AccountsPage {
+    pub accounts: Vec<HumanReadableAccount>,
+    /// The cursor of the next page, or None if all accounts have been listed
+    pub next_cursor: Option<AccountsCursor>,
 }
 
 /// public view functions
@@ -55,7 +214,29 @@ impl LiquidStakingContract {
             validators_total_base_stake_amount: self.validator_pool.total_base_stake_amount.into(),
             epoch_requested_stake_amount: self.epoch_requested_stake_amount.into(),
             epoch_requested_unstake_amount: self.epoch_requested_unstake_amount.into(),
+            liquidity_reserve_amount: self.liquidity_reserve_amount.into(),
+            liquidity_reserve_target: self.internal_liquidity_reserve_target().into(),
+            liquidity_reserve_bps: self.liquidity_reserve_bps,
+        }
+    }
+
+    /// Check the core accounting invariants of the contract.
+    /// If `assert_valid` is true, the call will panic when any invariant is violated.
+    /// If `assert_identities_valid` is true, the call will panic when any structural
+    /// identity is violated, which is used to validate the contract state after upgrade.
+    pub fn check_invariants(
+        &self,
+        assert_valid: Option<bool>,
+        assert_identities_valid: Option<bool>,
+    ) -> InvariantsReport {
+        let report = self.internal_check_invariants();
+        if assert_valid.unwrap_or(false) {
+            require!(report.valid, ERR_INVARIANTS_VIOLATED);
+        }
+        if assert_identities_valid.unwrap_or(false) {
+            require!(report.identities_valid, ERR_INVARIANTS_VIOLATED);
         }
+        report
     }
 
     /// Return account details for staking
@@ -140,24 +321,208 @@ impl LiquidStakingContract {
         }
     }
 
+    /// Returns the storage fee across the contract that is not covered by storage deposits
+    pub fn get_storage_debt(&self) -> StorageDebt {
+        let storage_balance_per_account = self.internal_min_storage_balance();
+        let accounts_num = self.get_number_of_accounts();
+        let total_storage_required = storage_balance_per_account * accounts_num as Balance
+            + self.internal_allowance_storage_cost() * self.allowances_num as Balance
+            + self.internal_account_stats_storage_cost() * self.account_stats_num as Balance
+            + self.internal_auto_claim_storage_cost() * self.auto_claim_accounts_num as Balance
+            + self.internal_withdrawal_allowlist_storage_cost()
+                * self.withdrawal_allowlists_num as Balance
+            + self.internal_balance_checkpoints_storage_cost()
+                * self.balance_checkpoints_num as Balance
+            + self.internal_omnibus_account_storage_cost() * self.omnibus_accounts_num as Balance
+            + self.internal_sub_account_storage_cost() * self.sub_accounts_num as Balance;
+        StorageDebt {
+            account_storage_usage: self.account_storage_usage,
+            storage_balance_per_account: storage_balance_per_account.into(),
+            accounts_num,
+            allowances_num: self.allowances_num,
+            account_stats_num: self.account_stats_num,
+            auto_claim_accounts_num: self.auto_claim_accounts_num,
+            withdrawal_allowlists_num: self.withdrawal_allowlists_num,
+            balance_checkpoints_num: self.balance_checkpoints_num,
+            omnibus_accounts_num: self.omnibus_accounts_num,
+            sub_accounts_num: self.sub_accounts_num,
+            total_storage_required: total_storage_required.into(),
+            total_storage_deposit: self.total_storage_deposit.into(),
+            storage_debt: total_storage_required
+                .saturating_sub(self.total_storage_deposit)
+                .into(),
+        }
+    }
+
+    /// Returns the storage balance that the account should top up via `storage_deposit`
+    pub fn get_account_storage_debt(&self, account_id: AccountId) -> U128 {
+        if self.internal_find_account(&account_id).is_some() {
+            self.internal_get_storage_debt(&account_id).into()
+        } else {
+            0.into()
+        }
+    }
+
     /// Returns the number of accounts that have positive balance on this staking pool.
     pub fn get_number_of_accounts(&self) -> u64 {
-        self.accounts.len()
+        self.accounts.len() + self.legacy_accounts.len()
     }
 
-    /// Returns the list of accounts
+    /// Returns the list of accounts. Accounts that are not migrated yet
+    /// are listed after the migrated ones.
+    /// The indexes shift when accounts are migrated, so use `get_accounts_page()`
+    /// to list all accounts while a migration is in progress.
     pub fn get_accounts(&self, from_index: u64, limit: u64) -> Vec<HumanReadableAccount> {
         let keys = self.accounts.keys_as_vector();
+        let legacy_keys = self.legacy_accounts.keys_as_vector();
 
-        (from_index..std::cmp::min(from_index + limit, keys.len()))
-            .map(|index| self.get_account(keys.get(index).unwrap()))
+        (from_index..std::cmp::min(from_index + limit, keys.len() + legacy_keys.len()))
+            .map(|index| {
+                let account_id = if index < keys.len() {
+                    keys.get(index)
+                } else {
+                    legacy_keys.get(index - keys.len())
+                };
+                self.get_account(account_id.unwrap())
+            })
             .collect()
     }
 
+    /// Returns a page of at most `limit` accounts from the cursor, starting from the
+    /// first page if no cursor is given. Each map is paginated separately, so no account
+    /// is skipped while accounts are migrated, though a migrated one may be listed twice:
+    /// - Legacy accounts are listed first, from the end of `legacy_accounts`. Migrating one
+    ///   moves the last one, which has been listed, into its index.
+    /// - Accounts are listed next, and the migrated ones are appended to `accounts`.
+    pub fn get_accounts_page(&self, cursor: Option<AccountsCursor>, limit: u64) -> AccountsPage {
+        let keys = self.accounts.keys_as_vector();
+        let legacy_keys = self.legacy_accounts.keys_as_vector();
+
+        let mut cursor = cursor.unwrap_or(AccountsCursor::LegacyAccounts(u64::MAX));
+        let mut accounts = vec![];
+        while (accounts.len() as u64) < limit {
+            match cursor {
+                AccountsCursor::LegacyAccounts(_) if legacy_keys.is_empty() => {
+                    cursor = AccountsCursor::Accounts(0);
+                }
+                AccountsCursor::LegacyAccounts(index) => {
+                    // the legacy accounts after the cursor may have been migrated
+                    let index = std::cmp::min(index, legacy_keys.len() - 1);
+                    accounts.push(self.get_account(legacy_keys.get(index).unwrap()));
+                    cursor = match index {
+                        0 => AccountsCursor::Accounts(0),
+                        _ => AccountsCursor::LegacyAccounts(index - 1),
+                    };
+                }
+                AccountsCursor::Accounts(index) if index < keys.len() => {
+                    accounts.push(self.get_account(keys.get(index).unwrap()));
+                    cursor = AccountsCursor::Accounts(index + 1);
+                }
+                AccountsCursor::Accounts(_) => break,
+            }
+        }
+
+        let next_cursor = match cursor {
+            AccountsCursor::Accounts(index) if index >= keys.len() => None,
+            cursor => Some(cursor),
+        };
+        AccountsPage {
+            accounts,
+            next_cursor,
+        }
+    }
+
     // --- custom staking pool view methods ---
 
+    /// Preview the result of staking `amount` of NEAR at the current LiNEAR price.
+    /// The calculation is shared with `stake()` and panics in the same way.
+    pub fn preview_stake(&self, amount: U128) -> StakePreview {
+        let quote = self.internal_quote_stake(amount.0);
+        StakePreview {
+            minted_stake_shares: quote.num_shares.into(),
+            charged_amount: quote.charge_amount.into(),
+        }
+    }
+
+    /// Preview the result of unstaking `amount` of NEAR at the current LiNEAR price.
+    /// The calculation is shared with `unstake()` and panics in the same way.
+    /// Pass `account_id` to get the unlock epoch the account would actually have.
+    pub fn preview_unstake(&self, amount: U128, account_id: Option<AccountId>) -> UnstakePreview {
+        let quote = self.internal_quote_unstake(amount.0);
+        let mut unstaked_available_epoch_height = quote.unstaked_available_epoch_height;
+        if let Some(account_id) = account_id {
+            // same as `internal_unstake()`, the pending unlock is not released earlier
+            unstaked_available_epoch_height = std::cmp::max(
+                self.internal_get_account(&account_id)
+                    .unstaked_available_epoch_height,
+                unstaked_available_epoch_height,
+            );
+        }
+        UnstakePreview {
+            burnt_stake_shares: quote.num_shares.into(),
+            received_amount: quote.receive_amount.into(),
+            unstaked_available_epoch_height,
+            from_liquidity_reserve: quote.from_liquidity_reserve,
+        }
+    }
+
+    /// Preview the result of unstaking `shares` of LiNEAR, which is what
+    /// `unstake_all()` does with all the shares of an account.
+    pub fn preview_unstake_shares(
+        &self,
+        shares: U128,
+        account_id: Option<AccountId>,
+    ) -> UnstakePreview {
+        self.preview_unstake(
+            self.staked_amount_from_num_shares_rounded_down(shares.0)
+                .into(),
+            account_id,
+        )
+    }
+
+    /// Preview the amount of NEAR the account could withdraw now
+    pub fn preview_withdraw(&self, account_id: AccountId) -> WithdrawPreview {
+        let account = self.internal_get_account(&account_id);
+        let withdrawable_amount = if account.unstaked_available_epoch_height <= get_epoch_height() {
+            std::cmp::min(
+                account.unstaked,
+                self.internal_contract_withdrawable_balance(),
+            )
+        } else {
+            0
+        };
+        WithdrawPreview {
+            unstaked_balance: account.unstaked.into(),
+            unstaked_available_epoch_height: account.unstaked_available_epoch_height,
+            withdrawable_amount: withdrawable_amount.into(),
+        }
+    }
+
+    /// Returns the stake limits and the remaining amount that could be staked or unstaked
+    pub fn get_stake_limits(&self) -> StakeLimitsView {
+        StakeLimitsView {
+            max_total_staked_near_amount: self.stake_limits.max_total_staked_near_amount.map(U128),
+            max_epoch_stake_amount: self.stake_limits.max_epoch_stake_amount.map(U128),
+            max_epoch_unstake_amount: self.stake_limits.max_epoch_unstake_amount.map(U128),
+            remaining_total_stake_amount: self.internal_remaining_total_stake_amount().map(U128),
+            remaining_epoch_stake_amount: self.internal_remaining_epoch_stake_amount().map(U128),
+            remaining_epoch_unstake_amount: self
+                .internal_remaining_epoch_unstake_amount()
+                .map(U128),
+        }
+    }
+
+    /// Returns the minimum amounts of NEAR to deposit, stake and unstake
+    pub fn get_min_amounts(&self) -> MinAmountsView {
+        MinAmountsView {
+            min_deposit_amount: self.min_amounts.min_deposit_amount.into(),
+            min_stake_amount: self.min_amounts.min_stake_amount.into(),
+            min_unstake_amount: self.min_amounts.min_unstake_amount.into(),
+        }
+    }
+
     /// confirm if the user can perform withdraw now
     pub fn can_account_withdraw(&self, account_id: AccountId, amount: U128) {
-        self.assert_can_withdraw(&account_id, amount.0);
+        self.assert_can_withdraw(&account_id, None, amount.0);
     }
 }
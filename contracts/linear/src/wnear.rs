@@ -0,0 +1,99 @@
+use crate::events::Event;
+use crate::*;
+use near_sdk::{is_promise_success, Promise, PromiseOrValue};
+
+#[ext_contract(ext_wnear)]
+trait ExtWnear {
+    fn near_withdraw(&mut self, amount: U128);
+}
+
+#[ext_contract(ext_self_wnear_cb)]
+trait WnearCallbacks {
+    fn wnear_withdraw_callback(&mut self, account_id: AccountId, amount: U128) -> U128;
+}
+
+impl LiquidStakingContract {
+    /// Unwrap the wNEAR received from `account_id` and stake the NEAR for it in callback.
+    /// Returns the unused wNEAR amount, which is refunded by the wNEAR contract.
+    pub(crate) fn internal_deposit_wnear(
+        &mut self,
+        account_id: AccountId,
+        amount: Balance,
+    ) -> PromiseOrValue<U128> {
+        self.assert_running();
+        self.assert_not_omnibus(&account_id);
+        require!(amount > 0, ERR_NON_POSITIVE_DEPOSIT_AMOUNT);
+        require!(
+            self.num_shares_from_staked_amount_rounded_down(amount) > 0,
+            ERR_NON_POSITIVE_CALCULATED_STAKING_SHARE
+        );
+
+        ext_wnear::near_withdraw(
+            amount.into(),
+            self.wnear_account_id.clone().unwrap(),
+            1,
+            GAS_EXT_NEAR_WITHDRAW,
+        )
+        .then(ext_self_wnear_cb::wnear_withdraw_callback(
+            account_id,
+            amount.into(),
+            env::current_account_id(),
+            NO_DEPOSIT,
+            GAS_CB_WNEAR_WITHDRAW,
+        ))
+        .into()
+    }
+}
+
+#[near_bindgen]
+impl LiquidStakingContract {
+    /// Stakes the unwrapped NEAR for the account. The NEAR is kept as unstaked balance
+    /// if it cannot be staked any more, e.g. the contract is paused in between.
+    /// Returns the unused wNEAR amount, which is all the amount if unwrapping failed.
+    #[private]
+    pub fn wnear_withdraw_callback(&mut self, account_id: AccountId, amount: U128) -> U128 {
+        if !is_promise_success() {
+            Event::DepositWnearFailed {
+                account_id: &account_id,
+                amount: &amount,
+            }
+            .emit();
+            return amount;
+        }
+
+        // omnibus accounts can only deposit via sub-accounts, which could be enabled
+        // while unwrapping, so the received NEAR is sent back
+        if self.internal_is_omnibus(&account_id) {
+            Promise::new(account_id).transfer(amount.0);
+            return U128(0);
+        }
+
+        // the NEAR is already received, so the deposit must not fail
+        let mut account = self.internal_get_account(&account_id);
+        account.unstaked += amount.0;
+        self.internal_save_account(&account_id, &account);
+        self.internal_record_deposit(&account_id, amount.0);
+
+        let can_stake = !self.paused
+            && amount.0 >= self.min_amounts.min_stake_amount
+            && self.num_shares_from_staked_amount_rounded_down(amount.0) > 0
+            && self.internal_within_stake_limits(self.internal_quote_stake(amount.0).stake_amount);
+        let minted_stake_shares = if can_stake {
+            self.internal_stake(&account_id, None, amount.0)
+        } else {
+            0
+        };
+
+        Event::DepositWnear {
+            account_id: &account_id,
+            amount: &amount,
+            minted_stake_shares: &U128(minted_stake_shares),
+        }
+        .emit();
+        U128(0)
+    }
+
+    pub fn get_wnear_account_id(&self) -> Option<AccountId> {
+        self.wnear_account_id.clone()
+    }
+}
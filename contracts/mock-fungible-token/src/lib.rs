@@ -4,7 +4,9 @@ use near_contract_standards::fungible_token::metadata::{
 use near_contract_standards::fungible_token::FungibleToken;
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
 use near_sdk::json_types::U128;
-use near_sdk::{near_bindgen, AccountId, PanicOnDefault, PromiseOrValue};
+use near_sdk::{
+    assert_one_yocto, env, near_bindgen, AccountId, PanicOnDefault, Promise, PromiseOrValue,
+};
 
 #[near_bindgen]
 #[derive(BorshSerialize, BorshDeserialize, PanicOnDefault)]
@@ -29,6 +31,28 @@ impl Contract {
     pub fn burn(&mut self, account_id: AccountId, amount: U128) {
         self.token.internal_withdraw(&account_id, amount.into());
     }
+
+    // -- wNEAR interface, so that this token could be used as a local wNEAR
+
+    /// Wraps the attached NEAR
+    #[payable]
+    pub fn near_deposit(&mut self) {
+        let account_id = env::predecessor_account_id();
+        if !self.token.accounts.contains_key(&account_id) {
+            self.token.internal_register_account(&account_id);
+        }
+        self.token
+            .internal_deposit(&account_id, env::attached_deposit());
+    }
+
+    /// Unwraps the amount and sends the NEAR to the predecessor
+    #[payable]
+    pub fn near_withdraw(&mut self, amount: U128) -> Promise {
+        assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+        self.token.internal_withdraw(&account_id, amount.into());
+        Promise::new(account_id).transfer(amount.into())
+    }
 }
 
 near_contract_standards::impl_fungible_token_core!(Contract, token);
@@ -73,4 +97,21 @@ mod tests {
         contract.burn(accounts(1), 500.into());
         assert_eq!(contract.ft_balance_of(accounts(1)), 500.into());
     }
+
+    #[test]
+    fn test_near_deposit_and_withdraw() {
+        let mut context = VMContextBuilder::new();
+        testing_env!(context.build());
+        let mut contract = Contract::new();
+        testing_env!(context
+            .predecessor_account_id(accounts(0))
+            .attached_deposit(1_000_000)
+            .build());
+        contract.near_deposit();
+        assert_eq!(contract.ft_balance_of(accounts(0)), 1_000_000.into());
+
+        testing_env!(context.attached_deposit(1).build());
+        contract.near_withdraw(400_000.into());
+        assert_eq!(contract.ft_balance_of(accounts(0)), 600_000.into());
+    }
 }
@@ -0,0 +1,106 @@
+import { NEAR } from 'near-workspaces';
+import {
+  initWorkspace,
+  assertFailure,
+  registerFungibleTokenUser,
+  ONE_YOCTO,
+  test,
+} from './helper';
+
+test.beforeEach(async (t) => {
+  t.context = await initWorkspace();
+});
+
+test.afterEach(async (t) => {
+  await t.context.worker.tearDown();
+});
+
+test('track lifetime account stats', async (t) => {
+  const { contract, alice, bob } = t.context;
+  await registerFungibleTokenUser(contract, bob);
+
+  // only registered accounts could enable stats
+  await assertFailure(
+    t,
+    alice.call(
+      contract,
+      'enable_account_stats',
+      {},
+      { attachedDeposit: NEAR.parse('0.01') },
+    ),
+    'The account is not registered',
+  );
+
+  await alice.call(
+    contract,
+    'deposit_and_stake',
+    {},
+    { attachedDeposit: NEAR.parse('10') },
+  );
+
+  // the storage of stats is paid via storage deposit
+  await assertFailure(
+    t,
+    alice.call(
+      contract,
+      'enable_account_stats',
+      {},
+      { attachedDeposit: ONE_YOCTO },
+    ),
+    'The attached deposit is less than the storage cost of the account stats',
+  );
+  await alice.call(
+    contract,
+    'enable_account_stats',
+    {},
+    { attachedDeposit: NEAR.parse('0.01') },
+  );
+  // alice was registered on stake, so only the storage of stats is deposited
+  const storageBalance: any = await contract.view('storage_balance_of', {
+    account_id: alice,
+  });
+  t.is(storageBalance.total, NEAR.parse('0.00205').toString());
+
+  // the cost basis starts from the current value of LiNEAR held
+  let stats: any = await contract.view('get_account_stats', {
+    account_id: alice,
+  });
+  t.is(stats.cost_basis, NEAR.parse('10').toString());
+  t.is(stats.total_deposited, '0');
+
+  await alice.call(
+    contract,
+    'deposit_and_stake',
+    {},
+    { attachedDeposit: NEAR.parse('10') },
+  );
+  // LiNEAR price goes up to 1.5 NEAR
+  await bob.call(contract, 'donate', {}, { attachedDeposit: NEAR.parse('15') });
+
+  await alice.call(
+    contract,
+    'ft_transfer',
+    { receiver_id: bob, amount: NEAR.parse('4') },
+    { attachedDeposit: ONE_YOCTO },
+  );
+
+  stats = await contract.view('get_account_stats', { account_id: alice });
+  t.is(stats.total_deposited, NEAR.parse('10').toString());
+  t.is(stats.total_received_shares, NEAR.parse('10').toString());
+  t.is(stats.total_sent_shares, NEAR.parse('4').toString());
+  t.is(stats.cost_basis, NEAR.parse('16').toString());
+  t.is(stats.realized_rewards, NEAR.parse('2').toString());
+  t.is(stats.staked_balance, NEAR.parse('24').toString());
+  t.is(stats.unrealized_rewards, NEAR.parse('8').toString());
+
+  // bob didn't enable stats
+  t.is(await contract.view('get_account_stats', { account_id: bob }), null);
+
+  await alice.call(
+    contract,
+    'disable_account_stats',
+    {},
+    { attachedDeposit: ONE_YOCTO },
+  );
+  t.is(await contract.view('get_account_stats', { account_id: alice }), null);
+});
@@ -0,0 +1,90 @@
+import { NEAR } from 'near-workspaces';
+import {
+  initWorkspace,
+  assertFailure,
+  registerFungibleTokenUser,
+  ONE_YOCTO,
+  test,
+} from './helper';
+
+test.beforeEach(async (t) => {
+  t.context = await initWorkspace();
+});
+
+test.afterEach(async (t) => {
+  await t.context.worker.tearDown();
+});
+
+test('approve and transfer LiNEAR from owner', async (t) => {
+  const { contract, alice, bob } = t.context;
+  await registerFungibleTokenUser(contract, bob);
+
+  await alice.call(
+    contract,
+    'deposit_and_stake',
+    {},
+    { attachedDeposit: NEAR.parse('10') },
+  );
+
+  // creating allowance requires storage deposit
+  await assertFailure(
+    t,
+    alice.call(
+      contract,
+      'ft_approve',
+      { spender_id: bob, amount: NEAR.parse('3') },
+      { attachedDeposit: ONE_YOCTO },
+    ),
+    'The attached deposit is less than the storage cost of the allowance',
+  );
+  await alice.call(
+    contract,
+    'ft_approve',
+    { spender_id: bob, amount: NEAR.parse('3') },
+    { attachedDeposit: NEAR.parse('0.01') },
+  );
+  t.is(
+    await contract.view('ft_allowance', { owner_id: alice, spender_id: bob }),
+    NEAR.parse('3').toString(),
+  );
+
+  // bob transfers 2 LiNEAR from alice to himself
+  await bob.call(
+    contract,
+    'ft_transfer_from',
+    { owner_id: alice, receiver_id: bob, amount: NEAR.parse('2') },
+    { attachedDeposit: ONE_YOCTO },
+  );
+  t.is(
+    await contract.view('ft_balance_of', { account_id: bob }),
+    NEAR.parse('2').toString(),
+  );
+  t.is(
+    await contract.view('ft_allowance', { owner_id: alice, spender_id: bob }),
+    NEAR.parse('1').toString(),
+  );
+
+  // cannot transfer more than allowance
+  await assertFailure(
+    t,
+    bob.call(
+      contract,
+      'ft_transfer_from',
+      { owner_id: alice, receiver_id: bob, amount: NEAR.parse('2') },
+      { attachedDeposit: ONE_YOCTO },
+    ),
+    "The spender doesn't have enough allowance",
+  );
+
+  // revoke allowance
+  await alice.call(
+    contract,
+    'ft_revoke',
+    { spender_id: bob },
+    { attachedDeposit: ONE_YOCTO },
+  );
+  t.is(
+    await contract.view('get_allowance', { owner_id: alice, spender_id: bob }),
+    null,
+  );
+});
@@ -0,0 +1,65 @@
+import { NEAR } from 'near-workspaces';
+import {
+  assertFailure,
+  epochHeightFastForward,
+  initWorkspace,
+  test,
+} from './helper';
+
+test.beforeEach(async (t) => {
+  t.context = await initWorkspace();
+});
+
+test.afterEach(async (t) => {
+  await t.context.worker.tearDown();
+});
+
+test('claim matured unstaked NEAR automatically', async (t) => {
+  const { contract, alice, bob } = t.context;
+  await alice.call(
+    contract,
+    'deposit_and_stake',
+    {},
+    { attachedDeposit: NEAR.parse('10') },
+  );
+
+  await assertFailure(
+    t,
+    alice.call(contract, 'process_claims', { limit: 0 }),
+    'Claim limit should be positive',
+  );
+  await alice.call(
+    contract,
+    'enable_auto_claim',
+    {},
+    { attachedDeposit: NEAR.parse('0.01') },
+  );
+  t.true(
+    await contract.view('is_auto_claim_enabled', { account_id: alice }),
+  );
+
+  await alice.call(contract, 'unstake', { amount: NEAR.parse('4') });
+  const account: any = await contract.view('get_account_details', {
+    account_id: alice,
+  });
+  t.is(
+    await contract.view('get_next_claim_epoch_height', {}),
+    account.unstaked_available_epoch_height,
+  );
+
+  // nothing is claimable before the unstaked NEAR matures
+  t.is(await bob.call(contract, 'process_claims', { limit: 10 }), 0);
+
+  await epochHeightFastForward(contract, bob);
+  const balance = await alice.availableBalance();
+  t.is(await bob.call(contract, 'process_claims', { limit: 10 }), 1);
+  t.is(
+    await contract.view('get_account_unstaked_balance', { account_id: alice }),
+    '0',
+  );
+  t.is(
+    (await alice.availableBalance()).sub(balance).toString(),
+    NEAR.parse('4').toString(),
+  );
+  t.is(await contract.view('get_next_claim_epoch_height', {}), null);
+});
@@ -0,0 +1,85 @@
+import { NEAR } from 'near-workspaces';
+import {
+  assertFailure,
+  epochHeightFastForward,
+  initWorkspace,
+  registerFungibleTokenUser,
+  ONE_YOCTO,
+  test,
+} from './helper';
+
+test.beforeEach(async (t) => {
+  t.context = await initWorkspace();
+});
+
+test.afterEach(async (t) => {
+  await t.context.worker.tearDown();
+});
+
+test('LiNEAR balance and supply at past epochs', async (t) => {
+  const { contract, alice, bob } = t.context;
+  await registerFungibleTokenUser(contract, bob);
+  await alice.call(
+    contract,
+    'deposit_and_stake',
+    {},
+    { attachedDeposit: NEAR.parse('10') },
+  );
+
+  await assertFailure(
+    t,
+    contract.view('ft_balance_of_at', { account_id: alice, epoch_height: 0 }),
+    'Balance checkpoints are not enabled for the account',
+  );
+  await alice.call(
+    contract,
+    'enable_balance_checkpoints',
+    {},
+    { attachedDeposit: NEAR.parse('0.1') },
+  );
+  t.true(
+    await contract.view('is_balance_checkpoints_enabled', {
+      account_id: alice,
+    }),
+  );
+
+  const snapshotEpochHeight: number = await contract.view(
+    'read_epoch_height',
+    {},
+  );
+  const supply = await contract.view('ft_total_supply', {});
+  // the snapshot of the current epoch could still change
+  await assertFailure(
+    t,
+    contract.view('ft_total_supply_at', { epoch_height: snapshotEpochHeight }),
+    'The epoch height should be before the current epoch',
+  );
+
+  await epochHeightFastForward(contract, alice);
+  await alice.call(
+    contract,
+    'ft_transfer',
+    { receiver_id: bob, amount: NEAR.parse('4') },
+    { attachedDeposit: ONE_YOCTO },
+  );
+  await alice.call(contract, 'unstake', { amount: NEAR.parse('1') });
+
+  // the snapshot is not affected by the changes afterwards
+  t.is(
+    await contract.view('ft_balance_of_at', {
+      account_id: alice,
+      epoch_height: snapshotEpochHeight,
+    }),
+    NEAR.parse('10').toString(),
+  );
+  t.is(
+    await contract.view('ft_total_supply_at', {
+      epoch_height: snapshotEpochHeight,
+    }),
+    supply,
+  );
+  t.is(
+    await contract.view('ft_balance_of', { account_id: alice }),
+    NEAR.parse('5').toString(),
+  );
+});
@@ -136,11 +136,20 @@ test('stake NEAR and transfer LiNEAR', async (t) => {
   );
 });
 
-// Ensure LiNEAR transfer work well with NEAR Wallet
-test('register LiNEAR with 0.00125Ⓝ storage balance', async (t) => {
+// The storage balance equals the measured storage usage of one account
+test('register LiNEAR with 0.00452Ⓝ storage balance', async (t) => {
   const { contract, alice, bob } = t.context;
-  await registerFungibleTokenUser(contract, alice, NEAR.parse('0.00125'));
-  await registerFungibleTokenUser(contract, bob, NEAR.parse('0.00125'));
+  const bounds: any = await contract.view('storage_balance_bounds', {});
+  t.is(bounds.min, NEAR.parse('0.00452').toString());
+  t.is(bounds.max, NEAR.parse('0.00452').toString());
+
+  await assertFailure(
+    t,
+    registerFungibleTokenUser(contract, alice, NEAR.parse('0.00125')),
+    'The attached deposit is less than the minimum storage balance',
+  );
+  await registerFungibleTokenUser(contract, alice, NEAR.parse('0.00452'));
+  await registerFungibleTokenUser(contract, bob, NEAR.parse('0.00452'));
 
   // deposit and stake 10 NEAR
   const stakeAmount = NEAR.parse('10');
@@ -164,26 +173,78 @@ test('register LiNEAR with 0.00125Ⓝ storage balance', async (t) => {
   );
 });
 
-test.skip('storage unregister', async (t) => {
-  const { contract, alice, bob } = t.context;
-  await registerFungibleTokenUser(contract, alice);
-  await registerFungibleTokenUser(contract, bob);
+test('top up storage debt', async (t) => {
+  const { contract, alice } = t.context;
+  const minBalance = NEAR.parse('0.00452');
 
+  // Alice is registered on stake without storage deposit
+  await alice.call(
+    contract,
+    'deposit_and_stake',
+    {},
+    { attachedDeposit: NEAR.parse('10') },
+  );
   t.is(
     ((await contract.view('storage_balance_of', { account_id: alice })) as any)
       .total,
-    NEAR.parse('0.00125').toString(),
+    '0',
+  );
+  t.is(
+    await contract.view('get_account_storage_debt', { account_id: alice }),
+    minBalance.toString(),
   );
+  const debt: any = await contract.view('get_storage_debt', {});
+  t.is(debt.storage_debt, minBalance.toString());
 
-  // Unregister Alice
+  // top up the storage debt, the extra deposit is refunded
   await alice.call(
     contract,
-    'storage_unregister',
+    'storage_deposit',
     {},
-    { attachedDeposit: ONE_YOCTO },
+    { attachedDeposit: NEAR.parse('0.01') },
+  );
+  t.deepEqual(
+    await contract.view('storage_balance_of', { account_id: alice }),
+    { total: minBalance.toString(), available: '0' },
+  );
+  t.is(
+    await contract.view('get_account_storage_debt', { account_id: alice }),
+    '0',
+  );
+});
+
+test('storage unregister', async (t) => {
+  const { contract, alice, bob } = t.context;
+  await registerFungibleTokenUser(contract, alice);
+  await registerFungibleTokenUser(contract, bob);
+
+  t.is(
+    ((await contract.view('storage_balance_of', { account_id: alice })) as any)
+      .total,
+    NEAR.parse('0.00452').toString(),
+  );
+
+  // Unregister Alice, the storage fee is refunded
+  t.true(
+    await alice.call(
+      contract,
+      'storage_unregister',
+      {},
+      { attachedDeposit: ONE_YOCTO },
+    ),
   );
   t.is(await contract.view('storage_balance_of', { account_id: alice }), null);
 
+  // Unregister again returns false
+  t.false(
+    await alice.call(
+      contract,
+      'storage_unregister',
+      {},
+      { attachedDeposit: ONE_YOCTO },
+    ),
+  );
+
   // Alice deposit and stake 10 NEAR
   await alice.call(
     contract,
@@ -193,6 +254,7 @@ test.skip('storage unregister', async (t) => {
   );
 
   // Force unregister Alice successfully.
+  // No storage fee is refunded since Alice was registered on stake.
   // The $LiNEAR owned by Alice are all burnt. Now $LiNEAR price increased to 2 $NEAR.
   await alice.call(
     contract,
@@ -270,8 +332,8 @@ test('ft_transfer_call LiNEAR', async (t) => {
   const { root, contract, alice } = t.context;
   // Deploy the decentralized exchange
   const dex = await deployDex(root);
-  await registerFungibleTokenUser(contract, alice, NEAR.parse('0.00125'));
-  await registerFungibleTokenUser(contract, dex, NEAR.parse('0.00125'));
+  await registerFungibleTokenUser(contract, alice);
+  await registerFungibleTokenUser(contract, dex);
 
   // deposit and stake 10 NEAR
   const stakeAmount = NEAR.parse('10');
@@ -327,3 +389,184 @@ test('ft_transfer_call LiNEAR', async (t) => {
     stakeAmount.sub(transferAmount1).toString(),
   );
 });
+
+test('unstake via ft_transfer_call to LiNEAR contract', async (t) => {
+  const { contract, alice, bob } = t.context;
+
+  const stakeAmount = NEAR.parse('10');
+  await alice.call(
+    contract,
+    'deposit_and_stake',
+    {},
+    { attachedDeposit: stakeAmount },
+  );
+
+  // invalid message, LiNEAR is refunded
+  await transferCall(contract, alice, contract, NEAR.parse('1'), 'unstake');
+  t.is(
+    await contract.view('ft_balance_of', { account_id: alice }),
+    stakeAmount.toString(),
+  );
+
+  // unstake 2 LiNEAR for the sender
+  await transferCall(
+    contract,
+    alice,
+    contract,
+    NEAR.parse('2'),
+    JSON.stringify({ action: 'unstake' }),
+  );
+  t.is(
+    await contract.view('ft_balance_of', { account_id: alice }),
+    NEAR.parse('8').toString(),
+  );
+  t.is(
+    await contract.view('get_account_unstaked_balance', { account_id: alice }),
+    NEAR.parse('2').toString(),
+  );
+
+  // unstake 1 LiNEAR for bob, who is registered
+  await transferCall(
+    contract,
+    alice,
+    contract,
+    NEAR.parse('1'),
+    JSON.stringify({ action: 'unstake', receiver_id: bob.accountId }),
+  );
+  t.is(
+    await contract.view('ft_balance_of', { account_id: alice }),
+    NEAR.parse('7').toString(),
+  );
+  t.is(
+    await contract.view('get_account_unstaked_balance', { account_id: bob }),
+    NEAR.parse('1').toString(),
+  );
+
+  t.is(await contract.view('ft_balance_of', { account_id: contract }), '0');
+});
+
+test('third party cannot delay withdrawal via ft_transfer_call unstake', async (t) => {
+  const { contract, alice, bob } = t.context;
+  await alice.call(
+    contract,
+    'deposit_and_stake',
+    {},
+    { attachedDeposit: NEAR.parse('10') },
+  );
+  await bob.call(
+    contract,
+    'deposit_and_stake',
+    {},
+    { attachedDeposit: NEAR.parse('10') },
+  );
+  await alice.call(contract, 'unstake', { amount: NEAR.parse('2') });
+
+  // bob tries to unstake for alice later, which is rejected and refunded
+  await epochHeightFastForward(contract, bob, 2);
+  await transferCall(
+    contract,
+    bob,
+    contract,
+    NEAR.parse('1'),
+    JSON.stringify({ action: 'unstake', receiver_id: alice.accountId }),
+  );
+  t.is(
+    await contract.view('ft_balance_of', { account_id: bob }),
+    NEAR.parse('10').toString(),
+  );
+  t.is(
+    await contract.view('get_account_unstaked_balance', { account_id: alice }),
+    NEAR.parse('2').toString(),
+  );
+
+  // alice withdraws on schedule
+  await epochHeightFastForward(contract, alice, 2);
+  await alice.call(contract, 'withdraw', { amount: NEAR.parse('2') });
+  t.is(
+    await contract.view('get_account_unstaked_balance', { account_id: alice }),
+    '0',
+  );
+});
+
+test('deposit_and_stake_call forwards LiNEAR to receiver', async (t) => {
+  const { root, contract, alice } = t.context;
+  const dex = await deployDex(root);
+  await registerFungibleTokenUser(contract, dex);
+
+  const depositAndStakeCall = (amount: NEAR, msg: string) =>
+    alice.call(
+      contract,
+      'deposit_and_stake_call',
+      { receiver_id: dex, msg },
+      { gas: Gas.parse('75 Tgas'), attachedDeposit: amount },
+    );
+
+  // not enough gas
+  await assertFailure(
+    t,
+    alice.call(
+      contract,
+      'deposit_and_stake_call',
+      { receiver_id: dex, msg: 'pass' },
+      { gas: Gas.parse('30 Tgas'), attachedDeposit: NEAR.parse('1') },
+    ),
+    'No enough gas',
+  );
+
+  // `ft_on_transfer()` passed, the minted LiNEAR is kept by dex
+  await depositAndStakeCall(NEAR.parse('1'), 'pass');
+  t.is(
+    await contract.view('ft_balance_of', { account_id: dex }),
+    NEAR.parse('1').toString(),
+  );
+  t.is(await contract.view('ft_balance_of', { account_id: alice }), '0');
+
+  // `ft_on_transfer()` refunded, the minted LiNEAR is refunded to alice
+  await depositAndStakeCall(NEAR.parse('2'), 'refund');
+  t.is(
+    await contract.view('ft_balance_of', { account_id: dex }),
+    NEAR.parse('1').toString(),
+  );
+  t.is(
+    await contract.view('ft_balance_of', { account_id: alice }),
+    NEAR.parse('2').toString(),
+  );
+});
+
+test('burn LiNEAR to pool', async (t) => {
+  const { contract, alice } = t.context;
+  await alice.call(
+    contract,
+    'deposit_and_stake',
+    {},
+    { attachedDeposit: NEAR.parse('20') },
+  );
+
+  // burn 5 LiNEAR directly, 25 LiNEAR left for 30 NEAR
+  await alice.call(
+    contract,
+    'burn_to_pool',
+    { amount: NEAR.parse('5') },
+    { attachedDeposit: ONE_YOCTO },
+  );
+  t.is(await contract.view('ft_total_supply'), NEAR.parse('25').toString());
+  t.is(
+    await contract.view('get_total_staked_balance'),
+    NEAR.parse('30').toString(),
+  );
+
+  // burn 5 LiNEAR via ft_transfer_call, 20 LiNEAR left for 30 NEAR
+  await transferCall(
+    contract,
+    alice,
+    contract,
+    NEAR.parse('5'),
+    JSON.stringify({ action: 'burn_to_pool' }),
+  );
+  t.is(await contract.view('ft_total_supply'), NEAR.parse('20').toString());
+  t.is(await contract.view('ft_price'), NEAR.parse('1.5').toString());
+  t.is(
+    await contract.view('ft_balance_of', { account_id: alice }),
+    NEAR.parse('10').toString(),
+  );
+});
@@ -0,0 +1,74 @@
+import { NEAR } from 'near-workspaces';
+import {
+  assertFailure,
+  epochStake,
+  getSummary,
+  initWorkspace,
+  test,
+} from './helper';
+
+test.beforeEach(async (t) => {
+  t.context = await initWorkspace();
+});
+
+test.afterEach(async (t) => {
+  await t.context.worker.tearDown();
+});
+
+test('only owner can set liquidity reserve', async (t) => {
+  const { contract, owner, alice } = t.context;
+  await assertFailure(
+    t,
+    alice.call(contract, 'set_liquidity_reserve_bps', { bps: 1000 }),
+    'Only owner can perform this action',
+  );
+  await assertFailure(
+    t,
+    owner.call(contract, 'set_liquidity_reserve_bps', { bps: 10001 }),
+    'The liquidity reserve bps should not exceed 10000',
+  );
+});
+
+test('unstake served by liquidity reserve', async (t) => {
+  const { contract, owner, alice } = t.context;
+  await owner.call(contract, 'set_liquidity_reserve_bps', { bps: 2000 });
+  await alice.call(
+    contract,
+    'deposit_and_stake',
+    {},
+    { attachedDeposit: NEAR.parse('10') },
+  );
+
+  // the reserve is filled from the amount to stake in epoch cleanup
+  await epochStake(owner, contract);
+  let summary = await getSummary(contract);
+  t.is(summary.liquidity_reserve_amount, NEAR.parse('4').toString());
+  t.is(summary.liquidity_reserve_target, NEAR.parse('4').toString());
+  t.is(summary.liquidity_reserve_bps, 2000);
+
+  const preview: any = await contract.view('preview_unstake', {
+    amount: NEAR.parse('3'),
+    account_id: alice,
+  });
+  t.true(preview.from_liquidity_reserve);
+  await alice.call(contract, 'unstake', { amount: NEAR.parse('3') });
+
+  // withdraw without waiting for validators
+  await alice.call(contract, 'withdraw_all', {});
+  t.is(
+    await contract.view('get_account_unstaked_balance', { account_id: alice }),
+    '0',
+  );
+
+  summary = await getSummary(contract);
+  t.is(summary.liquidity_reserve_amount, NEAR.parse('1').toString());
+  t.is(summary.epoch_requested_unstake_amount, '0');
+
+  // the unstake exceeding the reserve waits for validators
+  await alice.call(contract, 'unstake', { amount: NEAR.parse('2') });
+  await assertFailure(
+    t,
+    alice.call(contract, 'withdraw_all', {}),
+    'The unstaked balance is not yet available due to unstaking delay',
+  );
+});
@@ -0,0 +1,114 @@
+import { NEAR } from 'near-workspaces';
+import {
+  assertFailure,
+  initWorkspace,
+  registerFungibleTokenUser,
+  ONE_YOCTO,
+  test,
+} from './helper';
+
+const ONE_YEAR = 365 * 24 * 3600 * 1e9;
+
+test.beforeEach(async (t) => {
+  t.context = await initWorkspace();
+});
+
+test.afterEach(async (t) => {
+  await t.context.worker.tearDown();
+});
+
+test('lock schedule blocks transfer and unstake of locked LiNEAR', async (t) => {
+  const { contract, owner, alice, bob } = t.context;
+  await registerFungibleTokenUser(contract, bob);
+  await owner.call(
+    contract,
+    'deposit_and_stake',
+    {},
+    { attachedDeposit: NEAR.parse('20') },
+  );
+
+  const now = Date.now() * 1e6;
+  await assertFailure(
+    t,
+    alice.call(
+      contract,
+      'create_lock_schedule',
+      {
+        account_id: alice,
+        amount: NEAR.parse('10'),
+        start_timestamp: now.toString(),
+        cliff_timestamp: (now + ONE_YEAR).toString(),
+        end_timestamp: (now + 4 * ONE_YEAR).toString(),
+      },
+      { attachedDeposit: ONE_YOCTO },
+    ),
+    'Only owner can perform this action',
+  );
+  await owner.call(
+    contract,
+    'create_lock_schedule',
+    {
+      account_id: alice,
+      amount: NEAR.parse('10'),
+      start_timestamp: now.toString(),
+      cliff_timestamp: (now + ONE_YEAR).toString(),
+      end_timestamp: (now + 4 * ONE_YEAR).toString(),
+    },
+    { attachedDeposit: ONE_YOCTO },
+  );
+
+  const schedule: any = await contract.view('get_lock_schedule', {
+    account_id: alice,
+  });
+  t.is(schedule.locked_shares, NEAR.parse('10').toString());
+  t.is(schedule.transferable_shares, '0');
+  // the staking pool view is not affected
+  const account: any = await contract.view('get_account', {
+    account_id: alice,
+  });
+  t.is(account.staked_balance, NEAR.parse('10').toString());
+
+  await assertFailure(
+    t,
+    alice.call(
+      contract,
+      'ft_transfer',
+      { receiver_id: bob, amount: NEAR.parse('1') },
+      { attachedDeposit: ONE_YOCTO },
+    ),
+    'The LiNEAR balance is locked by the lock schedule',
+  );
+  await assertFailure(
+    t,
+    alice.call(contract, 'unstake', { amount: NEAR.parse('1') }),
+    'The LiNEAR balance is locked by the lock schedule',
+  );
+
+  // the LiNEAR received besides the schedule is transferable
+  await alice.call(
+    contract,
+    'deposit_and_stake',
+    {},
+    { attachedDeposit: NEAR.parse('2') },
+  );
+  await alice.call(
+    contract,
+    'ft_transfer',
+    { receiver_id: bob, amount: NEAR.parse('2') },
+    { attachedDeposit: ONE_YOCTO },
+  );
+
+  // the locked LiNEAR returns to the owner on termination
+  await owner.call(
+    contract,
+    'terminate_lock_schedule',
+    { account_id: alice },
+    { attachedDeposit: ONE_YOCTO },
+  );
+  t.is(await contract.view('get_lock_schedule', { account_id: alice }), null);
+  t.is(await contract.view('ft_balance_of', { account_id: alice }), '0');
+  t.is(
+    await contract.view('ft_balance_of', { account_id: owner }),
+    NEAR.parse('20').toString(),
+  );
+});
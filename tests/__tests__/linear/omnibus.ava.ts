@@ -0,0 +1,145 @@
+import { NEAR } from 'near-workspaces';
+import {
+  initWorkspace,
+  assertFailure,
+  registerFungibleTokenUser,
+  ONE_YOCTO,
+  test,
+} from './helper';
+
+test.beforeEach(async (t) => {
+  t.context = await initWorkspace();
+});
+
+test.afterEach(async (t) => {
+  await t.context.worker.tearDown();
+});
+
+test('stake and transfer via sub-accounts of omnibus account', async (t) => {
+  const { contract, alice, bob } = t.context;
+  await registerFungibleTokenUser(contract, alice);
+  await alice.call(
+    contract,
+    'enable_sub_accounts',
+    {},
+    { attachedDeposit: NEAR.parse('0.01') },
+  );
+
+  // omnibus account can only be operated via sub-accounts
+  await assertFailure(
+    t,
+    alice.call(
+      contract,
+      'deposit_and_stake',
+      {},
+      { attachedDeposit: NEAR.parse('1') },
+    ),
+    'The account is an omnibus account, which can only be operated via sub-accounts',
+  );
+
+  // the storage of a new sub-account is deducted from the deposit
+  await alice.call(
+    contract,
+    'sub_account_deposit_and_stake',
+    { sub_account_id: 'customer-1' },
+    { attachedDeposit: NEAR.parse('10.01') },
+  );
+  await alice.call(
+    contract,
+    'sub_account_transfer',
+    {
+      from_sub_account_id: 'customer-1',
+      to_sub_account_id: 'customer-2',
+      amount: NEAR.parse('4').toString(),
+    },
+    { attachedDeposit: NEAR.parse('0.01') },
+  );
+  await alice.call(contract, 'sub_account_unstake', {
+    sub_account_id: 'customer-2',
+    amount: NEAR.parse('1').toString(),
+  });
+
+  const customer1: any = await contract.view('get_sub_account', {
+    account_id: alice,
+    sub_account_id: 'customer-1',
+  });
+  const customer2: any = await contract.view('get_sub_account', {
+    account_id: alice,
+    sub_account_id: 'customer-2',
+  });
+  t.is(customer2.stake_shares, NEAR.parse('3').toString());
+  t.is(customer2.unstaked_balance, NEAR.parse('1').toString());
+
+  const omnibus: any = await contract.view('get_omnibus_account', {
+    account_id: alice,
+  });
+  t.is(omnibus.sub_accounts_num, 2);
+  t.true(omnibus.consistent);
+  t.is(
+    await contract.view('ft_balance_of', { account_id: alice }),
+    NEAR.from(customer1.stake_shares)
+      .add(NEAR.from(customer2.stake_shares))
+      .toString(),
+  );
+
+  // LiNEAR sent to omnibus account should name the sub-account in the memo
+  await bob.call(
+    contract,
+    'deposit_and_stake',
+    {},
+    { attachedDeposit: NEAR.parse('2') },
+  );
+  await assertFailure(
+    t,
+    bob.call(
+      contract,
+      'ft_transfer',
+      { receiver_id: alice, amount: NEAR.parse('1') },
+      { attachedDeposit: ONE_YOCTO },
+    ),
+    'Transfers to an omnibus account should name the receiver sub-account in the memo',
+  );
+
+  // the storage of a sub-account created by a transfer is paid from the
+  // storage balance of omnibus account
+  await alice.call(
+    contract,
+    'sub_account_storage_deposit',
+    {},
+    { attachedDeposit: NEAR.parse('0.01') },
+  );
+  await bob.call(
+    contract,
+    'ft_transfer',
+    {
+      receiver_id: alice,
+      amount: NEAR.parse('1').toString(),
+      memo: 'customer-3',
+    },
+    { attachedDeposit: ONE_YOCTO },
+  );
+  const customer3: any = await contract.view('get_sub_account', {
+    account_id: alice,
+    sub_account_id: 'customer-3',
+  });
+  t.is(customer3.stake_shares, NEAR.parse('1').toString());
+
+  await alice.call(
+    contract,
+    'ft_transfer_from_sub_account',
+    {
+      sub_account_id: 'customer-3',
+      receiver_id: bob,
+      amount: NEAR.parse('1').toString(),
+    },
+    { attachedDeposit: ONE_YOCTO },
+  );
+  t.is(
+    await contract.view('ft_balance_of', { account_id: bob }),
+    NEAR.parse('2').toString(),
+  );
+  t.true(
+    ((await contract.view('get_omnibus_account', { account_id: alice })) as any)
+      .consistent,
+  );
+});
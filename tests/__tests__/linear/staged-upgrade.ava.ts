@@ -0,0 +1,129 @@
+import bs58 from 'bs58';
+import { createHash } from 'crypto';
+import { readFileSync } from 'fs';
+import { Gas } from 'near-units';
+import { NEAR, NearAccount } from 'near-workspaces';
+import { assertFailure, initWorkspace, test } from './helper';
+
+const code = readFileSync('compiled-contracts/linear.wasm');
+const codeHash = bs58.encode(createHash('sha256').update(code).digest());
+const storageDeposit = NEAR.parse('0.00001').muln(code.length);
+
+function stageUpgrade(contract: NearAccount, signer: NearAccount) {
+  return signer.call(contract, 'stage_upgrade', code, {
+    gas: Gas.parse('300 Tgas'),
+    // storage of the staged code
+    attachedDeposit: storageDeposit,
+  });
+}
+
+function applyUpgrade(contract: NearAccount, signer: NearAccount) {
+  return signer.call(
+    contract,
+    'apply_upgrade',
+    {},
+    {
+      gas: Gas.parse('300 Tgas'),
+    },
+  );
+}
+
+test.beforeEach(async (t) => {
+  t.context = await initWorkspace();
+});
+
+test.afterEach(async (t) => {
+  await t.context.worker.tearDown();
+});
+
+test('non-owner cannot stage, apply or cancel upgrade', async (t) => {
+  const { contract, owner, alice } = t.context;
+
+  await assertFailure(
+    t,
+    stageUpgrade(contract, alice),
+    'Only owner can perform this action',
+  );
+
+  await stageUpgrade(contract, owner);
+
+  await assertFailure(
+    t,
+    applyUpgrade(contract, alice),
+    'Only owner can perform this action',
+  );
+  await assertFailure(
+    t,
+    alice.call(contract, 'cancel_upgrade', {}),
+    'Only owner can perform this action',
+  );
+  // the staged code is only removed by the contract itself once the upgrade succeeds
+  await assertFailure(
+    t,
+    alice.call(contract, 'complete_upgrade', {
+      code_hash: ((await contract.view('get_staged_upgrade')) as any).code_hash,
+    }),
+    'Method complete_upgrade is private',
+  );
+});
+
+test('stage upgrade and review code hash', async (t) => {
+  const { contract, owner } = t.context;
+
+  t.is(await contract.view('get_staged_upgrade'), null);
+
+  await stageUpgrade(contract, owner);
+
+  const staged: any = await contract.view('get_staged_upgrade');
+  t.is(staged.code_hash, codeHash);
+  t.is(staged.code_size, code.length);
+  t.is(staged.storage_deposit, storageDeposit.toString());
+  t.is(staged.staged_epoch, 10);
+  t.is(staged.activation_epoch, 14);
+
+  // cannot stage another upgrade before cancel
+  await assertFailure(
+    t,
+    stageUpgrade(contract, owner),
+    'There is already a staged upgrade',
+  );
+
+  // cannot apply before activation epoch
+  await owner.call(contract, 'set_epoch_height', { epoch: 13 });
+  await assertFailure(
+    t,
+    applyUpgrade(contract, owner),
+    'The staged upgrade is not activated yet',
+  );
+
+  // cancel and stage again
+  await owner.call(contract, 'cancel_upgrade', {});
+  t.is(await contract.view('get_staged_upgrade'), null);
+  await assertFailure(
+    t,
+    applyUpgrade(contract, owner),
+    'There is no staged upgrade',
+  );
+
+  await stageUpgrade(contract, owner);
+  const restaged: any = await contract.view('get_staged_upgrade');
+  t.is(restaged.code_hash, codeHash);
+  t.is(restaged.activation_epoch, 17);
+});
+
+// `migrate()` varies between versions, so applying the staged code only works when
+// the contract is deployed with the previous version. Keep this test case to make
+// it easier to be reused in future upgrade.
+test.skip('apply staged upgrade after activation epoch', async (t) => {
+  const { contract, owner } = t.context;
+
+  await stageUpgrade(contract, owner);
+  await owner.call(contract, 'set_epoch_height', { epoch: 14 });
+  await applyUpgrade(contract, owner);
+
+  t.is(await contract.view('get_staged_upgrade'), null);
+  t.is((await contract.accountView()).code_hash, codeHash);
+
+  const report: any = await contract.view('check_invariants', {});
+  t.true(report.valid);
+});
@@ -0,0 +1,146 @@
+import { NEAR } from 'near-workspaces';
+import { initWorkspace, assertFailure, test } from './helper';
+
+test.beforeEach(async (t) => {
+  t.context = await initWorkspace();
+});
+
+test.afterEach(async (t) => {
+  await t.context.worker.tearDown();
+});
+
+test('only owner can set stake limits', async (t) => {
+  const { contract, alice } = t.context;
+  await assertFailure(
+    t,
+    alice.call(contract, 'set_stake_limits', {
+      max_total_staked_near_amount: NEAR.parse('100'),
+    }),
+    'Only owner can perform this action',
+  );
+
+  // no limits by default
+  const limits: any = await contract.view('get_stake_limits', {});
+  t.is(limits.max_total_staked_near_amount, null);
+  t.is(limits.remaining_total_stake_amount, null);
+  t.is(limits.remaining_epoch_stake_amount, null);
+  t.is(limits.remaining_epoch_unstake_amount, null);
+});
+
+test('stake and unstake within limits', async (t) => {
+  const { contract, owner, alice, bob } = t.context;
+  // the contract holds 10 NEAR of initial stake, requested in the first epoch
+  await owner.call(contract, 'set_stake_limits', {
+    max_total_staked_near_amount: NEAR.parse('40'),
+    max_epoch_stake_amount: NEAR.parse('35'),
+    max_epoch_unstake_amount: NEAR.parse('5'),
+  });
+
+  await alice.call(
+    contract,
+    'deposit_and_stake',
+    {},
+    { attachedDeposit: NEAR.parse('20') },
+  );
+  let limits: any = await contract.view('get_stake_limits', {});
+  t.is(limits.remaining_total_stake_amount, NEAR.parse('10').toString());
+  t.is(limits.remaining_epoch_stake_amount, NEAR.parse('5').toString());
+
+  // exceeds the per-epoch limit
+  await assertFailure(
+    t,
+    bob.call(
+      contract,
+      'deposit_and_stake',
+      {},
+      { attachedDeposit: NEAR.parse('8') },
+    ),
+    'The staked amount in this epoch would exceed the maximum',
+  );
+
+  // exceeds the total limit
+  await owner.call(contract, 'set_stake_limits', {
+    max_total_staked_near_amount: NEAR.parse('40'),
+  });
+  await assertFailure(
+    t,
+    bob.call(
+      contract,
+      'deposit_and_stake',
+      {},
+      { attachedDeposit: NEAR.parse('11') },
+    ),
+    'The total staked amount would exceed the maximum',
+  );
+  await bob.call(
+    contract,
+    'deposit_and_stake',
+    {},
+    { attachedDeposit: NEAR.parse('10') },
+  );
+  limits = await contract.view('get_stake_limits', {});
+  t.is(limits.remaining_total_stake_amount, '0');
+
+  // unstake is limited per epoch
+  await owner.call(contract, 'set_stake_limits', {
+    max_epoch_unstake_amount: NEAR.parse('5'),
+  });
+  await assertFailure(
+    t,
+    alice.call(contract, 'unstake', { amount: NEAR.parse('6') }),
+    'The unstaked amount in this epoch would exceed the maximum',
+  );
+  await alice.call(contract, 'unstake', { amount: NEAR.parse('5') });
+  limits = await contract.view('get_stake_limits', {});
+  t.is(limits.remaining_epoch_unstake_amount, '0');
+});
+
+test('minimum deposit, stake and unstake amounts', async (t) => {
+  const { contract, owner, alice } = t.context;
+  await owner.call(contract, 'set_min_amounts', {
+    min_deposit_amount: NEAR.parse('1'),
+    min_stake_amount: NEAR.parse('2'),
+    min_unstake_amount: NEAR.parse('5'),
+  });
+  t.deepEqual(await contract.view('get_min_amounts', {}), {
+    min_deposit_amount: NEAR.parse('1').toString(),
+    min_stake_amount: NEAR.parse('2').toString(),
+    min_unstake_amount: NEAR.parse('5').toString(),
+  });
+
+  await assertFailure(
+    t,
+    alice.call(contract, 'deposit', {}, { attachedDeposit: NEAR.parse('0.5') }),
+    'The deposit amount is less than the minimum',
+  );
+  await alice.call(
+    contract,
+    'deposit',
+    {},
+    { attachedDeposit: NEAR.parse('1') },
+  );
+  await assertFailure(
+    t,
+    alice.call(contract, 'stake', { amount: NEAR.parse('1') }),
+    'The stake amount is less than the minimum',
+  );
+
+  await alice.call(
+    contract,
+    'deposit_and_stake',
+    {},
+    { attachedDeposit: NEAR.parse('3') },
+  );
+  await assertFailure(
+    t,
+    alice.call(contract, 'unstake', { amount: NEAR.parse('1') }),
+    'The unstake amount is less than the minimum',
+  );
+
+  // unstaking the full balance is exempted
+  await alice.call(contract, 'unstake_all', {});
+  t.is(
+    await contract.view('get_account_staked_balance', { account_id: alice }),
+    '0',
+  );
+});
@@ -117,6 +117,43 @@ test('deposit and stake', async (t) => {
   );
 });
 
+test('deposit and stake for another account', async (t) => {
+  const { contract, alice, bob } = t.context;
+
+  // alice deposits for bob, who is registered automatically
+  const depositAmount = NEAR.parse('2');
+  await alice.call(
+    contract,
+    'deposit_for',
+    { receiver_id: bob },
+    { attachedDeposit: depositAmount },
+  );
+  t.is(
+    await contract.view('get_account_unstaked_balance', { account_id: bob }),
+    depositAmount.toString(),
+  );
+
+  // alice stakes for bob
+  const stakeAmount = NEAR.parse('10');
+  const receivedLinearAmount = await alice.call<string>(
+    contract,
+    'deposit_and_stake_for',
+    { receiver_id: bob },
+    { attachedDeposit: stakeAmount },
+  );
+  t.is(stakeAmount.toString(), receivedLinearAmount.toString());
+
+  t.is(
+    await contract.view('ft_balance_of', { account_id: bob }),
+    stakeAmount.toString(),
+  );
+  t.is(await contract.view('ft_balance_of', { account_id: alice }), '0');
+  t.is(
+    await contract.view('get_account_unstaked_balance', { account_id: bob }),
+    depositAmount.toString(),
+  );
+});
+
 test('unstake', async (t) => {
   const { contract, alice } = t.context;
   // deposit
@@ -221,6 +258,40 @@ test('unstake and withdraw', async (t) => {
   );
 });
 
+test('withdraw to another account', async (t) => {
+  const { contract, alice, bob } = t.context;
+  const deposit = NEAR.parse('10');
+  await alice.call(contract, 'deposit', {}, { attachedDeposit: deposit });
+
+  // withdraw 2 NEAR to bob
+  const bobBalance = await bob.availableBalance();
+  const withdrawAmount = NEAR.parse('2');
+  await alice.call(contract, 'withdraw_to', {
+    receiver_id: bob,
+    amount: withdrawAmount.toString(),
+  });
+  t.is(
+    (await bob.availableBalance()).sub(bobBalance).toString(),
+    withdrawAmount.toString(),
+  );
+  t.is(
+    await contract.view('get_account_unstaked_balance', { account_id: alice }),
+    deposit.sub(withdrawAmount).toString(),
+  );
+
+  // withdraw to a non-existent account, the balance is restored
+  await alice.call(
+    contract,
+    'withdraw_all_to',
+    { receiver_id: 'non-existent.test.near' },
+    { gas: Gas.parse('50 Tgas') },
+  );
+  t.is(
+    await contract.view('get_account_unstaked_balance', { account_id: alice }),
+    deposit.sub(withdrawAmount).toString(),
+  );
+});
+
 test('late unstake and withdraw', async (t) => {
   const { contract, alice } = t.context;
   // deposit
@@ -261,3 +332,75 @@ test('late unstake and withdraw', async (t) => {
     amount: unstakeAmount.toString(),
   });
 });
+
+test('donate NEAR to raise LiNEAR price', async (t) => {
+  const { contract, alice, bob } = t.context;
+  await alice.call(
+    contract,
+    'deposit_and_stake',
+    {},
+    { attachedDeposit: NEAR.parse('10') },
+  );
+
+  // donation should be at least 1 NEAR
+  await assertFailure(
+    t,
+    bob.call(contract, 'donate', {}, { attachedDeposit: NEAR.parse('0.5') }),
+    'The donation amount is less than the minimum',
+  );
+
+  // 10 NEAR donated to 20 LiNEAR (including the initial 10 LiNEAR)
+  await bob.call(contract, 'donate', {}, { attachedDeposit: NEAR.parse('10') });
+  t.is(await contract.view('ft_total_supply'), NEAR.parse('20').toString());
+  t.is(
+    await contract.view('get_total_staked_balance'),
+    NEAR.parse('30').toString(),
+  );
+  t.is(await contract.view('ft_price'), NEAR.parse('1.5').toString());
+  t.is(
+    await contract.view('get_account_staked_balance', { account_id: alice }),
+    NEAR.parse('15').toString(),
+  );
+});
+
+test('preview stake, unstake and withdraw', async (t) => {
+  const { contract, alice, bob } = t.context;
+  // raise LiNEAR price to 1.5 NEAR
+  await bob.call(contract, 'donate', {}, { attachedDeposit: NEAR.parse('5') });
+
+  const stakePreview: any = await contract.view('preview_stake', {
+    amount: NEAR.parse('3').toString(),
+  });
+  t.is(stakePreview.minted_stake_shares, NEAR.parse('2').toString());
+  t.is(stakePreview.charged_amount, NEAR.parse('3').toString());
+
+  const minted = await alice.call<string>(
+    contract,
+    'deposit_and_stake',
+    {},
+    { attachedDeposit: NEAR.parse('3') },
+  );
+  t.is(minted, stakePreview.minted_stake_shares);
+
+  const unstakePreview: any = await contract.view('preview_unstake_shares', {
+    shares: NEAR.parse('2').toString(),
+  });
+  t.is(unstakePreview.burnt_stake_shares, NEAR.parse('2').toString());
+  t.is(unstakePreview.received_amount, NEAR.parse('3').toString());
+
+  await alice.call(contract, 'unstake_all', {});
+  const account: any = await contract.view('get_account_details', {
+    account_id: alice,
+  });
+  t.is(account.unstaked_balance, unstakePreview.received_amount);
+  t.is(
+    account.unstaked_available_epoch_height,
+    unstakePreview.unstaked_available_epoch_height,
+  );
+
+  const withdrawPreview: any = await contract.view('preview_withdraw', {
+    account_id: alice,
+  });
+  t.is(withdrawPreview.unstaked_balance, NEAR.parse('3').toString());
+  t.is(withdrawPreview.withdrawable_amount, '0');
+});
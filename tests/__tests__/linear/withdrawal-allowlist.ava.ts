@@ -0,0 +1,122 @@
+import { NEAR } from 'near-workspaces';
+import {
+  assertFailure,
+  epochHeightFastForward,
+  initWorkspace,
+  registerFungibleTokenUser,
+  ONE_YOCTO,
+  test,
+} from './helper';
+
+test.beforeEach(async (t) => {
+  t.context = await initWorkspace();
+});
+
+test.afterEach(async (t) => {
+  await t.context.worker.tearDown();
+});
+
+test('withdrawal allowlist restricts receivers', async (t) => {
+  const { contract, alice, bob, carol } = t.context;
+  await registerFungibleTokenUser(contract, bob);
+  await registerFungibleTokenUser(contract, carol);
+  await alice.call(
+    contract,
+    'deposit_and_stake',
+    {},
+    { attachedDeposit: NEAR.parse('10') },
+  );
+  await alice.call(
+    contract,
+    'enable_withdrawal_allowlist',
+    { allowlist: [alice.accountId, bob.accountId] },
+    { attachedDeposit: NEAR.parse('0.1') },
+  );
+
+  await alice.call(
+    contract,
+    'ft_transfer',
+    { receiver_id: bob, amount: NEAR.parse('1') },
+    { attachedDeposit: ONE_YOCTO },
+  );
+  await assertFailure(
+    t,
+    alice.call(
+      contract,
+      'ft_transfer',
+      { receiver_id: carol, amount: NEAR.parse('1') },
+      { attachedDeposit: ONE_YOCTO },
+    ),
+    'The receiver is not in the withdrawal allowlist of the account',
+  );
+
+  // the unstaked NEAR could only be withdrawn to the allowlist
+  await alice.call(contract, 'unstake', { amount: NEAR.parse('2') });
+  await epochHeightFastForward(contract, alice);
+  await assertFailure(
+    t,
+    alice.call(contract, 'withdraw_to', {
+      receiver_id: carol,
+      amount: NEAR.parse('1'),
+    }),
+    'The receiver is not in the withdrawal allowlist of the account',
+  );
+  await alice.call(contract, 'withdraw', { amount: NEAR.parse('1') });
+});
+
+test('withdrawal allowlist changes are delayed', async (t) => {
+  const { contract, alice, carol } = t.context;
+  await alice.call(
+    contract,
+    'enable_withdrawal_allowlist',
+    { allowlist: [alice.accountId] },
+    { attachedDeposit: NEAR.parse('0.1') },
+  );
+
+  const effectiveEpochHeight: number = await alice.call(
+    contract,
+    'update_withdrawal_allowlist',
+    { allowlist: [carol.accountId] },
+    { attachedDeposit: ONE_YOCTO },
+  );
+  let view: any = await contract.view('get_withdrawal_allowlist', {
+    account_id: alice,
+  });
+  t.deepEqual(view.allowlist, [alice.accountId]);
+  t.deepEqual(view.pending_allowlist, [carol.accountId]);
+  t.is(view.pending_effective_epoch_height, effectiveEpochHeight);
+
+  // the owner of the key notices the change and cancels it
+  await alice.call(
+    contract,
+    'cancel_withdrawal_allowlist_change',
+    {},
+    { attachedDeposit: ONE_YOCTO },
+  );
+  view = await contract.view('get_withdrawal_allowlist', {
+    account_id: alice,
+  });
+  t.is(view.pending_allowlist, null);
+  t.is(view.pending_effective_epoch_height, null);
+
+  await alice.call(
+    contract,
+    'disable_withdrawal_allowlist',
+    {},
+    { attachedDeposit: ONE_YOCTO },
+  );
+  await assertFailure(
+    t,
+    carol.call(contract, 'apply_withdrawal_allowlist_change', {
+      account_id: alice,
+    }),
+    'The change of the withdrawal allowlist is not effective yet',
+  );
+  t.true(
+    (
+      (await contract.view('get_withdrawal_allowlist', {
+        account_id: alice,
+      })) as any
+    ).pending_disable,
+  );
+});
@@ -0,0 +1,72 @@
+import { Gas, NEAR, NearAccount } from 'near-workspaces';
+import { createAndDeploy, initWorkspace, ONE_YOCTO, test } from './helper';
+
+async function transferWnear(
+  wnear: NearAccount,
+  sender: NearAccount,
+  receiver: NearAccount,
+  amount: NEAR,
+) {
+  await sender.call(
+    wnear,
+    'ft_transfer_call',
+    { receiver_id: receiver, amount, msg: '' },
+    { gas: Gas.parse('150 Tgas'), attachedDeposit: ONE_YOCTO },
+  );
+}
+
+test.beforeEach(async (t) => {
+  t.context = await initWorkspace();
+});
+
+test.afterEach(async (t) => {
+  await t.context.worker.tearDown();
+});
+
+test('deposit wNEAR and stake', async (t) => {
+  const { root, contract, owner, alice } = t.context;
+  const wnear = await createAndDeploy(
+    root,
+    'wnear',
+    'compiled-contracts/mock_fungible_token.wasm',
+    {
+      methodName: 'new',
+      args: {},
+    },
+  );
+  await owner.call(
+    wnear,
+    'storage_deposit',
+    { account_id: contract },
+    { attachedDeposit: NEAR.parse('0.1') },
+  );
+  await alice.call(
+    wnear,
+    'near_deposit',
+    {},
+    { attachedDeposit: NEAR.parse('10') },
+  );
+
+  // wNEAR is not configured yet, the transfer is refunded
+  await transferWnear(wnear, alice, contract, NEAR.parse('2'));
+  t.is(
+    await wnear.view('ft_balance_of', { account_id: alice }),
+    NEAR.parse('10').toString(),
+  );
+  t.is(await contract.view('ft_balance_of', { account_id: alice }), '0');
+
+  await owner.call(contract, 'set_wnear_account_id', { account_id: wnear });
+  t.is(await contract.view('get_wnear_account_id'), wnear.accountId);
+
+  // transfer 2 wNEAR to stake
+  await transferWnear(wnear, alice, contract, NEAR.parse('2'));
+  t.is(
+    await wnear.view('ft_balance_of', { account_id: alice }),
+    NEAR.parse('8').toString(),
+  );
+  t.is(await wnear.view('ft_balance_of', { account_id: contract }), '0');
+  t.is(
+    await contract.view('ft_balance_of', { account_id: alice }),
+    NEAR.parse('2').toString(),
+  );
+});